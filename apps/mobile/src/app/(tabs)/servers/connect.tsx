@@ -106,7 +106,7 @@ export default function ConnectScreen() {
 							pathname: '/servers/terminal',
 							params: {
 								connectionId: success.connectionId,
-								channelId: String(success.channelId),
+								shellId: success.shellId,
 							},
 						});
 					}),
@@ -65,10 +65,10 @@ function ServerDetailContent({ id }: { id: string }) {
 	const sshConnMutation = useSshConnMutation();
 
 	const openTerminal = React.useCallback(
-		(connectionId: string, channelId: number) => {
+		(connectionId: string, shellId: string) => {
 			router.push({
 				pathname: '/servers/terminal',
-				params: { connectionId, channelId: String(channelId) },
+				params: { connectionId, shellId },
 			});
 		},
 		[router],
@@ -123,14 +123,14 @@ function ServerDetailContent({ id }: { id: string }) {
 					const shell = yield* Effect.tryPromise(() =>
 						live.startShell({ shellIntegration: effective }),
 					);
-					openTerminal(live.connectionId, shell.channelId);
+					openTerminal(live.connectionId, shell.shellId);
 				} else {
 					// Reconnect goes through the mutation, which ANDs the global setting in
 					// itself — pass the per-host CHOICE.
 					const success = yield* Effect.tryPromise(() =>
 						sshConnMutation.mutateAsync(details, { shellIntegration }),
 					);
-					openTerminal(success.connectionId, success.channelId);
+					openTerminal(success.connectionId, success.shellId);
 				}
 			}).pipe(
 				Effect.catch((error) =>
@@ -210,10 +210,10 @@ function ServerDetailContent({ id }: { id: string }) {
 					<View className='gap-2.5'>
 						{shells.map((shell) => (
 							<ShellRow
-								key={`${shell.connectionId}:${shell.channelId}`}
+								key={shell.shellId}
 								shell={shell}
 								onResume={() =>
-									openTerminal(shell.connectionId, shell.channelId)
+									openTerminal(shell.connectionId, shell.shellId)
 								}
 							/>
 						))}
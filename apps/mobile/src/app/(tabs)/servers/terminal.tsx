@@ -113,15 +113,14 @@ function ShellDetail() {
 
 	const searchParams = useLocalSearchParams<{
 		connectionId?: string;
-		channelId?: string;
+		shellId?: string;
 	}>();
 
-	if (!searchParams.connectionId || !searchParams.channelId) {
-		throw new Error('Missing connectionId or channelId');
+	if (!searchParams.connectionId || !searchParams.shellId) {
+		throw new Error('Missing connectionId or shellId');
 	}
 
-	const { connectionId } = searchParams;
-	const channelId = Number.parseInt(searchParams.channelId, 10);
+	const { connectionId, shellId } = searchParams;
 
 	const router = useRouter();
 	const insets = useSafeAreaInsets();
@@ -200,18 +199,13 @@ function ShellDetail() {
 			? Math.max(keyboardHeight - spaceBelowColumn, insets.bottom)
 			: restingToolbarMargin;
 
-	// Shells are keyed in the store by their native `shellId` (opaque), but the
-	// route only carries connectionId + channelId — so resolve by those fields.
+	// Shells are keyed in the store by their native (opaque) `shellId`, which the
+	// route carries as-is.
 	const shell = useAtomValue(
 		sshShellsAtom,
 		useCallback(
-			(shells: Record<string, StoreShell>) =>
-				Object.values(shells).find(
-					(candidate) =>
-						candidate.connectionId === connectionId &&
-						candidate.channelId === channelId,
-				),
-			[connectionId, channelId],
+			(shells: Record<string, StoreShell>) => shells[shellId],
+			[shellId],
 		),
 	);
 	const connection = useAtomValue(connectionAtom(connectionId));
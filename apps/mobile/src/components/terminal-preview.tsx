@@ -9,7 +9,7 @@ import { ThemedText } from '@/components/themed/ThemedText';
 import { useTerminalRenderConfig } from '@/lib/preferences';
 
 /** Reserved shell id for the settings preview. Can't collide with a real shell id
- *  (`connectionId:channelId`). */
+ *  (`connectionId:kindN`). */
 const PREVIEW_ID = '__preview__';
 
 // Canned shell output that exercises the palette so changing any Terminal setting
@@ -2,7 +2,7 @@ import {
 	addFresshEventListener,
 	FresshEvent_Tags,
 	runCommand,
-	SshConnectionProgressEvent,
+	SshConnectionProgressEvent_Tags,
 	SshError_Tags,
 } from '@fressh/react-native-terminal';
 import { useAtomSet, useAtomValue } from '@effect/atom-react';
@@ -119,7 +119,7 @@ function classifyConnectError(error: unknown): FriendlyError {
 		: undefined;
 
 	return Match.value(tag).pipe(
-		Match.when(SshError_Tags.Auth, () => ({
+		Match.whenOr(SshError_Tags.Auth, SshError_Tags.AuthRejected, () => ({
 			title: 'Authentication failed',
 			hint: 'The server rejected your credentials — check the username, and that this key/password is authorized on the host.',
 			detail,
@@ -129,11 +129,36 @@ function classifyConnectError(error: unknown): FriendlyError {
 			hint: 'You declined the server’s host key, or its identity didn’t match what was expected.',
 			detail,
 		})),
+		Match.when(SshError_Tags.PassphraseRequired, () => ({
+			title: 'Key passphrase needed',
+			hint: 'This private key is encrypted — enter its passphrase.',
+			detail,
+		})),
+		Match.when(SshError_Tags.WrongPassphrase, () => ({
+			title: 'Wrong key passphrase',
+			hint: 'The passphrase is incorrect for this private key — check it and try again.',
+			detail,
+		})),
+		Match.when(SshError_Tags.Certificate, () => ({
+			title: 'Certificate can’t be used',
+			hint: 'The SSH certificate is expired, not yet valid, or not issued for this user/key.',
+			detail,
+		})),
 		Match.when(SshError_Tags.Keys, () => ({
 			title: 'Problem with the private key',
 			hint: 'The key couldn’t be used — try re-importing it in the Keys tab.',
 			detail,
 		})),
+		Match.when(SshError_Tags.Timeout, () => ({
+			title: 'Connection timed out',
+			hint: 'The host didn’t respond — check the address, port, and any firewall.',
+			detail,
+		})),
+		Match.when(SshError_Tags.Dns, () => ({
+			title: "Can't find that host",
+			hint: 'Check the hostname for typos and that you’re on the right network.',
+			detail,
+		})),
 		Match.when(SshError_Tags.NotFound, () => ({ title: 'Not found', detail })),
 		Match.when(SshError_Tags.Disconnected, () => ({
 			title: 'Disconnected',
@@ -349,11 +374,11 @@ const connectAtom = atomRuntime.fn(
 			yield* Effect.logInfo(
 				'Connected to SSH server',
 				sshConnection.connectionId,
-				shellHandle.channelId,
+				shellHandle.shellId,
 			);
 			return {
 				connectionId: sshConnection.connectionId,
-				channelId: shellHandle.channelId,
+				shellId: shellHandle.shellId,
 			};
 		});
 
@@ -378,8 +403,8 @@ const connectAtom = atomRuntime.fn(
 			});
 		});
 
-		// The native connect has no timeout of its own, so a black-holed TCP
-		// connect / handshake would leave the UI on "Connecting…" forever. Fail
+		// The native connect only bounds DNS + TCP (see ssh-store), so a server
+		// stalling the handshake would leave the UI on "Connecting…" forever. Fail
 		// after CONNECT_INACTIVITY_TIMEOUT without activity: each progress event
 		// opens a fresh window (per-phase), and while a host-key trust prompt is
 		// waiting on the user the wait is untimed (their reading time isn't the
@@ -415,10 +440,20 @@ const connectAtom = atomRuntime.fn(
 					if (event.tag !== FresshEvent_Tags.ConnectProgress) {
 						return;
 					}
-					const progress: SshConnectionProgress =
-						event.inner.event === SshConnectionProgressEvent.TcpConnected
+					const { event: native } = event.inner;
+					// DNS / per-address attempts only feed the log (and the watchdog).
+					const progress: SshConnectionProgress | undefined =
+						native.tag === SshConnectionProgressEvent_Tags.TcpConnected
 							? 'tcpConnected'
-							: 'sshHandshake';
+							: native.tag === SshConnectionProgressEvent_Tags.SshHandshake
+								? 'sshHandshake'
+								: undefined;
+					if (progress === undefined) {
+						appRuntime.runSync(
+							Effect.logDebug('SSH connect detail', native).pipe(annotateModule),
+						);
+						return;
+					}
 					// Plain callback outside the connect fiber — log via the runtime.
 					appRuntime.runSync(
 						Effect.logInfo('SSH connect progress event', progress).pipe(
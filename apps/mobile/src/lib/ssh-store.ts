@@ -47,9 +47,9 @@ export interface StoreConnection {
 }
 
 export interface StoreShell {
+	/** Opaque native id; the only key to route or key a shell by. */
 	shellId: string;
 	connectionId: string;
-	channelId: number;
 	createdAtMs: number;
 	pty: string;
 	/** Optional user-set local name for this session (runtime-only, set via the
@@ -90,16 +90,10 @@ export const connectionAtom = Atom.family((connectionId: string) =>
 	Atom.map(sshConnectionsAtom, (connections) => connections[connectionId]),
 );
 
-function channelIdFromShellId(shellId: string): number {
-	const n = Number.parseInt(shellId.slice(shellId.lastIndexOf(':') + 1), 10);
-	return Number.isNaN(n) ? 0 : n;
-}
-
 function makeShell(shellId: string, connectionId: string): StoreShell {
 	return {
 		shellId,
 		connectionId,
-		channelId: channelIdFromShellId(shellId),
 		createdAtMs: appRuntime.runSync(Clock.currentTimeMillis),
 		pty: 'xterm-256color',
 		sendData: (data) => fresshSendData(shellId, data),
@@ -139,6 +133,19 @@ function makeConnection(
 	};
 }
 
+/** Ping every 15s; three unanswered in a row ⇒ the connection is lost (mobile
+ *  networks drop without a FIN, so silence is the only signal). */
+const KEEPALIVE = { intervalSecs: 15, maxMissed: 3 };
+
+/** On loss, keep the shells (and their scrollback) and redial forever, backing
+ *  off from 1s to 30s between attempts. */
+const RECONNECT = { maxAttempts: 0, initialDelayMs: 1000, maxDelayMs: 30_000 };
+
+/** Give up on DNS + TCP after 15s. The handshake and auth stay untimed: they
+ *  can be waiting on the user (host-key trust, keyboard-interactive), and the
+ *  connect watchdog in query-fns covers a stalled server. */
+const TIMEOUTS = { connectMs: 15_000 };
+
 /** Open an SSH connection and register it in {@link sshConnectionsAtom}. */
 export const connectSsh = Effect.fnUntraced(function* (args: ConnectArgs) {
 	const security =
@@ -149,9 +156,15 @@ export const connectSsh = Effect.fnUntraced(function* (args: ConnectArgs) {
 		host: args.host,
 		port: args.port,
 		username: args.username,
-		security,
+		credentials: [security],
 	};
-	const connectionId = yield* Effect.tryPromise(() => fresshConnect(details));
+	const connectionId = yield* Effect.tryPromise(() =>
+		fresshConnect(details, [], {
+			keepalive: KEEPALIVE,
+			reconnect: RECONNECT,
+			timeouts: TIMEOUTS,
+		}),
+	);
 	const connection = makeConnection(connectionId, {
 		host: args.host,
 		port: args.port,
@@ -211,6 +224,35 @@ const onLifecycleEvent = Match.type<FresshEvent>().pipe(
 			});
 		}),
 	),
+	Match.discriminator('tag')(
+		FresshEvent_Tags.ConnectionLost,
+		Effect.fnUntraced(function* (event) {
+			const { connectionId, reason } = event.inner;
+			// Teardown (ShellClosed…, then ConnectionClosed) follows natively.
+			yield* Effect.logWarning('connection lost', connectionId, reason);
+		}),
+	),
+	Match.discriminator('tag')(
+		FresshEvent_Tags.Reconnecting,
+		Effect.fnUntraced(function* (event) {
+			const { connectionId, attempt, delayMs, reason } = event.inner;
+			// Shells stay registered (detached) until Reconnected re-binds them.
+			yield* Effect.logWarning(
+				'reconnecting',
+				connectionId,
+				attempt,
+				delayMs,
+				reason,
+			);
+		}),
+	),
+	Match.discriminator('tag')(
+		FresshEvent_Tags.Reconnected,
+		Effect.fnUntraced(function* (event) {
+			const { connectionId, attempt } = event.inner;
+			yield* Effect.logInfo('reconnected', connectionId, attempt);
+		}),
+	),
 	Match.discriminator('tag')(
 		FresshEvent_Tags.ShellClosed,
 		Effect.fnUntraced(function* (event) {
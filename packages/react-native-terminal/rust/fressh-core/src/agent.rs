@@ -0,0 +1,61 @@
+//! The app-wide SSH agent: one `fressh-ssh` [`KeyAgent`] whose keys every
+//! connection with `forward_agent` set serves to its far side. Keys are added
+//! and removed from JS (the app decides which ones to forward).
+//!
+//! Confirm-before-use is **park/resume**, like host-key verification
+//! (`host_key.rs`, §7): a signature with a `confirm` key is emitted as
+//! `AgentSignPending`, a `oneshot` is parked under a fresh request id, and
+//! [`respond_to_agent_sign`] resumes it. Several connections can ask at once,
+//! hence an id per request rather than per connection.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use once_cell::sync::Lazy;
+use tokio::sync::oneshot;
+
+use fressh_ssh::{AgentConfirmer, AgentSignRequest, KeyAgent};
+
+use crate::events::{self, CoreEvent};
+
+static AGENT: Lazy<KeyAgent> = Lazy::new(KeyAgent::new);
+static WAITERS: Lazy<DashMap<String, oneshot::Sender<bool>>> = Lazy::new(DashMap::new);
+static REQUEST_COUNTER: AtomicU64 = AtomicU64::new(1);
+
+/// The shared key store (a cheap handle onto it).
+pub fn agent() -> KeyAgent {
+	AGENT.clone()
+}
+
+/// The [`AgentConfirmer`] handed to `fressh-ssh`'s `connect`.
+pub(crate) struct ParkingConfirmer {
+	pub connection_id: String,
+}
+
+impl AgentConfirmer for ParkingConfirmer {
+	fn confirm(&self, request: AgentSignRequest) -> BoxFuture<'static, bool> {
+		let connection_id = self.connection_id.clone();
+		Box::pin(async move {
+			let n = REQUEST_COUNTER.fetch_add(1, Ordering::Relaxed);
+			let request_id = format!("{connection_id}/agent{n}");
+			let (tx, rx) = oneshot::channel();
+			WAITERS.insert(request_id.clone(), tx);
+			events::emit(CoreEvent::AgentSignPending {
+				connection_id,
+				request_id,
+				request,
+			});
+			// A dropped waiter refuses.
+			rx.await.unwrap_or(false)
+		})
+	}
+}
+
+/// Resume a parked agent signature: `allow` lets the key sign this once. No-op
+/// if nothing is waiting on that id.
+pub fn respond_to_agent_sign(request_id: &str, allow: bool) {
+	if let Some((_id, tx)) = WAITERS.remove(request_id) {
+		let _ = tx.send(allow);
+	}
+}
@@ -0,0 +1,141 @@
+//! Keyboard-interactive authentication as **park/resume**, the same shape as
+//! host-key verification (`host_key.rs`, §7): each round the server sends is
+//! emitted to JS as `AuthPromptPending`, a `oneshot` is parked under the
+//! connection id, and [`respond_to_auth_prompt`] resumes it with the answers.
+//!
+//! A server may ask several rounds (password, then OTP); each round parks
+//! afresh. Hops authenticate one after another, so one parked round per
+//! connection id is enough; the event's `hop` says which host is asking.
+
+use dashmap::DashMap;
+use futures::future::BoxFuture;
+use once_cell::sync::Lazy;
+use tokio::sync::oneshot;
+
+use fressh_ssh::{AuthPromptRequest, AuthPrompter};
+
+use crate::events::{self, CoreEvent};
+
+static WAITERS: Lazy<DashMap<String, oneshot::Sender<Option<Vec<String>>>>> =
+	Lazy::new(DashMap::new);
+
+/// The [`AuthPrompter`] handed to `fressh-ssh`'s `connect`. One per hop: `hop` is
+/// the jump-host index, `None` for the final target.
+pub(crate) struct ParkingPrompter {
+	pub connection_id: String,
+	pub hop: Option<u32>,
+}
+
+impl AuthPrompter for ParkingPrompter {
+	fn prompt(&self, request: AuthPromptRequest) -> BoxFuture<'static, Option<Vec<String>>> {
+		let connection_id = self.connection_id.clone();
+		let hop = self.hop;
+		Box::pin(async move {
+			let (tx, rx) = oneshot::channel();
+			WAITERS.insert(connection_id.clone(), tx);
+			events::emit(CoreEvent::AuthPromptPending {
+				connection_id,
+				hop,
+				request,
+			});
+			// JS resolves via respond_to_auth_prompt; a dropped waiter cancels.
+			rx.await.ok().flatten()
+		})
+	}
+}
+
+/// Resume a parked keyboard-interactive round with one answer per prompt, or
+/// `None` to cancel. No-op if nothing is waiting on that id.
+pub fn respond_to_auth_prompt(connection_id: &str, answers: Option<Vec<String>>) {
+	if let Some((_id, tx)) = WAITERS.remove(connection_id) {
+		let _ = tx.send(answers);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use futures::poll;
+
+	use fressh_ssh::AuthPrompt;
+
+	use super::*;
+	use crate::events::testing::capture;
+	use crate::runtime;
+
+	fn request(round: u32, prompt: &str) -> AuthPromptRequest {
+		AuthPromptRequest {
+			round,
+			name: String::new(),
+			instruction: String::new(),
+			prompts: vec![AuthPrompt {
+				prompt: prompt.to_string(),
+				echo: false,
+			}],
+		}
+	}
+
+	#[test]
+	fn each_round_parks_until_answered() {
+		let (_guard, cap) = capture();
+		let id = "me@host:22#prompt-rounds";
+		let prompter = ParkingPrompter {
+			connection_id: id.to_string(),
+			hop: Some(1),
+		};
+		runtime::handle().block_on(async {
+			for (round, prompt, answer) in [(0, "Password: ", "hunter2"), (1, "Code: ", "123456")] {
+				let mut parked = prompter.prompt(request(round, prompt));
+				assert!(poll!(&mut parked).is_pending());
+				respond_to_auth_prompt(id, Some(vec![answer.to_string()]));
+				assert_eq!(parked.await, Some(vec![answer.to_string()]));
+			}
+		});
+		let rounds: Vec<(Option<u32>, u32, String)> = cap
+			.events()
+			.into_iter()
+			.filter_map(|e| match e {
+				CoreEvent::AuthPromptPending {
+					connection_id,
+					hop,
+					request,
+				} if connection_id == id => Some((hop, request.round, request.prompts[0].prompt.clone())),
+				_ => None,
+			})
+			.collect();
+		assert_eq!(
+			rounds,
+			[
+				(Some(1), 0, "Password: ".to_string()),
+				(Some(1), 1, "Code: ".to_string())
+			]
+		);
+	}
+
+	#[test]
+	fn a_cancelled_or_dropped_round_answers_none() {
+		let (_guard, _cap) = capture();
+		let id = "me@host:22#prompt-cancel";
+		let prompter = ParkingPrompter {
+			connection_id: id.to_string(),
+			hop: None,
+		};
+		runtime::handle().block_on(async {
+			let mut cancelled = prompter.prompt(request(0, "Code: "));
+			assert!(poll!(&mut cancelled).is_pending());
+			respond_to_auth_prompt(id, None);
+			assert_eq!(cancelled.await, None);
+
+			// A newer round on the same connection drops the older waiter.
+			let mut stale = prompter.prompt(request(0, "Code: "));
+			assert!(poll!(&mut stale).is_pending());
+			let mut fresh = prompter.prompt(request(1, "Token: "));
+			assert!(poll!(&mut fresh).is_pending());
+			assert_eq!(stale.await, None);
+
+			// Abandoned (the connect was dropped): answering it is a no-op.
+			drop(fresh);
+			respond_to_auth_prompt(id, Some(vec!["654321".to_string()]));
+			assert!(!WAITERS.contains_key(id));
+		});
+	}
+}
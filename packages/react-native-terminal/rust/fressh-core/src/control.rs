@@ -3,7 +3,8 @@
 //! the registry + sessions and runs its work on the core runtime via
 //! [`runtime::run`] so russh's internal tasks land on our runtime, not the shim's.
 
-use std::sync::atomic::{AtomicU64, Ordering};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 
 use alacritty_terminal::grid::{Dimensions, Scroll};
@@ -12,15 +13,31 @@ use alacritty_terminal::selection::{Selection, SelectionType};
 use alacritty_terminal::term::{viewport_to_point, TermMode};
 use alacritty_terminal::Term;
 
+use fressh_ssh::shell_integration::build_context_command;
 use fressh_ssh::{
-	CommandOutput, ConnectOptions, ConnectionDetails, KeyType, ProgressCallback, SshError,
-	StartShellOptions,
+	AgentKeyInfo, CertificateInfo, CommandOutput, ConnectConfig, ConnectOptions,
+	Connection as SshConnection, ConnectionDetails, ExecSignal, JumpHost, KeyType,
+	ProgressCallback, ScpOptions, SftpEntry, SftpStat, SshConfig, SshConfigHost, SshConnectionInfo,
+	SshError, StartShellOptions,
 };
 
+use crate::agent::{self, ParkingConfirmer};
+use crate::auth_prompt::{self, ParkingPrompter};
 use crate::events::{self, CoreEvent};
+use crate::exec::{ExecOptions, ExecSession};
+use crate::forward::{ForwardInfo, ForwardSession};
+use crate::health;
 use crate::host_key::{self, ParkingVerifier};
-use crate::session::{ConnectionSession, CoreListener, RenderMetrics, ShellSession};
+use crate::known_hosts;
+use crate::mosh;
+use crate::session::{
+	ConnectionSession, CoreListener, RenderMetrics, ShellCloseReason, ShellEnd, ShellSession,
+};
+use crate::sftp::SftpSession;
 use crate::source::ShellBackend;
+use crate::transfer::{
+	self, Transfer, TransferDirection, TransferInfo, TransferState, TransferVia,
+};
 use crate::{registry, runtime};
 
 /// Reserved `connection_id` for preview shells. They live in the same registry as
@@ -34,6 +51,11 @@ const PREVIEW_COLS: usize = 40;
 const PREVIEW_ROWS: usize = 12;
 
 static CONN_COUNTER: AtomicU64 = AtomicU64::new(1);
+static FORWARD_COUNTER: AtomicU64 = AtomicU64::new(1);
+static SFTP_COUNTER: AtomicU64 = AtomicU64::new(1);
+static TRANSFER_COUNTER: AtomicU64 = AtomicU64::new(1);
+static EXEC_COUNTER: AtomicU64 = AtomicU64::new(1);
+static SHELL_COUNTER: AtomicU64 = AtomicU64::new(1);
 
 fn next_connection_id(details: &ConnectionDetails) -> String {
 	let n = CONN_COUNTER.fetch_add(1, Ordering::Relaxed);
@@ -43,74 +65,296 @@ fn next_connection_id(details: &ConnectionDetails) -> String {
 	)
 }
 
-/// Establish + authenticate a connection. The connection id is assigned up front
-/// so the `hostKeyPending` event (emitted mid-handshake, before this resolves)
-/// can be answered with `respond_to_host_key(connectionId, …)`. (§7)
-pub async fn connect(details: ConnectionDetails) -> Result<String, SshError> {
-	runtime::run(async move {
-		let connection_id = next_connection_id(&details);
+fn next_forward_id(connection_id: &str) -> String {
+	let n = FORWARD_COUNTER.fetch_add(1, Ordering::Relaxed);
+	format!("{connection_id}/fwd{n}")
+}
 
-		let verifier = Arc::new(ParkingVerifier {
-			connection_id: connection_id.clone(),
-		});
+fn next_sftp_id(connection_id: &str) -> String {
+	let n = SFTP_COUNTER.fetch_add(1, Ordering::Relaxed);
+	format!("{connection_id}/sftp{n}")
+}
 
-		let progress_conn_id = connection_id.clone();
-		let on_progress: ProgressCallback = Arc::new(move |event| {
-			events::emit(CoreEvent::ConnectProgress {
-				connection_id: progress_conn_id.clone(),
-				event,
-			});
-		});
+/// `parent` is the SFTP session, or for SCP the connection.
+fn next_transfer_id(parent: &str) -> String {
+	let n = TRANSFER_COUNTER.fetch_add(1, Ordering::Relaxed);
+	format!("{parent}/xfer{n}")
+}
 
-		let conn = fressh_ssh::connect(ConnectOptions {
+/// Not the channel number: a re-bound shell keeps its id on a new channel, and
+/// a later shell may be handed the old number.
+fn next_shell_id(connection_id: &str, kind: &str) -> String {
+	let n = SHELL_COUNTER.fetch_add(1, Ordering::Relaxed);
+	format!("{connection_id}:{kind}{n}")
+}
+
+fn next_exec_id(connection_id: &str) -> String {
+	let n = EXEC_COUNTER.fetch_add(1, Ordering::Relaxed);
+	format!("{connection_id}/exec{n}")
+}
+
+/// Establish + authenticate a connection, tunnelled through `jump_hosts` (in
+/// order; empty ⇒ direct). The connection id is assigned up front so the
+/// `hostKeyPending` / `authPromptPending` events (emitted mid-connect — per hop —
+/// before this resolves) can be answered with `respond_to_host_key` /
+/// `respond_to_auth_prompt(connectionId, …)`. (§7)
+///
+/// With `config.keepalive` set, a health monitor pings the connection and
+/// reports `connectionDegraded` / `connectionLost`; with `config.reconnect`, a
+/// lost connection is re-established instead of torn down (`reconnect.rs`).
+pub async fn connect(
+	details: ConnectionDetails,
+	jump_hosts: Vec<ConnectionDetails>,
+	config: ConnectConfig,
+) -> Result<String, SshError> {
+	runtime::run(async move {
+		let connection_id = next_connection_id(&details);
+		let conn = open_connection(&connection_id, details, jump_hosts.clone(), config).await?;
+		register_connection(connection_id.clone(), conn, jump_hosts);
+		Ok(connection_id)
+	})
+	.await
+}
+
+/// Dial + authenticate under `connection_id`: host-key and keyboard-interactive
+/// decisions park on that id, and progress is reported against it. Used for
+/// the first connect and for every reconnect.
+pub(crate) async fn open_connection(
+	connection_id: &str,
+	details: ConnectionDetails,
+	jump_hosts: Vec<ConnectionDetails>,
+	config: ConnectConfig,
+) -> Result<SshConnection, SshError> {
+	let verifier = Arc::new(ParkingVerifier {
+		connection_id: connection_id.to_string(),
+		hop: None,
+	});
+	let prompter = Arc::new(ParkingPrompter {
+		connection_id: connection_id.to_string(),
+		hop: None,
+	});
+	let jump_hosts = (0u32..)
+		.zip(jump_hosts)
+		.map(|(hop, details)| JumpHost {
 			details,
-			verifier,
-			on_progress: Some(on_progress),
+			verifier: Arc::new(ParkingVerifier {
+				connection_id: connection_id.to_string(),
+				hop: Some(hop),
+			}),
+			prompter: Some(Arc::new(ParkingPrompter {
+				connection_id: connection_id.to_string(),
+				hop: Some(hop),
+			})),
 		})
-		.await?;
+		.collect();
 
-		registry::insert_connection(Arc::new(ConnectionSession {
-			connection_id: connection_id.clone(),
-			inner: Arc::new(conn),
-		}));
-		Ok(connection_id)
+	let progress_conn_id = connection_id.to_string();
+	let on_progress: ProgressCallback = Arc::new(move |event| {
+		events::emit(CoreEvent::ConnectProgress {
+			connection_id: progress_conn_id.clone(),
+			event,
+		});
+	});
+
+	fressh_ssh::connect(ConnectOptions {
+		details,
+		verifier,
+		prompter: Some(prompter),
+		on_progress: Some(on_progress),
+		jump_hosts,
+		config,
+		agent: Some(agent::agent()),
+		agent_confirmer: Some(Arc::new(ParkingConfirmer {
+			connection_id: connection_id.to_string(),
+		})),
 	})
 	.await
 }
 
+/// Put a connection in the registry (replacing a lost one with the same id)
+/// and start its health monitor if keepalives are configured.
+pub(crate) fn register_connection(
+	connection_id: String,
+	conn: SshConnection,
+	jump_hosts: Vec<ConnectionDetails>,
+) {
+	let conn = Arc::new(conn);
+	let health_task = conn
+		.config
+		.keepalive
+		.map(|keepalive| health::spawn(connection_id.clone(), conn.clone(), keepalive));
+	registry::insert_connection(Arc::new(ConnectionSession {
+		connection_id,
+		inner: conn,
+		health_task,
+		jump_hosts,
+		reconnecting: AtomicBool::new(false),
+	}));
+}
+
+/// What is known about an open connection: its details, timings and the
+/// negotiated algorithms. `None` for an unknown id.
+pub fn connection_info(connection_id: &str) -> Option<SshConnectionInfo> {
+	registry::connection(connection_id).map(|c| c.inner.info.clone())
+}
+
 /// Resume a parked host-key decision (accept/reject the server key). (§7)
 pub fn respond_to_host_key(connection_id: &str, accept: bool) {
 	host_key::respond_to_host_key(connection_id, accept);
 }
 
-/// Open a PTY + shell on a connection, returning the new shell id. The reader
-/// loop starts immediately, feeding the durable `Term`.
+/// Install the app's `known_hosts` text, consulted before any `hostKeyPending`
+/// is raised. Replaces what was there; returns how many entries were understood.
+pub fn set_known_hosts(text: &str) -> usize {
+	known_hosts::set_known_hosts(text)
+}
+
+/// Resume a parked keyboard-interactive round: one answer per prompt, in order,
+/// or `None` to cancel (the connect then fails with an auth error).
+pub fn respond_to_auth_prompt(connection_id: &str, answers: Option<Vec<String>>) {
+	auth_prompt::respond_to_auth_prompt(connection_id, answers);
+}
+
+/// Hold a key in the app's SSH agent, for connections with `forward_agent`.
+/// With `confirm`, each signature first raises `agentSignPending`.
+pub fn agent_add_key(
+	private_key_content: &str,
+	passphrase: Option<&str>,
+	comment: Option<String>,
+	confirm: bool,
+) -> Result<AgentKeyInfo, SshError> {
+	agent::agent().add_key(private_key_content, passphrase, comment, confirm)
+}
+
+/// Drop a key from the agent by fingerprint; `false` if it wasn't held.
+pub fn agent_remove_key(fingerprint: &str) -> bool {
+	agent::agent().remove_key(fingerprint)
+}
+
+pub fn agent_remove_all() {
+	agent::agent().remove_all();
+}
+
+pub fn agent_list_keys() -> Vec<AgentKeyInfo> {
+	agent::agent().list()
+}
+
+/// Resume a parked agent signature (allow/refuse this one use of the key).
+pub fn respond_to_agent_sign(request_id: &str, allow: bool) {
+	agent::respond_to_agent_sign(request_id, allow);
+}
+
+/// What [`start_shell`] opened.
+#[derive(Debug, Clone)]
+pub struct StartedShell {
+	pub shell_id: String,
+	/// Names from `StartShellOptions::env` the server's `AcceptEnv` refused;
+	/// exported by the shell-integration bootstrap instead when that is on.
+	pub rejected_env: Vec<String>,
+}
+
+/// Open a PTY + shell on a connection. The reader loop starts immediately,
+/// feeding the durable `Term`.
 pub async fn start_shell(
 	connection_id: String,
 	opts: StartShellOptions,
 	cols: usize,
 	rows: usize,
 	scrollback_lines: usize,
-) -> Result<String, SshError> {
+) -> Result<StartedShell, SshError> {
 	runtime::run(async move {
 		let conn = registry::connection(&connection_id)
 			.ok_or_else(|| SshError::NotFound(connection_id.clone()))?;
-		let shell = conn.inner.open_shell(opts).await?;
-		let shell_id = format!("{}:{}", connection_id, shell.channel_id);
-		let session = ShellSession::spawn(
-			shell_id.clone(),
+		let shell = conn.inner.open_shell(opts.clone()).await?;
+		let rejected_env = shell.rejected_env.clone();
+		let shell_id = register_shell(
 			connection_id,
+			"shell",
 			ShellBackend::from_ssh(shell),
+			Some(opts),
 			cols,
 			rows,
 			scrollback_lines,
 		);
-		registry::insert_shell(session);
-		Ok(shell_id)
+		Ok(StartedShell {
+			shell_id,
+			rejected_env,
+		})
 	})
 	.await
 }
 
+/// Start a mosh shell through a connection, returning the new shell id. The
+/// connection runs `server` (`mosh-server` if `None`) and is then out of the
+/// data path: the shell talks UDP to the same host and rides out network
+/// changes (and a reconnect of the connection) by itself. It still belongs to
+/// the connection — `disconnect` closes it like any other shell. Needs a
+/// direct connection: through a jump host or proxy it fails with `Mosh`.
+pub async fn start_mosh_shell(
+	connection_id: String,
+	server: Option<String>,
+	cols: usize,
+	rows: usize,
+	scrollback_lines: usize,
+) -> Result<String, SshError> {
+	runtime::run(async move {
+		let conn = registry::connection(&connection_id)
+			.ok_or_else(|| SshError::NotFound(connection_id.clone()))?;
+		// mosh's UDP goes straight to the host, so it needs the address the SSH
+		// connection reached — unknown, and maybe unreachable, when tunnelled.
+		let ip = conn
+			.inner
+			.info
+			.remote_ip
+			.as_deref()
+			.and_then(|ip| ip.parse().ok())
+			.ok_or_else(|| {
+				SshError::Mosh(
+					"mosh needs a direct connection, not one through a jump host or proxy"
+						.to_string(),
+				)
+			})?;
+		let connect =
+			mosh::bootstrap(&conn.inner, server.as_deref().unwrap_or("mosh-server")).await?;
+		let port = connect.port;
+		let (reader, writer) = mosh::start(ip, connect, cols as u32, rows as u32).await?;
+		Ok(register_shell(
+			connection_id,
+			"mosh",
+			ShellBackend::from_mosh(port, reader, writer),
+			None,
+			cols,
+			rows,
+			scrollback_lines,
+		))
+	})
+	.await
+}
+
+/// Spawn a shell's session on `backend` and register it under a fresh id.
+fn register_shell(
+	connection_id: String,
+	kind: &str,
+	backend: ShellBackend,
+	start_opts: Option<StartShellOptions>,
+	cols: usize,
+	rows: usize,
+	scrollback_lines: usize,
+) -> String {
+	let shell_id = next_shell_id(&connection_id, kind);
+	let session = ShellSession::spawn(
+		shell_id.clone(),
+		connection_id,
+		backend,
+		start_opts,
+		cols,
+		rows,
+		scrollback_lines,
+	);
+	registry::insert_shell(session);
+	shell_id
+}
+
 /// Run a one-off command on a connection **without** a PTY/shell, returning the
 /// captured stdout/stderr/exit code. Powers the Commands tab's one-off runner.
 pub async fn run_command(
@@ -125,6 +369,448 @@ pub async fn run_command(
 	.await
 }
 
+/// Like [`run_command`], but in the shell's latest working directory and with
+/// the variables it reported exported — what shell integration (or the user's
+/// own OSC 7) told us. A shell that reported nothing runs the command as
+/// `run_command` does, in the login directory.
+pub async fn run_command_in_shell_context(
+	shell_id: String,
+	command: String,
+) -> Result<CommandOutput, SshError> {
+	runtime::run(async move {
+		let shell =
+			registry::shell(&shell_id).ok_or_else(|| SshError::NotFound(shell_id.clone()))?;
+		let conn = registry::connection(&shell.connection_id)
+			.ok_or_else(|| SshError::NotFound(shell.connection_id.clone()))?;
+		let command = {
+			let context = shell.context.lock().unwrap_or_else(|p| p.into_inner());
+			let env: Vec<(String, String)> = context
+				.env
+				.iter()
+				.map(|(name, value)| (name.clone(), value.clone()))
+				.collect();
+			build_context_command(context.cwd.as_deref(), &env, &command)
+		};
+		conn.inner.exec_command(&command).await
+	})
+	.await
+}
+
+/// Start a command whose output streams back as `ExecOutput` events instead
+/// of being collected, returning its exec id. It ends with one `ExecExited` —
+/// on its own, on [`exec_close`], or on hitting a limit in `options`.
+pub async fn start_exec(
+	connection_id: String,
+	command: String,
+	options: ExecOptions,
+) -> Result<String, SshError> {
+	runtime::run(async move {
+		let conn = registry::connection(&connection_id)
+			.ok_or_else(|| SshError::NotFound(connection_id.clone()))?;
+		let exec = conn.inner.open_exec(&command).await?;
+		let exec_id = next_exec_id(&connection_id);
+		registry::insert_exec(ExecSession::spawn(
+			exec_id.clone(),
+			connection_id,
+			command,
+			exec,
+			options,
+		));
+		Ok(exec_id)
+	})
+	.await
+}
+
+fn exec_session(exec_id: &str) -> Result<Arc<ExecSession>, SshError> {
+	registry::exec(exec_id).ok_or_else(|| SshError::NotFound(exec_id.to_string()))
+}
+
+/// Write to a streaming command's stdin.
+pub async fn exec_write(exec_id: String, data: Vec<u8>) -> Result<(), SshError> {
+	let exec = exec_session(&exec_id)?;
+	runtime::run(async move { exec.write(&data).await }).await
+}
+
+/// Close a streaming command's stdin.
+pub async fn exec_eof(exec_id: String) -> Result<(), SshError> {
+	let exec = exec_session(&exec_id)?;
+	runtime::run(async move { exec.eof().await }).await
+}
+
+/// Signal a streaming command. Servers may ignore it; [`exec_close`] is the
+/// fallback.
+pub async fn exec_signal(exec_id: String, signal: ExecSignal) -> Result<(), SshError> {
+	let exec = exec_session(&exec_id)?;
+	runtime::run(async move { exec.signal(signal).await }).await
+}
+
+/// Close a streaming command's channel; its `ExecExited` follows. No-op if
+/// it already ended.
+pub async fn exec_close(exec_id: String) -> Result<(), SshError> {
+	if let Some(exec) = registry::exec(&exec_id) {
+		runtime::run(async move { exec.close().await }).await;
+	}
+	Ok(())
+}
+
+/// Start a local port forward (`-L`): listen on `bind_host:bind_port` on the
+/// device and forward each accepted socket to `remote_host:remote_port` as seen
+/// from the server. `bind_port` 0 picks a free port (see `bound_port`). The
+/// forward lives in the registry until [`stop_forward`] or [`disconnect`].
+pub async fn start_local_forward(
+	connection_id: String,
+	bind_host: String,
+	bind_port: u16,
+	remote_host: String,
+	remote_port: u16,
+) -> Result<ForwardInfo, SshError> {
+	runtime::run(async move {
+		let conn = registry::connection(&connection_id)
+			.ok_or_else(|| SshError::NotFound(connection_id.clone()))?;
+		let forward = ForwardSession::spawn_local(
+			next_forward_id(&connection_id),
+			connection_id,
+			conn.inner.clone(),
+			bind_host,
+			bind_port,
+			remote_host,
+			remote_port,
+		)
+		.await?;
+		Ok(forward.info())
+	})
+	.await
+}
+
+/// Start a remote port forward (`-R`): ask the server to listen on
+/// `bind_host:bind_port` and forward each connection it accepts to
+/// `local_host:local_port` as seen from the device. `bind_port` 0 lets the server
+/// pick (see `bound_port`). [`stop_forward`] sends `cancel-tcpip-forward`.
+pub async fn request_remote_forward(
+	connection_id: String,
+	bind_host: String,
+	bind_port: u16,
+	local_host: String,
+	local_port: u16,
+) -> Result<ForwardInfo, SshError> {
+	runtime::run(async move {
+		let conn = registry::connection(&connection_id)
+			.ok_or_else(|| SshError::NotFound(connection_id.clone()))?;
+		let forward = ForwardSession::spawn_remote(
+			next_forward_id(&connection_id),
+			connection_id,
+			conn.inner.clone(),
+			bind_host,
+			bind_port,
+			local_host,
+			local_port,
+		)
+		.await?;
+		Ok(forward.info())
+	})
+	.await
+}
+
+/// Start a dynamic forward (`-D`): a SOCKS5/SOCKS4a proxy on
+/// `bind_host:bind_port` whose every `CONNECT` becomes a `direct-tcpip` channel
+/// on this connection. `bind_port` 0 picks a free port (see `bound_port`).
+pub async fn start_dynamic_forward(
+	connection_id: String,
+	bind_host: String,
+	bind_port: u16,
+) -> Result<ForwardInfo, SshError> {
+	runtime::run(async move {
+		let conn = registry::connection(&connection_id)
+			.ok_or_else(|| SshError::NotFound(connection_id.clone()))?;
+		let forward = ForwardSession::spawn_dynamic(
+			next_forward_id(&connection_id),
+			connection_id,
+			conn.inner.clone(),
+			bind_host,
+			bind_port,
+		)
+		.await?;
+		Ok(forward.info())
+	})
+	.await
+}
+
+/// The forwards open on a connection, with their live traffic counters.
+pub fn list_forwards(connection_id: &str) -> Vec<ForwardInfo> {
+	registry::forwards_for_connection(connection_id)
+		.iter()
+		.map(|f| f.info())
+		.collect()
+}
+
+/// Stop a forward: stop listening (for `-R`, `cancel-tcpip-forward` on the
+/// server) and drop its in-flight streams.
+pub async fn stop_forward(forward_id: String) -> Result<(), SshError> {
+	if let Some(forward) = registry::remove_forward(&forward_id) {
+		runtime::run(async move { forward.stop().await }).await;
+		events::emit(CoreEvent::ForwardClosed { forward_id });
+	}
+	Ok(())
+}
+
+// ─────────────────────────── SFTP ───────────────────────────
+
+/// Start an SFTP session on a connection (a sibling channel; shells and
+/// forwards are untouched), returning its id. It lives in the registry until
+/// [`close_sftp`] or [`disconnect`].
+pub async fn open_sftp(connection_id: String) -> Result<String, SshError> {
+	runtime::run(async move {
+		let conn = registry::connection(&connection_id)
+			.ok_or_else(|| SshError::NotFound(connection_id.clone()))?;
+		let inner = conn.inner.open_sftp().await?;
+		let sftp_id = next_sftp_id(&connection_id);
+		registry::insert_sftp(Arc::new(SftpSession {
+			sftp_id: sftp_id.clone(),
+			connection_id,
+			inner,
+		}));
+		Ok(sftp_id)
+	})
+	.await
+}
+
+pub async fn close_sftp(sftp_id: String) -> Result<(), SshError> {
+	if let Some(sftp) = registry::remove_sftp(&sftp_id) {
+		transfer::fail_riding(
+			|via| matches!(via, TransferVia::Sftp { sftp_id: id } if *id == sftp_id),
+			"SFTP session closed",
+		);
+		runtime::run(async move { sftp.close().await }).await;
+		events::emit(CoreEvent::SftpClosed { sftp_id });
+	}
+	Ok(())
+}
+
+fn sftp_session(sftp_id: &str) -> Result<Arc<SftpSession>, SshError> {
+	registry::sftp(sftp_id).ok_or_else(|| SshError::NotFound(sftp_id.to_string()))
+}
+
+/// The absolute form of `path` (`.` resolves to the login directory).
+pub async fn sftp_realpath(sftp_id: String, path: String) -> Result<String, SshError> {
+	let sftp = sftp_session(&sftp_id)?;
+	runtime::run(async move { sftp.inner.realpath(&path).await }).await
+}
+
+pub async fn sftp_read_dir(sftp_id: String, path: String) -> Result<Vec<SftpEntry>, SshError> {
+	let sftp = sftp_session(&sftp_id)?;
+	runtime::run(async move { sftp.inner.read_dir(&path).await }).await
+}
+
+/// Attributes of `path`, following symlinks.
+pub async fn sftp_stat(sftp_id: String, path: String) -> Result<SftpStat, SshError> {
+	let sftp = sftp_session(&sftp_id)?;
+	runtime::run(async move { sftp.inner.stat(&path).await }).await
+}
+
+/// Attributes of `path` itself, even if it is a symlink.
+pub async fn sftp_lstat(sftp_id: String, path: String) -> Result<SftpStat, SshError> {
+	let sftp = sftp_session(&sftp_id)?;
+	runtime::run(async move { sftp.inner.lstat(&path).await }).await
+}
+
+pub async fn sftp_mkdir(sftp_id: String, path: String) -> Result<(), SshError> {
+	let sftp = sftp_session(&sftp_id)?;
+	runtime::run(async move { sftp.inner.mkdir(&path).await }).await
+}
+
+pub async fn sftp_rename(sftp_id: String, from: String, to: String) -> Result<(), SshError> {
+	let sftp = sftp_session(&sftp_id)?;
+	runtime::run(async move { sftp.inner.rename(&from, &to).await }).await
+}
+
+/// Remove a file, symlink or empty directory.
+pub async fn sftp_remove(sftp_id: String, path: String) -> Result<(), SshError> {
+	let sftp = sftp_session(&sftp_id)?;
+	runtime::run(async move { sftp.inner.remove(&path).await }).await
+}
+
+/// Create a symlink at `link_path` pointing to `target`.
+pub async fn sftp_symlink(
+	sftp_id: String,
+	target: String,
+	link_path: String,
+) -> Result<(), SshError> {
+	let sftp = sftp_session(&sftp_id)?;
+	runtime::run(async move { sftp.inner.symlink(&target, &link_path).await }).await
+}
+
+/// Read up to `len` bytes of `path` from `offset` — one chunk of a file the
+/// app shows or edits. Shorter only at EOF.
+pub async fn sftp_read(
+	sftp_id: String,
+	path: String,
+	offset: u64,
+	len: u32,
+) -> Result<Vec<u8>, SshError> {
+	let sftp = sftp_session(&sftp_id)?;
+	runtime::run(async move { sftp.inner.read(&path, offset, len).await }).await
+}
+
+/// Write one chunk into `path` at `offset`, creating it if needed; `truncate`
+/// empties the file first (for the first chunk of a replacement).
+pub async fn sftp_write(
+	sftp_id: String,
+	path: String,
+	offset: u64,
+	data: Vec<u8>,
+	truncate: bool,
+) -> Result<(), SshError> {
+	let sftp = sftp_session(&sftp_id)?;
+	runtime::run(async move { sftp.inner.write(&path, offset, &data, truncate).await }).await
+}
+
+// ─────────────────────────── file transfers ───────────────────────────
+
+/// Queue a copy of remote `remote_path` to the device file `local_path`
+/// (replaced if present) over an SFTP session, returning the transfer id.
+/// Progress and the outcome arrive as `Transfer*` events.
+pub fn start_download(
+	sftp_id: String,
+	remote_path: String,
+	local_path: String,
+) -> Result<String, SshError> {
+	start_transfer(
+		sftp_id,
+		TransferDirection::Download,
+		remote_path,
+		local_path,
+	)
+}
+
+/// Queue a copy of the device file `local_path` to remote `remote_path`
+/// (replaced if present). As [`start_download`] otherwise.
+pub fn start_upload(
+	sftp_id: String,
+	local_path: String,
+	remote_path: String,
+) -> Result<String, SshError> {
+	start_transfer(sftp_id, TransferDirection::Upload, remote_path, local_path)
+}
+
+/// Queue a copy of remote `remote_path` to `local_path` by running `scp` on
+/// the connection, for servers without SFTP. As with `scp`, an existing
+/// directory at `local_path` receives the copy under its remote name. A
+/// resumed SCP transfer starts over.
+pub fn start_scp_download(
+	connection_id: String,
+	remote_path: String,
+	local_path: String,
+	options: ScpOptions,
+) -> Result<String, SshError> {
+	start_scp_transfer(
+		connection_id,
+		TransferDirection::Download,
+		remote_path,
+		local_path,
+		options,
+	)
+}
+
+/// Queue a copy of `local_path` to remote `remote_path` over `scp`. As
+/// [`start_scp_download`] otherwise.
+pub fn start_scp_upload(
+	connection_id: String,
+	local_path: String,
+	remote_path: String,
+	options: ScpOptions,
+) -> Result<String, SshError> {
+	start_scp_transfer(
+		connection_id,
+		TransferDirection::Upload,
+		remote_path,
+		local_path,
+		options,
+	)
+}
+
+fn start_transfer(
+	sftp_id: String,
+	direction: TransferDirection,
+	remote_path: String,
+	local_path: String,
+) -> Result<String, SshError> {
+	sftp_session(&sftp_id)?;
+	queue_transfer(
+		next_transfer_id(&sftp_id),
+		TransferVia::Sftp { sftp_id },
+		direction,
+		remote_path,
+		local_path,
+	)
+}
+
+fn start_scp_transfer(
+	connection_id: String,
+	direction: TransferDirection,
+	remote_path: String,
+	local_path: String,
+	options: ScpOptions,
+) -> Result<String, SshError> {
+	registry::connection(&connection_id)
+		.ok_or_else(|| SshError::NotFound(connection_id.clone()))?;
+	queue_transfer(
+		next_transfer_id(&connection_id),
+		TransferVia::Scp {
+			connection_id,
+			options,
+		},
+		direction,
+		remote_path,
+		local_path,
+	)
+}
+
+fn queue_transfer(
+	transfer_id: String,
+	via: TransferVia,
+	direction: TransferDirection,
+	remote_path: String,
+	local_path: String,
+) -> Result<String, SshError> {
+	let transfer = Transfer::new(transfer_id, via, direction, remote_path, local_path);
+	registry::insert_transfer(transfer.clone());
+	transfer.start(false);
+	Ok(transfer.transfer_id.clone())
+}
+
+/// Transfers queued, running or paused (finished and failed ones are gone).
+pub fn list_transfers() -> Vec<TransferInfo> {
+	registry::transfers().iter().map(|t| t.info()).collect()
+}
+
+fn transfer(transfer_id: &str) -> Result<Arc<Transfer>, SshError> {
+	registry::transfer(transfer_id).ok_or_else(|| SshError::NotFound(transfer_id.to_string()))
+}
+
+/// Stop a transfer where it is; [`resume_transfer`] carries on from there.
+pub fn pause_transfer(transfer_id: &str) -> Result<(), SshError> {
+	transfer(transfer_id)?.pause();
+	Ok(())
+}
+
+/// Queue a paused transfer again, continuing from what the destination
+/// already holds. No-op unless it is paused.
+pub fn resume_transfer(transfer_id: &str) -> Result<(), SshError> {
+	let transfer = transfer(transfer_id)?;
+	if transfer.state() == TransferState::Paused {
+		transfer.start(true);
+	}
+	Ok(())
+}
+
+/// Stop a transfer and forget it. No event follows; a partial destination
+/// file is left as it is.
+pub fn cancel_transfer(transfer_id: &str) {
+	if let Some(transfer) = registry::remove_transfer(transfer_id) {
+		transfer.abort();
+	}
+}
+
 /// Create a non-SSH **preview** shell: a registry `Term` fed a canned snippet,
 /// used by the Terminal-settings live preview. It is an ordinary [`ShellSession`]
 /// whose bytes come from [`ShellBackend::canned`] instead of an SSH channel, so the
@@ -140,6 +826,7 @@ pub fn create_preview(preview_id: String, demo: Vec<u8>) {
 		preview_id,
 		PREVIEW_CONNECTION_ID.to_string(),
 		ShellBackend::canned(demo.into()),
+		None,
 		PREVIEW_COLS,
 		PREVIEW_ROWS,
 		0,
@@ -366,38 +1053,125 @@ fn arrow_keys(mode: TermMode, lines: i32) -> Vec<u8> {
 pub async fn close_shell(shell_id: String) -> Result<(), SshError> {
 	if let Some(shell) = registry::remove_shell(&shell_id) {
 		runtime::run(async move { shell.close().await }).await;
-		events::emit(CoreEvent::ShellClosed { shell_id });
+		shell_closed(shell_id, ShellEnd::new(ShellCloseReason::LocalClose));
 	}
 	Ok(())
 }
 
-/// Disconnect a connection: close its shells, then drop the connection.
+/// How a closed shell ended (as its `ShellClosed` said), while the registry
+/// still remembers it — the most recent few dozen.
+pub fn shell_end(shell_id: &str) -> Option<ShellEnd> {
+	registry::shell_end(shell_id)
+}
+
+/// Record how an already unregistered shell ended and emit `ShellClosed`.
+pub(crate) fn shell_closed(shell_id: String, end: ShellEnd) {
+	registry::insert_shell_end(shell_id.clone(), end.clone());
+	events::emit(CoreEvent::ShellClosed { shell_id, end });
+}
+
+/// Disconnect a connection: stop its forwards, close its shells, then drop the
+/// connection.
 pub async fn disconnect(connection_id: String) -> Result<(), SshError> {
-	runtime::run(async move {
-		for shell in registry::shells_for_connection(&connection_id) {
-			registry::remove_shell(&shell.shell_id);
-			shell.close().await;
-			events::emit(CoreEvent::ShellClosed {
-				shell_id: shell.shell_id.clone(),
-			});
-		}
-		if let Some(conn) = registry::remove_connection(&connection_id) {
-			let _ = conn.inner.disconnect().await;
-			events::emit(CoreEvent::ConnectionClosed { connection_id });
+	runtime::run(teardown_connection(
+		connection_id,
+		ShellCloseReason::LocalClose,
+	))
+	.await;
+	Ok(())
+}
+
+/// Stop a connection's forwards and SFTP sessions, close its shells, then drop
+/// the connection, emitting the matching close events. Shared by [`disconnect`]
+/// and the health monitor (for a connection that was lost); `reason` is what
+/// the shells' `ShellClosed` says.
+pub(crate) async fn teardown_connection(connection_id: String, reason: ShellCloseReason) {
+	close_channel_users(&connection_id).await;
+	for shell in registry::shells_for_connection(&connection_id) {
+		registry::remove_shell(&shell.shell_id);
+		shell.close().await;
+		shell_closed(shell.shell_id.clone(), ShellEnd::new(reason));
+	}
+	if let Some(conn) = registry::remove_connection(&connection_id) {
+		if let Some(task) = &conn.health_task {
+			task.abort();
 		}
-		Ok(())
-	})
-	.await
+		let _ = conn.inner.disconnect().await;
+		events::emit(CoreEvent::ConnectionClosed { connection_id });
+	}
+}
+
+/// Stop the forwards, SFTP sessions, transfers and streaming commands riding a
+/// connection's channels — they can't outlive it, even across a reconnect.
+pub(crate) async fn close_channel_users(connection_id: &str) {
+	let sftp_ids: Vec<String> = registry::sftps_for_connection(connection_id)
+		.iter()
+		.map(|sftp| sftp.sftp_id.clone())
+		.collect();
+	transfer::fail_riding(
+		|via| match via {
+			TransferVia::Sftp { sftp_id } => sftp_ids.contains(sftp_id),
+			TransferVia::Scp {
+				connection_id: id, ..
+			} => id == connection_id,
+		},
+		"connection closed",
+	);
+	for forward in registry::forwards_for_connection(connection_id) {
+		registry::remove_forward(&forward.forward_id);
+		forward.stop().await;
+		events::emit(CoreEvent::ForwardClosed {
+			forward_id: forward.forward_id.clone(),
+		});
+	}
+	for sftp in registry::sftps_for_connection(connection_id) {
+		registry::remove_sftp(&sftp.sftp_id);
+		sftp.close().await;
+		events::emit(CoreEvent::SftpClosed {
+			sftp_id: sftp.sftp_id.clone(),
+		});
+	}
+	// Each one's reader task reports its own `ExecExited`.
+	for exec in registry::execs_for_connection(connection_id) {
+		exec.close().await;
+	}
+}
+
+/// Generate a new key pair (OpenSSH private-key string), encrypted under
+/// `passphrase` if given. Sync; no runtime needed.
+pub fn generate_key_pair(key_type: KeyType, passphrase: Option<&str>) -> Result<String, SshError> {
+	fressh_ssh::generate_key_pair(key_type, passphrase)
+}
+
+/// Describe an OpenSSH certificate (principals, validity window, CA) so the app
+/// can show it and warn before it expires. Sync.
+pub fn inspect_certificate(certificate: &str) -> Result<CertificateInfo, SshError> {
+	fressh_ssh::inspect_certificate(certificate)
+}
+
+/// Import an `ssh_config`: every concrete `Host` alias, resolved. `includes`
+/// maps paths to the files `Include` may pull in. Sync.
+pub fn parse_ssh_config(
+	config: &str,
+	includes: &HashMap<String, String>,
+) -> Result<Vec<SshConfigHost>, SshError> {
+	SshConfig::parse(config, includes)?.resolve_all()
 }
 
-/// Generate a new key pair (OpenSSH private-key string). Sync; no runtime needed.
-pub fn generate_key_pair(key_type: KeyType) -> Result<String, SshError> {
-	fressh_ssh::generate_key_pair(key_type)
+/// Resolve one alias (or bare hostname) against an `ssh_config`, so the app can
+/// connect by alias. Sync.
+pub fn resolve_ssh_config_host(
+	config: &str,
+	includes: &HashMap<String, String>,
+	alias: &str,
+) -> Result<SshConfigHost, SshError> {
+	SshConfig::parse(config, includes)?.resolve(alias)
 }
 
-/// Validate a private key, returning its canonical OpenSSH form. Sync.
-pub fn validate_private_key(pem: &str) -> Result<String, SshError> {
-	fressh_ssh::validate_private_key(pem)
+/// Validate a private key (decrypting it with `passphrase` if it is
+/// encrypted), returning its canonical OpenSSH form. Sync.
+pub fn validate_private_key(pem: &str, passphrase: Option<&str>) -> Result<String, SshError> {
+	fressh_ssh::validate_private_key(pem, passphrase)
 }
 
 #[cfg(test)]
@@ -408,6 +1182,46 @@ mod tests {
 	use super::*;
 	use crate::source::ReadSource;
 
+	fn canned_on_channel(channel_id: u32) -> ShellBackend {
+		ShellBackend {
+			channel_id,
+			..ShellBackend::canned(Bytes::from_static(b"$ "))
+		}
+	}
+
+	/// A reconnect re-binds a shell to a new channel under its old id; a shell
+	/// opened afterwards on the old channel number must not replace it.
+	#[test]
+	fn a_new_shell_after_a_reconnect_gets_its_own_id() {
+		let connection_id = "me@host:22#reconnect-test".to_string();
+		let first = register_shell(
+			connection_id.clone(),
+			"shell",
+			canned_on_channel(0),
+			None,
+			80,
+			24,
+			100,
+		);
+		let rebound = registry::shell(&first).unwrap();
+		rebound.detach();
+		rebound.rebind(canned_on_channel(5));
+		let second = register_shell(
+			connection_id.clone(),
+			"shell",
+			canned_on_channel(0),
+			None,
+			80,
+			24,
+			100,
+		);
+		assert_ne!(first, second);
+		assert!(Arc::ptr_eq(&registry::shell(&first).unwrap(), &rebound));
+		assert_eq!(registry::shells_for_connection(&connection_id).len(), 2);
+		registry::remove_shell(&first);
+		registry::remove_shell(&second);
+	}
+
 	/// The canned preview source must deliver its snippet exactly once and then
 	/// *park* — never returning `None` — so the reader loop never hits the EOF
 	/// branch that would tear the preview down. This is the load-bearing difference
@@ -1,15 +1,23 @@
 //! One-way event sink to JS (the shim supplies the concrete emitter). (§10)
 //!
 //! These are the LOW-FREQUENCY events that must reach JS: connection progress,
-//! a pending host-key decision, and close notifications. The high-frequency byte
-//! stream never comes through here — it feeds `Term` natively. (§10 data plane.)
+//! a pending host-key decision or auth prompt, connection health, forward and
+//! SFTP lifecycle, transfer progress, streamed command output, and close
+//! notifications. The high-frequency byte stream never comes through here — it
+//! feeds `Term` natively. (§10 data plane.)
 
 use std::sync::Arc;
 
 use once_cell::sync::Lazy;
 use std::sync::Mutex;
 
-use fressh_ssh::{ServerPublicKeyInfo, SshConnectionProgressEvent};
+use fressh_ssh::{
+	AgentSignRequest, AuthPromptRequest, ServerPublicKeyInfo, SshConnectionProgressEvent,
+	StreamKind,
+};
+
+use crate::known_hosts::HostKeyStatus;
+use crate::session::ShellEnd;
 
 /// Events emitted from the core to the binding shim (uniffi callback / craby Signal).
 #[derive(Debug, Clone)]
@@ -18,15 +26,142 @@ pub enum CoreEvent {
 		connection_id: String,
 		event: SshConnectionProgressEvent,
 	},
+	/// `hop` is the jump-host index being verified, `None` for the final target.
+	/// `status` is `Unknown` or `Changed` — trusted/revoked keys never park.
 	HostKeyPending {
 		connection_id: String,
+		hop: Option<u32>,
 		info: ServerPublicKeyInfo,
+		status: HostKeyStatus,
+	},
+	/// A keyboard-interactive round is waiting for answers
+	/// (`respond_to_auth_prompt`). `hop` as for `HostKeyPending`.
+	AuthPromptPending {
+		connection_id: String,
+		hop: Option<u32>,
+		request: AuthPromptRequest,
+	},
+	/// A forwarded agent wants a signature from a `confirm` key; answer with
+	/// `respond_to_agent_sign(request_id, …)`.
+	AgentSignPending {
+		connection_id: String,
+		request_id: String,
+		request: AgentSignRequest,
 	},
 	ConnectionClosed {
 		connection_id: String,
 	},
+
+	// ── Connection health (`health.rs`), only with keepalives configured.
+	/// Keepalives are going unanswered (`missed` in a row, out of `max_missed`
+	/// before the connection is declared lost). `rtt_ms` is the last good one.
+	ConnectionDegraded {
+		connection_id: String,
+		missed: u32,
+		max_missed: u32,
+		rtt_ms: Option<u32>,
+	},
+	/// A degraded connection answered again.
+	ConnectionRecovered {
+		connection_id: String,
+		rtt_ms: u32,
+	},
+	/// The connection is dead. Emitted before its shells and forwards are torn
+	/// down (their `ShellClosed` / `ForwardClosed`, then `ConnectionClosed`,
+	/// follow).
+	ConnectionLost {
+		connection_id: String,
+		reason: String,
+	},
+
+	// ── Automatic reconnect (`reconnect.rs`), only with a reconnect policy.
+	/// The connection dropped; attempt `attempt` starts after `delay_ms`. Its
+	/// shells are detached — `send_data` fails, the `Term` keeps its content.
+	Reconnecting {
+		connection_id: String,
+		attempt: u32,
+		delay_ms: u32,
+		reason: String,
+	},
+	/// Reconnected on attempt `attempt`; the surviving shells are re-bound to
+	/// fresh channels under their old ids.
+	Reconnected {
+		connection_id: String,
+		attempt: u32,
+	},
+	/// `end` says why, and how the shell exited if the server told us; it
+	/// stays available from `shell_end`.
 	ShellClosed {
 		shell_id: String,
+		end: ShellEnd,
+	},
+
+	// ── Port forwards (`forward.rs`). Traffic counters are pulled via
+	// `list_forwards`, not pushed — they change far too often for this plane.
+	/// A forward is bound and serving. `bound_port` is the real port when the
+	/// request asked for 0.
+	ForwardOpened {
+		forward_id: String,
+		connection_id: String,
+		bound_port: u16,
+	},
+	/// A forward stopped: explicitly, with its connection, or because its
+	/// listener failed (preceded by a `ForwardError`).
+	ForwardClosed {
+		forward_id: String,
+	},
+	/// Something went wrong on a forward. A single stream failing (e.g. the
+	/// target refused) leaves the forward running.
+	ForwardError {
+		forward_id: String,
+		message: String,
+	},
+
+	// ── SFTP sessions (`sftp.rs`).
+	/// An SFTP session ended: explicitly, or with its connection (including
+	/// one that is being reconnected).
+	SftpClosed {
+		sftp_id: String,
+	},
+
+	// ── File transfers (`transfer.rs`). Progress is throttled per transfer;
+	// `list_transfers` has the exact counters.
+	/// `bytes` are at the destination; `total` is the source's size.
+	TransferProgress {
+		transfer_id: String,
+		bytes: u64,
+		total: Option<u64>,
+	},
+	/// Copied, and the destination's size matches the source's.
+	TransferFinished {
+		transfer_id: String,
+		bytes: u64,
+	},
+	/// The transfer stopped on an error and is gone from `list_transfers`;
+	/// what was copied stays at the destination.
+	TransferFailed {
+		transfer_id: String,
+		message: String,
+	},
+
+	// ── Streaming commands (`exec.rs`).
+	/// Output from a command started with `start_exec`, coalesced: several
+	/// reads of one stream arrive as one event, a few per second at most.
+	ExecOutput {
+		exec_id: String,
+		stream: StreamKind,
+		data: Vec<u8>,
+	},
+	/// The command's channel closed; it is gone from the registry. `exit_code`
+	/// and `exit_signal` are whichever the server reported (neither, if the
+	/// channel was closed first). `truncated` / `timed_out`: it was stopped for
+	/// hitting the output cap / the timeout.
+	ExecExited {
+		exec_id: String,
+		exit_code: Option<i32>,
+		exit_signal: Option<String>,
+		truncated: bool,
+		timed_out: bool,
 	},
 
 	// ── Shell-integration semantic events (OSC 7 + OSC 133 + OSC 633). ─────
@@ -86,3 +221,37 @@ pub(crate) fn emit(event: CoreEvent) {
 		sink.emit(event);
 	}
 }
+
+/// Test support for asserting on emitted events. The sink is a process-wide
+/// static (last-writer-wins), so parallel tests would clobber each other's sink
+/// mid-run: hold the guard [`capture`] returns for as long as you emit.
+#[cfg(test)]
+pub(crate) mod testing {
+	use std::sync::{Arc, Mutex, MutexGuard};
+
+	use super::{set_event_sink, CoreEvent, EventSink};
+
+	#[derive(Default)]
+	pub(crate) struct Capture(Mutex<Vec<CoreEvent>>);
+
+	impl Capture {
+		pub(crate) fn events(&self) -> Vec<CoreEvent> {
+			self.0.lock().unwrap_or_else(|p| p.into_inner()).clone()
+		}
+	}
+
+	impl EventSink for Capture {
+		fn emit(&self, event: CoreEvent) {
+			self.0.lock().unwrap_or_else(|p| p.into_inner()).push(event);
+		}
+	}
+
+	/// Install a fresh capturing sink, serialized against other capturing tests.
+	pub(crate) fn capture() -> (MutexGuard<'static, ()>, Arc<Capture>) {
+		static TEST_LOCK: Mutex<()> = Mutex::new(());
+		let guard = TEST_LOCK.lock().unwrap_or_else(|p| p.into_inner());
+		let cap = Arc::new(Capture::default());
+		set_event_sink(cap.clone());
+		(guard, cap)
+	}
+}
@@ -0,0 +1,236 @@
+//! Streaming commands: an `exec` channel whose output reaches JS as it arrives,
+//! for what `run_command` can't wait out (`tail -f`, a long build).
+//!
+//! Each command is a registry entry with a reader task on the core runtime.
+//! Output is coalesced — same-stream runs merged, in arrival order — and sent
+//! as `ExecOutput` events at most once per [`OUTPUT_INTERVAL`] (sooner once
+//! [`OUTPUT_FLUSH_BYTES`] pile up). The entry goes away when the channel
+//! closes, with one `ExecExited` carrying the exit status or signal.
+//!
+//! A per-call output cap and timeout end the command early: it is sent TERM
+//! and its channel closed, and `ExecExited` says which limit was hit.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::time::{sleep_until, Instant};
+
+use fressh_ssh::{Exec, ExecEvent, ExecReader, ExecSignal, ExecWriter, SshError, StreamKind};
+
+use crate::events::{self, CoreEvent};
+use crate::{registry, runtime};
+
+/// Minimum gap between two flushes of one command's output.
+const OUTPUT_INTERVAL: Duration = Duration::from_millis(100);
+
+/// Buffered output that is flushed without waiting out the interval.
+const OUTPUT_FLUSH_BYTES: usize = 64 * 1024;
+
+/// Per-call limits for [`crate::control::start_exec`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ExecOptions {
+	/// Stdout and stderr bytes delivered, combined, before the command is
+	/// stopped. `None` is unlimited.
+	pub max_output_bytes: Option<u64>,
+	/// Wall-clock limit from the start. `None` waits indefinitely.
+	pub timeout_ms: Option<u32>,
+}
+
+pub struct ExecSession {
+	pub exec_id: String,
+	pub connection_id: String,
+	pub command: String,
+	writer: ExecWriter,
+}
+
+impl ExecSession {
+	pub(crate) fn spawn(
+		exec_id: String,
+		connection_id: String,
+		command: String,
+		exec: Exec,
+		options: ExecOptions,
+	) -> Arc<Self> {
+		runtime::handle().spawn(pump(
+			exec_id.clone(),
+			exec.reader,
+			exec.writer.clone(),
+			options,
+		));
+		Arc::new(ExecSession {
+			exec_id,
+			connection_id,
+			command,
+			writer: exec.writer,
+		})
+	}
+
+	pub async fn write(&self, data: &[u8]) -> Result<(), SshError> {
+		self.writer.send_data(data).await
+	}
+
+	pub async fn eof(&self) -> Result<(), SshError> {
+		self.writer.eof().await
+	}
+
+	pub async fn signal(&self, signal: ExecSignal) -> Result<(), SshError> {
+		self.writer.signal(signal).await
+	}
+
+	/// Close the channel. The reader task then flushes what it holds and
+	/// reports `ExecExited` — also what happens when the connection drops.
+	pub async fn close(&self) {
+		let _ = self.writer.close().await;
+	}
+}
+
+/// Output held back between two flushes, and the cap on what gets through.
+struct OutputBuffer {
+	runs: Vec<(StreamKind, Vec<u8>)>,
+	len: usize,
+	last_flush: Option<Instant>,
+	/// What the cap still lets through; `None` without a cap.
+	room: Option<u64>,
+}
+
+impl OutputBuffer {
+	fn new(cap: Option<u64>) -> Self {
+		OutputBuffer {
+			runs: Vec::new(),
+			len: 0,
+			last_flush: None,
+			room: cap,
+		}
+	}
+
+	/// Buffer `bytes`, clipped to the cap. `false` if some had to be dropped.
+	fn push(&mut self, stream: StreamKind, bytes: &[u8]) -> bool {
+		let take = match self.room {
+			Some(room) => bytes.len().min(usize::try_from(room).unwrap_or(usize::MAX)),
+			None => bytes.len(),
+		};
+		if let Some(room) = &mut self.room {
+			*room -= take as u64;
+		}
+		if take > 0 {
+			match self.runs.last_mut() {
+				Some((last, run)) if *last == stream => run.extend_from_slice(&bytes[..take]),
+				_ => self.runs.push((stream, bytes[..take].to_vec())),
+			}
+			self.len += take;
+		}
+		take == bytes.len()
+	}
+
+	/// When the buffered output should go out; `None` if there is none.
+	fn flush_at(&self, now: Instant) -> Option<Instant> {
+		if self.runs.is_empty() {
+			return None;
+		}
+		match self.last_flush {
+			Some(last) if self.len < OUTPUT_FLUSH_BYTES => Some(last + OUTPUT_INTERVAL),
+			_ => Some(now),
+		}
+	}
+
+	fn take(&mut self, now: Instant) -> Vec<(StreamKind, Vec<u8>)> {
+		self.len = 0;
+		self.last_flush = Some(now);
+		std::mem::take(&mut self.runs)
+	}
+}
+
+fn flush(exec_id: &str, out: &mut OutputBuffer) {
+	for (stream, data) in out.take(Instant::now()) {
+		events::emit(CoreEvent::ExecOutput {
+			exec_id: exec_id.to_string(),
+			stream,
+			data,
+		});
+	}
+}
+
+async fn pump(exec_id: String, mut reader: ExecReader, writer: ExecWriter, options: ExecOptions) {
+	let deadline = options
+		.timeout_ms
+		.map(|ms| Instant::now() + Duration::from_millis(ms.into()));
+	let mut out = OutputBuffer::new(options.max_output_bytes);
+	let mut exit_code = None;
+	let mut exit_signal = None;
+	let mut truncated = false;
+	let mut timed_out = false;
+	loop {
+		let flush_at = out.flush_at(Instant::now());
+		tokio::select! {
+			event = reader.recv() => match event {
+				Some(ExecEvent::Output(chunk)) => {
+					if !out.push(chunk.stream, &chunk.bytes) {
+						truncated = true;
+						break;
+					}
+				}
+				Some(ExecEvent::ExitStatus(code)) => exit_code = Some(code as i32),
+				Some(ExecEvent::ExitSignal { signal, .. }) => exit_signal = Some(signal),
+				None => break,
+			},
+			() = sleep_until(flush_at.unwrap_or_else(Instant::now)), if flush_at.is_some() => {
+				flush(&exec_id, &mut out);
+			}
+			() = sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+				timed_out = true;
+				break;
+			}
+		}
+	}
+	flush(&exec_id, &mut out);
+	if truncated || timed_out {
+		let _ = writer.signal(ExecSignal::Term).await;
+		let _ = writer.close().await;
+	}
+	registry::remove_exec(&exec_id);
+	events::emit(CoreEvent::ExecExited {
+		exec_id,
+		exit_code,
+		exit_signal,
+		truncated,
+		timed_out,
+	});
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn output_is_merged_in_order_and_capped() {
+		let mut out = OutputBuffer::new(Some(10));
+		assert!(out.push(StreamKind::Stdout, b"ab"));
+		assert!(out.push(StreamKind::Stdout, b"cd"));
+		assert!(out.push(StreamKind::Stderr, b"ef"));
+		assert!(!out.push(StreamKind::Stdout, b"ghijk"));
+		assert!(!out.push(StreamKind::Stdout, b"l"));
+		assert_eq!(
+			out.take(Instant::now()),
+			[
+				(StreamKind::Stdout, b"abcd".to_vec()),
+				(StreamKind::Stderr, b"ef".to_vec()),
+				(StreamKind::Stdout, b"ghij".to_vec()),
+			]
+		);
+	}
+
+	#[test]
+	fn flushes_are_spaced_unless_the_buffer_fills() {
+		let start = Instant::now();
+		let mut out = OutputBuffer::new(None);
+		assert_eq!(out.flush_at(start), None);
+		out.push(StreamKind::Stdout, b"first");
+		// Nothing went out yet: flush right away.
+		assert_eq!(out.flush_at(start), Some(start));
+		out.take(start);
+		out.push(StreamKind::Stdout, b"second");
+		assert_eq!(out.flush_at(start), Some(start + OUTPUT_INTERVAL));
+		out.push(StreamKind::Stdout, &vec![0; OUTPUT_FLUSH_BYTES]);
+		assert_eq!(out.flush_at(start), Some(start));
+	}
+}
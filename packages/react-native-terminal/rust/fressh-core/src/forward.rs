@@ -0,0 +1,588 @@
+//! Port forwards over a live connection's multiplexed transport.
+//!
+//! A forward is a long-lived registry entry, like a shell: it is owned by the
+//! registry (not by a JS handle), stopped explicitly or torn down with its
+//! connection on `disconnect`. The forwarded bytes never leave the .so — JS only
+//! sees lifecycle [`CoreEvent`]s and the per-forward counters in [`ForwardInfo`].
+//!
+//! A local forward (`-L`) binds a listener on the device and, per accepted
+//! socket, opens a `direct-tcpip` channel on the connection and [`pump`]s bytes
+//! both ways until both sides hit EOF. A remote forward (`-R`) is the mirror
+//! image: the server listens, hands each connection back as a `forwarded-tcpip`
+//! channel, and we dial the local target and pump the same way. A dynamic
+//! forward (`-D`) is a local forward whose target is chosen per socket: each
+//! client speaks SOCKS (see [`crate::socks`]) to name it first.
+
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::oneshot;
+use tokio::task::{JoinHandle, JoinSet};
+
+use fressh_ssh::{Connection as SshConnection, ForwardedConnection, SshError};
+
+use crate::events::{self, CoreEvent};
+use crate::socks::{self, REP5_HOST_UNREACHABLE};
+use crate::{registry, runtime};
+
+/// Read buffer per pump direction.
+const PUMP_BUF_SIZE: usize = 32 * 1024;
+
+/// What a forward does, as requested. Mirrors the OpenSSH flag it corresponds to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ForwardSpec {
+	/// `-L bind_host:bind_port:remote_host:remote_port` — listen on the device,
+	/// connect out from the server.
+	Local {
+		bind_host: String,
+		bind_port: u16,
+		remote_host: String,
+		remote_port: u16,
+	},
+	/// `-R bind_host:bind_port:local_host:local_port` — the server listens,
+	/// connections are dialed out from the device.
+	Remote {
+		bind_host: String,
+		bind_port: u16,
+		local_host: String,
+		local_port: u16,
+	},
+	/// `-D bind_host:bind_port` — a SOCKS5/SOCKS4a proxy on the device; each
+	/// `CONNECT` is connected out from the server.
+	Dynamic { bind_host: String, bind_port: u16 },
+}
+
+/// Live traffic counters for one forward. `out` = device → server (written into
+/// the SSH channel), `in` = server → device.
+#[derive(Default)]
+pub struct ForwardStats {
+	bytes_out: AtomicU64,
+	bytes_in: AtomicU64,
+	active_streams: AtomicU64,
+	total_streams: AtomicU64,
+}
+
+/// A snapshot of a forward for the control plane (`list_forwards`).
+#[derive(Debug, Clone)]
+pub struct ForwardInfo {
+	pub forward_id: String,
+	pub connection_id: String,
+	pub spec: ForwardSpec,
+	/// The port actually bound — differs from the spec's when it asked for 0.
+	pub bound_port: u16,
+	pub bytes_out: u64,
+	pub bytes_in: u64,
+	/// Streams currently being pumped.
+	pub active_streams: u64,
+	/// Streams accepted over the forward's lifetime.
+	pub total_streams: u64,
+}
+
+/// A running forward: its spec, counters, and the task serving it. Aborting the
+/// task drops its `JoinSet`, which aborts every in-flight stream with it.
+pub struct ForwardSession {
+	pub forward_id: String,
+	pub connection_id: String,
+	pub spec: ForwardSpec,
+	pub bound_port: u16,
+	conn: Arc<SshConnection>,
+	stats: Arc<ForwardStats>,
+	task: JoinHandle<()>,
+}
+
+impl ForwardSession {
+	/// Bind `bind_host:bind_port` and start serving a `-L` forward to
+	/// `remote_host:remote_port` over `conn`. Errors only if the bind fails; once
+	/// bound, per-stream failures are reported as `ForwardError` events.
+	pub(crate) async fn spawn_local(
+		forward_id: String,
+		connection_id: String,
+		conn: Arc<SshConnection>,
+		bind_host: String,
+		bind_port: u16,
+		remote_host: String,
+		remote_port: u16,
+	) -> Result<Arc<Self>, SshError> {
+		let listener = TcpListener::bind((bind_host.as_str(), bind_port)).await?;
+		let bound_port = listener.local_addr()?.port();
+		let stats = Arc::new(ForwardStats::default());
+
+		let serve = {
+			let forward_id = forward_id.clone();
+			let conn = conn.clone();
+			let stats = stats.clone();
+			let remote_host = remote_host.clone();
+			async move {
+				let mut streams = JoinSet::new();
+				loop {
+					tokio::select! {
+						accepted = listener.accept() => match accepted {
+							Ok((socket, peer)) => {
+								streams.spawn(serve_local_stream(
+									forward_id.clone(),
+									conn.clone(),
+									socket,
+									peer,
+									remote_host.clone(),
+									remote_port,
+									stats.clone(),
+								));
+							}
+							Err(e) => {
+								events::emit(CoreEvent::ForwardError {
+									forward_id: forward_id.clone(),
+									message: e.to_string(),
+								});
+								break;
+							}
+						},
+						// Reap finished streams so the set doesn't grow unbounded.
+						Some(_) = streams.join_next(), if !streams.is_empty() => {}
+					}
+				}
+				// The listener failed: the forward is dead. (An explicit stop aborts
+				// this task instead and emits `ForwardClosed` itself.)
+				registry::remove_forward(&forward_id);
+				events::emit(CoreEvent::ForwardClosed { forward_id });
+			}
+		};
+
+		let spec = ForwardSpec::Local {
+			bind_host,
+			bind_port,
+			remote_host,
+			remote_port,
+		};
+		Ok(Self::launch(
+			forward_id,
+			connection_id,
+			spec,
+			bound_port,
+			conn,
+			stats,
+			serve,
+		))
+	}
+
+	/// Ask the server to listen on `bind_host:bind_port` and serve a `-R` forward
+	/// to `local_host:local_port` (dialed from the device per connection). Errors
+	/// if the server refuses the `tcpip-forward`.
+	pub(crate) async fn spawn_remote(
+		forward_id: String,
+		connection_id: String,
+		conn: Arc<SshConnection>,
+		bind_host: String,
+		bind_port: u16,
+		local_host: String,
+		local_port: u16,
+	) -> Result<Arc<Self>, SshError> {
+		let granted = conn.request_remote_forward(&bind_host, bind_port).await?;
+		let bound_port = granted.bound_port;
+		let mut incoming = granted.incoming;
+		let stats = Arc::new(ForwardStats::default());
+
+		let serve = {
+			let forward_id = forward_id.clone();
+			let stats = stats.clone();
+			let local_host = local_host.clone();
+			async move {
+				let mut streams = JoinSet::new();
+				loop {
+					tokio::select! {
+						forwarded = incoming.recv() => match forwarded {
+							Some(forwarded) => {
+								streams.spawn(serve_remote_stream(
+									forward_id.clone(),
+									forwarded,
+									local_host.clone(),
+									local_port,
+									stats.clone(),
+								));
+							}
+							// The route was removed: the forward was cancelled.
+							None => break,
+						},
+						Some(_) = streams.join_next(), if !streams.is_empty() => {}
+					}
+				}
+				registry::remove_forward(&forward_id);
+				events::emit(CoreEvent::ForwardClosed { forward_id });
+			}
+		};
+
+		let spec = ForwardSpec::Remote {
+			bind_host,
+			bind_port,
+			local_host,
+			local_port,
+		};
+		Ok(Self::launch(
+			forward_id,
+			connection_id,
+			spec,
+			bound_port,
+			conn,
+			stats,
+			serve,
+		))
+	}
+
+	/// Bind `bind_host:bind_port` and start serving a `-D` SOCKS proxy over
+	/// `conn`. Errors only if the bind fails; a client whose handshake is bad or
+	/// whose target the server can't reach gets a SOCKS failure reply and a
+	/// `ForwardError` event, and the proxy stays up.
+	pub(crate) async fn spawn_dynamic(
+		forward_id: String,
+		connection_id: String,
+		conn: Arc<SshConnection>,
+		bind_host: String,
+		bind_port: u16,
+	) -> Result<Arc<Self>, SshError> {
+		let listener = TcpListener::bind((bind_host.as_str(), bind_port)).await?;
+		let bound_port = listener.local_addr()?.port();
+		let stats = Arc::new(ForwardStats::default());
+
+		let serve = {
+			let forward_id = forward_id.clone();
+			let conn = conn.clone();
+			let stats = stats.clone();
+			async move {
+				let mut streams = JoinSet::new();
+				loop {
+					tokio::select! {
+						accepted = listener.accept() => match accepted {
+							Ok((socket, peer)) => {
+								streams.spawn(serve_socks_stream(
+									forward_id.clone(),
+									conn.clone(),
+									socket,
+									peer,
+									stats.clone(),
+								));
+							}
+							Err(e) => {
+								events::emit(CoreEvent::ForwardError {
+									forward_id: forward_id.clone(),
+									message: e.to_string(),
+								});
+								break;
+							}
+						},
+						Some(_) = streams.join_next(), if !streams.is_empty() => {}
+					}
+				}
+				registry::remove_forward(&forward_id);
+				events::emit(CoreEvent::ForwardClosed { forward_id });
+			}
+		};
+
+		let spec = ForwardSpec::Dynamic {
+			bind_host,
+			bind_port,
+		};
+		Ok(Self::launch(
+			forward_id,
+			connection_id,
+			spec,
+			bound_port,
+			conn,
+			stats,
+			serve,
+		))
+	}
+
+	/// Register the forward and emit `ForwardOpened`, then let `serve` run — in
+	/// that order, so a forward that dies at once is removed after it was
+	/// inserted and its `ForwardClosed` follows its `ForwardOpened`. The task is
+	/// spawned first but parked until then, so `stop` can still abort it.
+	fn launch(
+		forward_id: String,
+		connection_id: String,
+		spec: ForwardSpec,
+		bound_port: u16,
+		conn: Arc<SshConnection>,
+		stats: Arc<ForwardStats>,
+		serve: impl Future<Output = ()> + Send + 'static,
+	) -> Arc<Self> {
+		let (registered, go) = oneshot::channel::<()>();
+		let task = runtime::handle().spawn(async move {
+			if go.await.is_ok() {
+				serve.await;
+			}
+		});
+		let forward = Arc::new(Self {
+			forward_id,
+			connection_id,
+			spec,
+			bound_port,
+			conn,
+			stats,
+			task,
+		});
+		registry::insert_forward(forward.clone());
+		events::emit(CoreEvent::ForwardOpened {
+			forward_id: forward.forward_id.clone(),
+			connection_id: forward.connection_id.clone(),
+			bound_port,
+		});
+		let _ = registered.send(());
+		forward
+	}
+
+	/// Snapshot the spec + counters.
+	pub fn info(&self) -> ForwardInfo {
+		ForwardInfo {
+			forward_id: self.forward_id.clone(),
+			connection_id: self.connection_id.clone(),
+			spec: self.spec.clone(),
+			bound_port: self.bound_port,
+			bytes_out: self.stats.bytes_out.load(Ordering::Relaxed),
+			bytes_in: self.stats.bytes_in.load(Ordering::Relaxed),
+			active_streams: self.stats.active_streams.load(Ordering::Relaxed),
+			total_streams: self.stats.total_streams.load(Ordering::Relaxed),
+		}
+	}
+
+	/// Stop accepting and drop every in-flight stream (closing their channels);
+	/// for `-R`, also tell the server to stop listening (`cancel-tcpip-forward`).
+	/// The caller removes the forward from the registry.
+	pub async fn stop(&self) {
+		self.task.abort();
+		if let ForwardSpec::Remote { bind_host, .. } = &self.spec {
+			let _ = self
+				.conn
+				.cancel_remote_forward(bind_host, self.bound_port)
+				.await;
+		}
+	}
+}
+
+/// One accepted `-L` socket: open the channel, then pump until both sides close.
+async fn serve_local_stream(
+	forward_id: String,
+	conn: Arc<SshConnection>,
+	socket: TcpStream,
+	peer: SocketAddr,
+	remote_host: String,
+	remote_port: u16,
+	stats: Arc<ForwardStats>,
+) {
+	let tunnel = match conn
+		.open_direct_tcpip(&remote_host, remote_port, peer)
+		.await
+	{
+		Ok(tunnel) => tunnel,
+		Err(e) => {
+			// e.g. the server couldn't reach the target. The forward stays up; this
+			// one socket is dropped (the local client sees a reset).
+			events::emit(CoreEvent::ForwardError {
+				forward_id,
+				message: e.to_string(),
+			});
+			return;
+		}
+	};
+	pump(socket, tunnel, &stats).await;
+}
+
+/// One accepted `-D` socket: read the SOCKS request, open a channel to its
+/// target, answer the client, then pump until both sides close.
+async fn serve_socks_stream(
+	forward_id: String,
+	conn: Arc<SshConnection>,
+	mut socket: TcpStream,
+	peer: SocketAddr,
+	stats: Arc<ForwardStats>,
+) {
+	let request = match socks::read_request(&mut socket).await {
+		Ok(request) => request,
+		Err(e) => {
+			events::emit(CoreEvent::ForwardError {
+				forward_id,
+				message: format!("{peer}: {e}"),
+			});
+			return;
+		}
+	};
+	let tunnel = match conn
+		.open_direct_tcpip(&request.host, request.port, peer)
+		.await
+	{
+		Ok(tunnel) => tunnel,
+		Err(e) => {
+			let _ =
+				socks::send_reply(&mut socket, request.version, false, REP5_HOST_UNREACHABLE).await;
+			events::emit(CoreEvent::ForwardError {
+				forward_id,
+				message: format!("{}:{}: {e}", request.host, request.port),
+			});
+			return;
+		}
+	};
+	if socks::send_reply(&mut socket, request.version, true, REP5_HOST_UNREACHABLE)
+		.await
+		.is_err()
+	{
+		return;
+	}
+	pump(socket, tunnel, &stats).await;
+}
+
+/// One `-R` connection handed back by the server: dial the local target, then
+/// pump until both sides close.
+async fn serve_remote_stream(
+	forward_id: String,
+	forwarded: ForwardedConnection,
+	local_host: String,
+	local_port: u16,
+	stats: Arc<ForwardStats>,
+) {
+	let socket = match TcpStream::connect((local_host.as_str(), local_port)).await {
+		Ok(socket) => socket,
+		Err(e) => {
+			// Nothing listening locally. Dropping the channel closes it; the remote
+			// client sees its connection end.
+			events::emit(CoreEvent::ForwardError {
+				forward_id,
+				message: format!("{local_host}:{local_port}: {e}"),
+			});
+			return;
+		}
+	};
+	pump(socket, forwarded.stream, &stats).await;
+}
+
+/// Shuttle bytes both ways between a local stream and an SSH channel stream until
+/// both directions reach EOF (each EOF is propagated as a half-close), counting
+/// them into `stats`. An error either way ends both: the streams are dropped,
+/// closing the socket and the channel.
+pub(crate) async fn pump<L, R>(local: L, remote: R, stats: &ForwardStats)
+where
+	L: AsyncRead + AsyncWrite + Unpin,
+	R: AsyncRead + AsyncWrite + Unpin,
+{
+	stats.active_streams.fetch_add(1, Ordering::Relaxed);
+	stats.total_streams.fetch_add(1, Ordering::Relaxed);
+	let (mut local_rd, mut local_wr) = tokio::io::split(local);
+	let (mut remote_rd, mut remote_wr) = tokio::io::split(remote);
+	let _ = tokio::try_join!(
+		copy_counted(&mut local_rd, &mut remote_wr, &stats.bytes_out),
+		copy_counted(&mut remote_rd, &mut local_wr, &stats.bytes_in),
+	);
+	stats.active_streams.fetch_sub(1, Ordering::Relaxed);
+}
+
+/// `tokio::io::copy` that bumps `counter` as it goes (so the counters are live
+/// mid-transfer, not only at close) and shuts the writer down on EOF.
+async fn copy_counted<R, W>(rd: &mut R, wr: &mut W, counter: &AtomicU64) -> std::io::Result<()>
+where
+	R: AsyncRead + Unpin,
+	W: AsyncWrite + Unpin,
+{
+	let mut buf = vec![0u8; PUMP_BUF_SIZE];
+	loop {
+		let n = rd.read(&mut buf).await?;
+		if n == 0 {
+			return wr.shutdown().await;
+		}
+		wr.write_all(&buf[..n]).await?;
+		counter.fetch_add(n as u64, Ordering::Relaxed);
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::*;
+
+	/// Both directions are copied, counted, and each EOF ends the pump — the
+	/// contract every forward kind relies on.
+	#[test]
+	fn pump_copies_and_counts_both_directions() {
+		runtime::handle().block_on(async {
+			let (local, mut local_peer) = tokio::io::duplex(64);
+			let (remote, mut remote_peer) = tokio::io::duplex(64);
+			let stats = ForwardStats::default();
+
+			let peers = async {
+				local_peer.write_all(b"hello").await.unwrap();
+				local_peer.shutdown().await.unwrap();
+				remote_peer.write_all(b"hi").await.unwrap();
+				remote_peer.shutdown().await.unwrap();
+
+				let mut got_remote = Vec::new();
+				remote_peer.read_to_end(&mut got_remote).await.unwrap();
+				let mut got_local = Vec::new();
+				local_peer.read_to_end(&mut got_local).await.unwrap();
+				(got_local, got_remote)
+			};
+			let ((got_local, got_remote), ()) = tokio::join!(peers, pump(local, remote, &stats));
+
+			assert_eq!(got_remote, b"hello");
+			assert_eq!(got_local, b"hi");
+			assert_eq!(stats.bytes_out.load(Ordering::Relaxed), 5);
+			assert_eq!(stats.bytes_in.load(Ordering::Relaxed), 2);
+			assert_eq!(stats.active_streams.load(Ordering::Relaxed), 0);
+			assert_eq!(stats.total_streams.load(Ordering::Relaxed), 1);
+		});
+	}
+
+	/// Never readable; every write fails — a channel whose peer has gone.
+	struct Broken;
+
+	impl AsyncRead for Broken {
+		fn poll_read(
+			self: std::pin::Pin<&mut Self>,
+			_cx: &mut std::task::Context<'_>,
+			_buf: &mut tokio::io::ReadBuf<'_>,
+		) -> std::task::Poll<std::io::Result<()>> {
+			std::task::Poll::Pending
+		}
+	}
+
+	impl AsyncWrite for Broken {
+		fn poll_write(
+			self: std::pin::Pin<&mut Self>,
+			_cx: &mut std::task::Context<'_>,
+			_buf: &[u8],
+		) -> std::task::Poll<std::io::Result<usize>> {
+			std::task::Poll::Ready(Err(std::io::ErrorKind::BrokenPipe.into()))
+		}
+
+		fn poll_flush(
+			self: std::pin::Pin<&mut Self>,
+			_cx: &mut std::task::Context<'_>,
+		) -> std::task::Poll<std::io::Result<()>> {
+			std::task::Poll::Ready(Ok(()))
+		}
+
+		fn poll_shutdown(
+			self: std::pin::Pin<&mut Self>,
+			_cx: &mut std::task::Context<'_>,
+		) -> std::task::Poll<std::io::Result<()>> {
+			std::task::Poll::Ready(Ok(()))
+		}
+	}
+
+	#[test]
+	fn an_error_one_way_ends_the_other() {
+		runtime::handle().block_on(async {
+			let (local, mut local_peer) = tokio::io::duplex(64);
+			let stats = ForwardStats::default();
+			local_peer.write_all(b"hello").await.unwrap();
+
+			tokio::time::timeout(Duration::from_secs(5), pump(local, Broken, &stats))
+				.await
+				.expect("the idle direction kept the pump alive");
+			// The local side was dropped, so its peer sees EOF.
+			let mut rest = Vec::new();
+			local_peer.read_to_end(&mut rest).await.unwrap();
+			assert_eq!(stats.active_streams.load(Ordering::Relaxed), 0);
+		});
+	}
+}
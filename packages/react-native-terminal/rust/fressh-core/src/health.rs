@@ -0,0 +1,186 @@
+//! Connection health: a per-connection keepalive monitor.
+//!
+//! On mobile the network vanishes without a FIN — a tunnel, a Wi-Fi handoff, a
+//! suspended radio — and without traffic a dead session looks alive forever.
+//! When the connection was made with a [`KeepaliveConfig`], this task pings the
+//! server every interval and times the reply. A missed reply emits
+//! `ConnectionDegraded`; an answer after that, `ConnectionRecovered`; too many
+//! misses in a row (or the session dying under us) hands the connection to
+//! [`reconnect::connection_lost`], which either reconnects or emits
+//! `ConnectionLost` and tears it down.
+
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+
+use fressh_ssh::{Connection as SshConnection, KeepaliveConfig, SshError};
+
+use crate::events::{self, CoreEvent};
+use crate::{reconnect, runtime};
+
+/// Start monitoring `conn`. The task lives until the connection is lost or
+/// the returned handle is aborted (by teardown).
+pub(crate) fn spawn(
+	connection_id: String,
+	conn: Arc<SshConnection>,
+	keepalive: KeepaliveConfig,
+) -> JoinHandle<()> {
+	runtime::handle().spawn(async move {
+		let conn = &conn;
+		let reason = monitor(
+			&connection_id,
+			keepalive.interval(),
+			keepalive.max_missed,
+			|| conn.ping(),
+		)
+		.await;
+		reconnect::connection_lost(connection_id, reason);
+	})
+}
+
+/// Ping every `interval` until the connection is lost; returns why.
+async fn monitor<F, Fut>(
+	connection_id: &str,
+	interval: Duration,
+	max_missed: u32,
+	mut ping: F,
+) -> String
+where
+	F: FnMut() -> Fut,
+	Fut: Future<Output = Result<Duration, SshError>>,
+{
+	let mut missed = 0u32;
+	let mut rtt_ms: Option<u32> = None;
+	loop {
+		tokio::time::sleep(interval).await;
+		match tokio::time::timeout(interval, ping()).await {
+			Ok(Ok(rtt)) => {
+				let ms = u32::try_from(rtt.as_millis()).unwrap_or(u32::MAX);
+				if missed > 0 {
+					events::emit(CoreEvent::ConnectionRecovered {
+						connection_id: connection_id.to_string(),
+						rtt_ms: ms,
+					});
+				}
+				missed = 0;
+				rtt_ms = Some(ms);
+			}
+			Ok(Err(e)) => return e.to_string(),
+			Err(_elapsed) => {
+				missed += 1;
+				// 0 = never give up (as russh's `keepalive_max`).
+				if max_missed != 0 && missed >= max_missed {
+					return format!("no reply to {missed} keepalives");
+				}
+				events::emit(CoreEvent::ConnectionDegraded {
+					connection_id: connection_id.to_string(),
+					missed,
+					max_missed,
+					rtt_ms,
+				});
+			}
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::collections::VecDeque;
+
+	use futures::future::BoxFuture;
+
+	use super::*;
+	use crate::events::testing::capture;
+
+	/// How the server treats one ping.
+	#[derive(Clone, Copy)]
+	enum Reply {
+		After(Duration),
+		Never,
+		Dead,
+	}
+
+	/// Run the monitor over a scripted server (gone once the script runs out);
+	/// returns the loss reason and the health events.
+	fn watch(max_missed: u32, script: &[Reply]) -> (String, Vec<CoreEvent>) {
+		let (_guard, cap) = capture();
+		let mut script: VecDeque<Reply> = script.iter().copied().collect();
+		let ping = move || -> BoxFuture<'static, Result<Duration, SshError>> {
+			match script.pop_front().unwrap_or(Reply::Dead) {
+				Reply::After(rtt) => Box::pin(async move {
+					tokio::time::sleep(rtt).await;
+					Ok(rtt)
+				}),
+				Reply::Never => Box::pin(std::future::pending()),
+				Reply::Dead => Box::pin(async { Err(SshError::Disconnected) }),
+			}
+		};
+		let id = "me@host:22#health-test";
+		let reason =
+			runtime::handle().block_on(monitor(id, Duration::from_millis(50), max_missed, ping));
+		let events = cap
+			.events()
+			.into_iter()
+			.filter(|e| {
+				matches!(e, CoreEvent::ConnectionDegraded { connection_id, .. }
+					| CoreEvent::ConnectionRecovered { connection_id, .. } if connection_id == id)
+			})
+			.collect();
+		(reason, events)
+	}
+
+	#[test]
+	fn misses_degrade_then_an_answer_recovers() {
+		let ms = Duration::from_millis;
+		let (reason, events) = watch(
+			3,
+			&[
+				Reply::After(ms(5)),
+				Reply::Never,
+				Reply::Never,
+				Reply::After(ms(10)),
+				Reply::Dead,
+			],
+		);
+		assert_eq!(reason, "Disconnected");
+		assert!(
+			matches!(
+				events.as_slice(),
+				[
+					CoreEvent::ConnectionDegraded {
+						missed: 1,
+						max_missed: 3,
+						rtt_ms: Some(5..),
+						..
+					},
+					CoreEvent::ConnectionDegraded {
+						missed: 2,
+						rtt_ms: Some(5..),
+						..
+					},
+					CoreEvent::ConnectionRecovered { rtt_ms: 10.., .. },
+				]
+			),
+			"{events:?}"
+		);
+	}
+
+	#[test]
+	fn too_many_misses_lose_the_connection() {
+		let (reason, events) = watch(2, &[Reply::Never, Reply::Never, Reply::Dead]);
+		assert_eq!(reason, "no reply to 2 keepalives");
+		assert!(
+			matches!(
+				events.as_slice(),
+				[CoreEvent::ConnectionDegraded {
+					missed: 1,
+					rtt_ms: None,
+					..
+				}]
+			),
+			"{events:?}"
+		);
+	}
+}
@@ -2,6 +2,13 @@
 //! server key, we emit a `HostKeyPending` event to JS, park a `oneshot` keyed by
 //! the (pre-assigned) connection id, and resume when JS calls
 //! [`respond_to_host_key`]. Expressible identically in uniffi and craby.
+//!
+//! Keys already vouched for by the app's `known_hosts` ([`crate::known_hosts`])
+//! are settled without parking: trusted ones accepted, revoked ones refused.
+//!
+//! Connections through jump hosts verify every hop in turn. The hops are strictly
+//! sequential, so one parked decision per connection id is enough; the event's
+//! `hop` tells JS which host it is being asked about.
 
 use dashmap::DashMap;
 use futures::future::BoxFuture;
@@ -11,25 +18,37 @@ use tokio::sync::oneshot;
 use fressh_ssh::{HostKeyVerifier, ServerPublicKeyInfo};
 
 use crate::events::{self, CoreEvent};
+use crate::known_hosts::{self, HostKeyStatus};
 
 static WAITERS: Lazy<DashMap<String, oneshot::Sender<bool>>> = Lazy::new(DashMap::new);
 
 /// The [`HostKeyVerifier`] handed to `fressh-ssh`'s `connect`. Bound to the
 /// connection id assigned before the handshake so JS can route its answer back.
+/// One per hop: `hop` is the jump-host index, `None` for the final target.
 pub(crate) struct ParkingVerifier {
 	pub connection_id: String,
+	pub hop: Option<u32>,
 }
 
 impl HostKeyVerifier for ParkingVerifier {
 	fn verify(&self, info: ServerPublicKeyInfo) -> BoxFuture<'static, bool> {
 		let connection_id = self.connection_id.clone();
+		let hop = self.hop;
 		Box::pin(async move {
+			let status = known_hosts::check_server_key(&info);
+			match status {
+				HostKeyStatus::Trusted => return true,
+				HostKeyStatus::Revoked => return false,
+				HostKeyStatus::Changed | HostKeyStatus::Unknown => {}
+			}
 			let (tx, rx) = oneshot::channel();
 			// If a previous attempt for this id is still parked, drop it.
 			WAITERS.insert(connection_id.clone(), tx);
 			events::emit(CoreEvent::HostKeyPending {
 				connection_id,
+				hop,
 				info,
+				status,
 			});
 			// JS resolves via respond_to_host_key; default to reject if dropped.
 			rx.await.unwrap_or(false)
@@ -0,0 +1,388 @@
+//! A Rust `known_hosts` engine, consulted before host-key park/resume (§7).
+//!
+//! The app hands us its `known_hosts` text ([`set_known_hosts`]); the
+//! `ParkingVerifier` checks every server key against it first. A match is
+//! accepted without bothering the user, a `@revoked` key is refused outright,
+//! and only unknown or *changed* keys fall through to `HostKeyPending` — with
+//! the [`HostKeyStatus`] attached so the app can shout about a changed key.
+//!
+//! Understands OpenSSH syntax: comma-separated patterns with `*`/`?` wildcards
+//! and `!` negation, `[host]:port` for non-22 ports, hashed `|1|salt|hmac`
+//! names, and the `@revoked` marker. Keys are compared as raw blobs (the
+//! base64 field), so no key parsing is needed.
+//!
+//! Host certificates are not supported: russh 0.54 can't negotiate the
+//! `*-cert-v01` host-key algorithms, so a server never presents one to check
+//! against a CA. `@cert-authority` lines are skipped like any other unknown
+//! marker (and not counted), so a CA key never trusts — or is mistaken for —
+//! a host key.
+
+use std::sync::RwLock;
+
+use base64::Engine as _;
+use hmac::{Hmac, Mac};
+use once_cell::sync::Lazy;
+use sha1::Sha1;
+
+use fressh_ssh::ServerPublicKeyInfo;
+
+static KNOWN_HOSTS: Lazy<RwLock<KnownHosts>> = Lazy::new(|| RwLock::new(KnownHosts::default()));
+
+/// The verdict on a server key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HostKeyStatus {
+	/// Listed for this host.
+	Trusted,
+	/// Listed under `@revoked`: never accept it.
+	Revoked,
+	/// This host is listed with a different key of the same type — either the
+	/// server was reinstalled or someone is in the middle.
+	Changed,
+	/// Nothing on record for this host and key type.
+	Unknown,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Marker {
+	None,
+	Revoked,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HostPatterns {
+	/// `|1|base64(salt)|base64(HMAC-SHA1(salt, name))`
+	Hashed { salt: Vec<u8>, hash: Vec<u8> },
+	/// `(negated, lowercase pattern)` per comma-separated item.
+	Plain(Vec<(bool, String)>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Entry {
+	marker: Marker,
+	hosts: HostPatterns,
+	key_type: String,
+	key_blob: Vec<u8>,
+}
+
+/// A parsed `known_hosts` file.
+#[derive(Debug, Clone, Default)]
+pub struct KnownHosts {
+	entries: Vec<Entry>,
+}
+
+/// The names a host is looked up under: `host` (or `[host]:port` off port 22),
+/// plus the same for its IP when known.
+fn lookup_names(host: &str, port: u16, remote_ip: Option<&str>) -> Vec<String> {
+	let name = |h: &str| {
+		let h = h.to_ascii_lowercase();
+		if port == 22 {
+			h
+		} else {
+			format!("[{h}]:{port}")
+		}
+	};
+	let mut names = vec![name(host)];
+	if let Some(ip) = remote_ip.filter(|ip| !ip.eq_ignore_ascii_case(host)) {
+		names.push(name(ip));
+	}
+	names
+}
+
+/// OpenSSH `match_pattern`: `*` matches any run, `?` any one character.
+fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+	match (pattern.split_first(), name.split_first()) {
+		(None, None) => true,
+		(Some((b'*', rest)), _) => {
+			wildcard_match(rest, name) || (!name.is_empty() && wildcard_match(pattern, &name[1..]))
+		}
+		(Some((b'?', rest)), Some((_, name_rest))) => wildcard_match(rest, name_rest),
+		(Some((p, rest)), Some((n, name_rest))) if p == n => wildcard_match(rest, name_rest),
+		_ => false,
+	}
+}
+
+fn decode_b64(s: &str) -> Option<Vec<u8>> {
+	base64::engine::general_purpose::STANDARD.decode(s).ok()
+}
+
+impl HostPatterns {
+	fn parse(field: &str) -> Option<Self> {
+		if let Some(hashed) = field.strip_prefix("|1|") {
+			let (salt, hash) = hashed.split_once('|')?;
+			return Some(HostPatterns::Hashed {
+				salt: decode_b64(salt)?,
+				hash: decode_b64(hash)?,
+			});
+		}
+		Some(HostPatterns::Plain(
+			field
+				.split(',')
+				.filter(|p| !p.is_empty())
+				.map(|p| match p.strip_prefix('!') {
+					Some(p) => (true, p.to_ascii_lowercase()),
+					None => (false, p.to_ascii_lowercase()),
+				})
+				.collect(),
+		))
+	}
+
+	/// A line applies if some name matches a positive pattern and no name
+	/// matches a negated one.
+	fn matches(&self, names: &[String]) -> bool {
+		match self {
+			HostPatterns::Hashed { salt, hash } => names.iter().any(|name| {
+				let Ok(mut mac) = Hmac::<Sha1>::new_from_slice(salt) else {
+					return false;
+				};
+				mac.update(name.as_bytes());
+				mac.verify_slice(hash).is_ok()
+			}),
+			HostPatterns::Plain(patterns) => {
+				let hit = |negated: bool| {
+					patterns.iter().any(|(neg, p)| {
+						*neg == negated
+							&& names
+								.iter()
+								.any(|n| wildcard_match(p.as_bytes(), n.as_bytes()))
+					})
+				};
+				hit(false) && !hit(true)
+			}
+		}
+	}
+}
+
+impl Entry {
+	fn parse(line: &str) -> Option<Self> {
+		let line = line.trim();
+		if line.is_empty() || line.starts_with('#') {
+			return None;
+		}
+		let mut fields = line.split_whitespace();
+		let mut first = fields.next()?;
+		let marker = match first {
+			"@revoked" => Marker::Revoked,
+			m if m.starts_with('@') => return None,
+			_ => Marker::None,
+		};
+		if marker != Marker::None {
+			first = fields.next()?;
+		}
+		Some(Entry {
+			marker,
+			hosts: HostPatterns::parse(first)?,
+			key_type: fields.next()?.to_string(),
+			key_blob: decode_b64(fields.next()?)?,
+		})
+	}
+}
+
+impl KnownHosts {
+	/// Parse `known_hosts` text. Lines we can't make sense of are skipped, as
+	/// OpenSSH does.
+	pub fn parse(text: &str) -> Self {
+		KnownHosts {
+			entries: text.lines().filter_map(Entry::parse).collect(),
+		}
+	}
+
+	pub fn len(&self) -> usize {
+		self.entries.len()
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.entries.is_empty()
+	}
+
+	fn entries_for<'a>(
+		&'a self,
+		names: &'a [String],
+		marker: Marker,
+	) -> impl Iterator<Item = &'a Entry> + 'a {
+		self.entries
+			.iter()
+			.filter(move |e| e.marker == marker && e.hosts.matches(names))
+	}
+
+	/// Check a plain server key (`key_type` + base64 blob, as in
+	/// [`ServerPublicKeyInfo`]).
+	pub fn check(
+		&self,
+		host: &str,
+		port: u16,
+		remote_ip: Option<&str>,
+		key_type: &str,
+		key_base64: &str,
+	) -> HostKeyStatus {
+		let Some(blob) = decode_b64(key_base64) else {
+			return HostKeyStatus::Unknown;
+		};
+		let names = lookup_names(host, port, remote_ip);
+		self.check_blob(&names, key_type, &blob)
+	}
+
+	fn check_blob(&self, names: &[String], key_type: &str, blob: &[u8]) -> HostKeyStatus {
+		if self
+			.entries_for(names, Marker::Revoked)
+			.any(|e| e.key_blob == blob)
+		{
+			return HostKeyStatus::Revoked;
+		}
+		let mut changed = false;
+		for entry in self.entries_for(names, Marker::None) {
+			if entry.key_blob == blob {
+				return HostKeyStatus::Trusted;
+			}
+			changed |= entry.key_type == key_type;
+		}
+		if changed {
+			HostKeyStatus::Changed
+		} else {
+			HostKeyStatus::Unknown
+		}
+	}
+}
+
+/// Replace the known-hosts database the verifier consults. Returns how many
+/// entries were understood.
+pub fn set_known_hosts(text: &str) -> usize {
+	let parsed = KnownHosts::parse(text);
+	let n = parsed.len();
+	*KNOWN_HOSTS.write().unwrap_or_else(|p| p.into_inner()) = parsed;
+	n
+}
+
+/// Check a server key from a live handshake against the installed database.
+pub(crate) fn check_server_key(info: &ServerPublicKeyInfo) -> HostKeyStatus {
+	KNOWN_HOSTS.read().unwrap_or_else(|p| p.into_inner()).check(
+		&info.host,
+		info.port,
+		info.remote_ip.as_deref(),
+		&info.algorithm,
+		&info.key_base64,
+	)
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const HOST_KEY: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIIm0mp1wpXBDwxBq3xnLM/ZhjLjRUfNWPad74jbb6b3C";
+	const OTHER_KEY: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIFE165uJLn4hPVerV8KLAvkeXW2Gbr+JzNSSNVKO0yGy";
+	const CA_KEY: &str = "AAAAC3NzaC1lZDI1NTE5AAAAIPPbfEpiDEA23mK8mZVNYOZxzG7BQVbX+JAHTdS+XrLM";
+
+	fn check(kh: &KnownHosts, host: &str, port: u16, key: &str) -> HostKeyStatus {
+		kh.check(host, port, None, "ssh-ed25519", key)
+	}
+
+	#[test]
+	fn plain_entries_trust_change_and_unknown() {
+		let kh = KnownHosts::parse(&format!(
+			"# comment\n\nexample.com,10.0.0.1 ssh-ed25519 {HOST_KEY} me@laptop\n\
+			 garbage line\n[alt.example.com]:2222 ssh-ed25519 {HOST_KEY}\n"
+		));
+		assert_eq!(kh.len(), 2);
+		assert_eq!(
+			check(&kh, "example.com", 22, HOST_KEY),
+			HostKeyStatus::Trusted
+		);
+		assert_eq!(
+			check(&kh, "EXAMPLE.com", 22, HOST_KEY),
+			HostKeyStatus::Trusted
+		);
+		assert_eq!(
+			check(&kh, "example.com", 22, OTHER_KEY),
+			HostKeyStatus::Changed
+		);
+		assert_eq!(
+			check(&kh, "example.com", 2200, HOST_KEY),
+			HostKeyStatus::Unknown
+		);
+		assert_eq!(
+			check(&kh, "alt.example.com", 2222, HOST_KEY),
+			HostKeyStatus::Trusted
+		);
+		assert_eq!(
+			check(&kh, "alt.example.com", 22, HOST_KEY),
+			HostKeyStatus::Unknown
+		);
+		assert_eq!(
+			kh.check("db", 22, Some("10.0.0.1"), "ssh-ed25519", HOST_KEY),
+			HostKeyStatus::Trusted,
+			"the IP alias matches too"
+		);
+		// A different key type isn't a change, just not on record.
+		assert_eq!(
+			kh.check("example.com", 22, None, "ssh-rsa", OTHER_KEY),
+			HostKeyStatus::Unknown
+		);
+	}
+
+	#[test]
+	fn wildcards_and_negation() {
+		let kh = KnownHosts::parse(&format!(
+			"*.internal,!bastion.internal ssh-ed25519 {HOST_KEY}\n\
+			 [web?.example.com]:* ssh-ed25519 {HOST_KEY}\n"
+		));
+		assert_eq!(
+			check(&kh, "db.internal", 22, HOST_KEY),
+			HostKeyStatus::Trusted
+		);
+		assert_eq!(
+			check(&kh, "bastion.internal", 22, HOST_KEY),
+			HostKeyStatus::Unknown
+		);
+		assert_eq!(
+			check(&kh, "web1.example.com", 8022, HOST_KEY),
+			HostKeyStatus::Trusted
+		);
+		assert_eq!(
+			check(&kh, "web10.example.com", 8022, HOST_KEY),
+			HostKeyStatus::Unknown
+		);
+	}
+
+	#[test]
+	fn hashed_hosts() {
+		// `ssh-keygen -H` over `example.com` and `[alt.example.com]:2222`.
+		let kh = KnownHosts::parse(&format!(
+			"|1|PCYnekNtdYh44wOZtL+QqnEHgIQ=|ohzZKqQhG8v9iJ85593B8uyebgE= ssh-ed25519 {HOST_KEY}\n\
+			 |1|QzCovZeFTDaIrsDHt/8JTbT3t0U=|IETg1ApddZrFojZC1jS9sv6tqq4= ssh-ed25519 {HOST_KEY}\n"
+		));
+		assert_eq!(
+			check(&kh, "example.com", 22, HOST_KEY),
+			HostKeyStatus::Trusted
+		);
+		assert_eq!(
+			check(&kh, "alt.example.com", 2222, HOST_KEY),
+			HostKeyStatus::Trusted
+		);
+		assert_eq!(
+			check(&kh, "example.org", 22, HOST_KEY),
+			HostKeyStatus::Unknown
+		);
+	}
+
+	#[test]
+	fn revoked_wins_over_trusted() {
+		let kh = KnownHosts::parse(&format!(
+			"example.com ssh-ed25519 {HOST_KEY}\n@revoked * ssh-ed25519 {HOST_KEY}\n"
+		));
+		assert_eq!(
+			check(&kh, "example.com", 22, HOST_KEY),
+			HostKeyStatus::Revoked
+		);
+	}
+
+	#[test]
+	fn cert_authority_lines_are_skipped() {
+		let kh = KnownHosts::parse(&format!(
+			"@cert-authority *.example.com ssh-ed25519 {CA_KEY}\n"
+		));
+		assert!(kh.is_empty(), "host certificates aren't supported");
+		assert_eq!(
+			check(&kh, "host.example.com", 22, CA_KEY),
+			HostKeyStatus::Unknown
+		);
+	}
+}
@@ -14,33 +14,60 @@
 //! The registry owns sessions (tmux-style). Dropping a JS handle can't kill a
 //! session; only explicit `disconnect`/`close_shell` removes the pin. (§7, §9)
 
+pub mod agent;
+pub mod auth_prompt;
 pub mod control;
 pub mod events;
+pub mod exec;
+pub mod forward;
+mod health;
 pub mod host_key;
+pub mod known_hosts;
+pub mod mosh;
 pub mod osc;
+mod reconnect;
 pub mod registry;
 pub mod runtime;
 pub mod session;
+pub mod sftp;
+mod socks;
 pub mod source;
+pub mod transfer;
 
 // --- control plane (wrapped by the binding shim) --------------------------
 pub use control::{
-	close_preview, close_shell, connect, create_preview, disconnect, generate_key_pair, resize,
-	respond_to_host_key, run_command, scroll, selection_clear, selection_start, selection_text,
-	selection_update, send_data, set_cursor_default_blinking, set_render_metrics, start_shell,
-	validate_private_key, SelectionKind,
+	agent_add_key, agent_list_keys, agent_remove_all, agent_remove_key, cancel_transfer,
+	close_preview, close_sftp, close_shell, connect, connection_info, create_preview, disconnect,
+	exec_close, exec_eof, exec_signal, exec_write, generate_key_pair, inspect_certificate,
+	list_forwards, list_transfers, open_sftp, parse_ssh_config, pause_transfer,
+	request_remote_forward, resize, resolve_ssh_config_host, respond_to_agent_sign,
+	respond_to_auth_prompt, respond_to_host_key, resume_transfer, run_command,
+	run_command_in_shell_context, scroll, selection_clear, selection_start, selection_text,
+	selection_update, send_data, set_cursor_default_blinking, set_known_hosts, set_render_metrics,
+	sftp_lstat, sftp_mkdir, sftp_read, sftp_read_dir, sftp_realpath, sftp_remove, sftp_rename,
+	sftp_stat, sftp_symlink, sftp_write, shell_end, start_download, start_dynamic_forward,
+	start_exec, start_local_forward, start_mosh_shell, start_scp_download, start_scp_upload,
+	start_shell, start_upload, stop_forward, validate_private_key, SelectionKind, StartedShell,
 };
+pub use exec::ExecOptions;
+pub use forward::{ForwardInfo, ForwardSpec};
+pub use known_hosts::HostKeyStatus;
+pub use transfer::{TransferDirection, TransferInfo, TransferState, TransferVia};
 
 // --- event plane (the shim installs the concrete sink) --------------------
 pub use events::{set_event_sink, CoreEvent, EventSink};
 
 // --- render plane (the Nitro view's C-ABI reads this) ---------------------
 pub use registry::{shell_input_idle_ms, shell_term};
-pub use session::{CoreListener, SharedTerm};
+pub use session::{CoreListener, SharedTerm, ShellCloseReason, ShellEnd};
 
 // --- re-exported SSH value types crossing the shim/render boundary --------
 pub use fressh_ssh::{
-	CommandOutput, ConnectionDetails, KeyType, Security, ServerPublicKeyInfo,
-	SshConnectionProgressEvent, SshError, StartShellOptions, StreamKind, TerminalMode,
-	TerminalPixelSize, TerminalSize, TerminalType,
+	AgentKeyInfo, AgentSignRequest, AlgorithmPreferences, AuthPrompt, AuthPromptRequest,
+	CertificateInfo, CertificateType, CommandOutput, ConnectConfig, ConnectTimeouts,
+	ConnectionDetails, ExecSignal, IpPreference, KeepaliveConfig, KeyType, NegotiatedAlgorithms,
+	ProxyKind, ReconnectConfig, ScpOptions, Security, ServerPublicKeyInfo, SftpEntry, SftpFileType,
+	SftpStat, SshConfigHop, SshConfigHost, SshConnectionInfo, SshConnectionProgressEvent, SshError,
+	StartShellOptions, StreamKind, TerminalMode, TerminalPixelSize, TerminalSize, TerminalType,
+	UpstreamProxy,
 };
@@ -0,0 +1,526 @@
+//! Mosh transport: a roaming-tolerant shell over UDP, as a `Term` byte source.
+//!
+//! SSH is only the bootstrap: [`bootstrap`] runs `mosh-server new` on an open
+//! connection, which prints `MOSH CONNECT <port> <key>` and detaches. From then
+//! on the shell is mosh's State Synchronization Protocol over UDP, encrypted
+//! with AES-128-OCB under that key — it survives the SSH connection dropping,
+//! the device changing networks (we re-bind the socket when the server goes
+//! quiet; the server follows our new address), and long silences.
+//!
+//! SSP synchronizes two *states*, not byte streams. Ours is the user stream
+//! (keystrokes and resizes); we send the server "apply these events to state
+//! `acked` to get state `n`" until it acknowledges `n`. The server's is its
+//! screen; it sends "apply these escape sequences to screen `m` to get screen
+//! `n`", and those escape sequences are exactly what the [`MoshReader`] hands
+//! the reader loop — so the same durable `Term` is fed as for SSH. We keep only
+//! the current screen (the `Term`), so a diff from any other base is dropped;
+//! the server falls back to diffing from our last acknowledged state within a
+//! round trip. No local echo prediction.
+
+mod ocb;
+mod wire;
+
+use std::collections::VecDeque;
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+
+use base64::Engine as _;
+use bytes::Bytes;
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+
+use fressh_ssh::{Connection as SshConnection, SshError};
+
+use crate::runtime;
+use ocb::Ocb;
+use wire::{Assembly, Instruction, UserEvent};
+
+/// Don't retransmit sooner than this, or later (mosh's `MIN_RTO`/`MAX_RTO`).
+const MIN_RTO: Duration = Duration::from_millis(50);
+const MAX_RTO: Duration = Duration::from_millis(1000);
+/// Send an (empty) heartbeat after this much silence, so the server keeps
+/// our address and knows we're alive.
+const HEARTBEAT: Duration = Duration::from_secs(3);
+/// Heard nothing for this long: assume our address went away (Wi-Fi → LTE)
+/// and send from a fresh socket.
+const PORT_HOP: Duration = Duration::from_secs(10);
+/// How long a close waits for the server to confirm the shutdown.
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(2);
+const TICK: Duration = Duration::from_millis(50);
+
+/// What `mosh-server new` printed: where to send, and the session key.
+#[derive(Debug, PartialEq)]
+pub struct MoshConnect {
+	pub port: u16,
+	key: [u8; 16],
+}
+
+impl MoshConnect {
+	/// Find the `MOSH CONNECT <port> <key>` line in `mosh-server`'s output.
+	pub fn parse(output: &str) -> Option<Self> {
+		output.lines().find_map(|line| {
+			let mut words = line
+				.trim()
+				.strip_prefix("MOSH CONNECT ")?
+				.split_whitespace();
+			let port = words.next()?.parse().ok()?;
+			let key = base64::engine::general_purpose::STANDARD_NO_PAD
+				.decode(words.next()?.trim_end_matches('='))
+				.ok()?
+				.try_into()
+				.ok()?;
+			Some(Self { port, key })
+		})
+	}
+}
+
+/// Start a `mosh-server` for this connection's user (`server` is its command,
+/// `mosh-server` unless it lives off the `PATH`).
+pub async fn bootstrap(conn: &SshConnection, server: &str) -> Result<MoshConnect, SshError> {
+	// `-s`: bind where the SSH connection came in. `-l`: mosh-server refuses
+	// to start without a UTF-8 locale, which a non-interactive exec may lack;
+	// `C.UTF-8` is the one most hosts have without generating any.
+	let command = format!("{server} new -s -c 256 -l LANG=C.UTF-8");
+	let output = conn.exec_command(&command).await?;
+	MoshConnect::parse(&String::from_utf8_lossy(&output.stdout)).ok_or_else(|| {
+		let stderr = String::from_utf8_lossy(&output.stderr);
+		SshError::Mosh(match stderr.trim() {
+			"" => format!("`{server}` printed no MOSH CONNECT line"),
+			err => err.lines().last().unwrap_or(err).to_string(),
+		})
+	})
+}
+
+/// Reach the server `connect` describes at `ip` — the address the SSH
+/// connection reached, which is where `-s` bound it — and start the transport
+/// at `cols`×`rows`. Returns its two halves, as an SSH channel would.
+pub async fn start(
+	ip: IpAddr,
+	connect: MoshConnect,
+	cols: u32,
+	rows: u32,
+) -> Result<(MoshReader, MoshWriter), SshError> {
+	let server = SocketAddr::new(ip, connect.port);
+	let socket = bind(server)
+		.await
+		.map_err(|e| SshError::Mosh(format!("binding a UDP socket: {e}")))?;
+	let (output_tx, output_rx) = mpsc::channel(64);
+	let (input_tx, input_rx) = mpsc::unbounded_channel();
+	let mut transport = Transport::new(Ocb::new(&connect.key), server, socket, output_tx);
+	// The server learns the terminal size (and our address) from this.
+	transport.push(UserEvent::Resize { cols, rows });
+	runtime::handle().spawn(transport.run(input_rx));
+	Ok((MoshReader(output_rx), MoshWriter(input_tx)))
+}
+
+async fn bind(server: SocketAddr) -> std::io::Result<UdpSocket> {
+	let any: SocketAddr = if server.is_ipv4() {
+		"0.0.0.0:0".parse().expect("literal")
+	} else {
+		"[::]:0".parse().expect("literal")
+	};
+	let socket = UdpSocket::bind(any).await?;
+	socket.connect(server).await?;
+	Ok(socket)
+}
+
+/// The read half: screen updates as terminal bytes, `None` once the session
+/// has ended (the remote shell exited, or we closed it).
+pub struct MoshReader(mpsc::Receiver<Bytes>);
+
+impl MoshReader {
+	pub async fn recv(&mut self) -> Option<Bytes> {
+		self.0.recv().await
+	}
+}
+
+enum Input {
+	Event(UserEvent),
+	Close,
+}
+
+/// The write half. Writes never block on the network: they join the user
+/// stream, which is retransmitted until the server has it.
+#[derive(Clone)]
+pub struct MoshWriter(mpsc::UnboundedSender<Input>);
+
+impl MoshWriter {
+	pub fn send_data(&self, data: &[u8]) -> Result<(), SshError> {
+		self.send(Input::Event(UserEvent::Keys(data.to_vec())))
+	}
+
+	pub fn resize(&self, cols: u32, rows: u32) -> Result<(), SshError> {
+		self.send(Input::Event(UserEvent::Resize { cols, rows }))
+	}
+
+	/// Ask the server to end the session; the reader sees EOF once it has.
+	pub fn close(&self) -> Result<(), SshError> {
+		self.send(Input::Close)
+	}
+
+	fn send(&self, input: Input) -> Result<(), SshError> {
+		self.0.send(input).map_err(|_| SshError::Disconnected)
+	}
+}
+
+/// Whether the session goes on after handling something.
+#[derive(PartialEq)]
+enum Flow {
+	Continue,
+	Done,
+}
+
+struct Transport {
+	ocb: Ocb,
+	server: SocketAddr,
+	socket: UdpSocket,
+	output: mpsc::Sender<Bytes>,
+	/// Zero point of the 16-bit millisecond timestamps.
+	epoch: Instant,
+
+	// Our state: user events past what the server has acknowledged, each
+	// tagged with the state number that introduced it.
+	pending: VecDeque<(u64, UserEvent)>,
+	current_num: u64,
+	acked_num: u64,
+	shutdown_since: Option<Instant>,
+
+	// Their state: the screen number the `Term` is at.
+	remote_num: u64,
+	ack_due: bool,
+
+	// Datagram layer.
+	seq: u64,
+	fragment_id: u64,
+	assembly: Assembly,
+	/// The server's last timestamp and when it arrived, to echo back.
+	saved_timestamp: Option<(u16, Instant)>,
+	/// Smoothed RTT and its variance, in ms (RFC 6298).
+	rtt: Option<(f64, f64)>,
+	last_sent: Instant,
+	last_heard: Instant,
+	last_hop: Instant,
+}
+
+impl Transport {
+	fn new(ocb: Ocb, server: SocketAddr, socket: UdpSocket, output: mpsc::Sender<Bytes>) -> Self {
+		let now = Instant::now();
+		Self {
+			ocb,
+			server,
+			socket,
+			output,
+			epoch: now,
+			pending: VecDeque::new(),
+			current_num: 0,
+			acked_num: 0,
+			shutdown_since: None,
+			remote_num: 0,
+			ack_due: false,
+			seq: 0,
+			fragment_id: 0,
+			assembly: Assembly::default(),
+			saved_timestamp: None,
+			rtt: None,
+			last_sent: now,
+			last_heard: now,
+			last_hop: now,
+		}
+	}
+
+	async fn run(mut self, mut input: mpsc::UnboundedReceiver<Input>) {
+		self.send().await;
+		let mut tick = tokio::time::interval(TICK);
+		tick.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+		let mut buf = vec![0u8; 64 * 1024];
+		loop {
+			let flow = tokio::select! {
+				received = self.socket.recv(&mut buf) => match received {
+					Ok(n) => self.receive(&buf[..n]).await,
+					// ICMP errors surface here on a connected socket; the
+					// server may be back any moment, so keep going.
+					Err(_) => Flow::Continue,
+				},
+				cmd = input.recv(), if self.shutdown_since.is_none() => {
+					match cmd {
+						Some(Input::Event(event)) => self.push(event),
+						// Closed, or every writer dropped with the session.
+						Some(Input::Close) | None => {
+							self.shutdown_since = Some(Instant::now());
+						}
+					}
+					self.send().await;
+					Flow::Continue
+				}
+				_ = tick.tick() => self.on_tick().await,
+			};
+			if flow == Flow::Done {
+				break;
+			}
+		}
+		// Dropping `output` is the reader's EOF.
+	}
+
+	fn push(&mut self, event: UserEvent) {
+		self.current_num += 1;
+		self.pending.push_back((self.current_num, event));
+	}
+
+	fn rto(&self) -> Duration {
+		let Some((srtt, rttvar)) = self.rtt else {
+			return MAX_RTO;
+		};
+		Duration::from_millis((srtt + 4.0 * rttvar).ceil() as u64).clamp(MIN_RTO, MAX_RTO)
+	}
+
+	fn timestamp(&self) -> u16 {
+		self.epoch.elapsed().as_millis() as u16
+	}
+
+	/// Send our state as a diff from what the server last acknowledged,
+	/// carrying our acknowledgement of its state.
+	async fn send(&mut self) {
+		let inst = Instruction {
+			old_num: self.acked_num,
+			new_num: if self.shutdown_since.is_some() {
+				u64::MAX
+			} else {
+				self.current_num
+			},
+			ack_num: self.remote_num,
+			throwaway_num: self.acked_num,
+			diff: wire::encode_user_diff(self.pending.iter().map(|(_, e)| e)),
+		};
+		self.fragment_id += 1;
+		for fragment in wire::fragment(self.fragment_id, &inst.pack()) {
+			let timestamp_reply = match self.saved_timestamp.take() {
+				Some((ts, at)) if at.elapsed() < Duration::from_secs(1) => {
+					ts.wrapping_add(at.elapsed().as_millis() as u16)
+				}
+				_ => u16::MAX,
+			};
+			let datagram = wire::seal(
+				&self.ocb,
+				self.seq,
+				self.timestamp(),
+				timestamp_reply,
+				&fragment,
+			);
+			self.seq += 1;
+			// A send error is the network being down; the tick retransmits.
+			let _ = self.socket.send(&datagram).await;
+		}
+		self.last_sent = Instant::now();
+		self.ack_due = false;
+	}
+
+	async fn receive(&mut self, datagram: &[u8]) -> Flow {
+		let Some((timestamp, timestamp_reply, fragment)) = wire::open(&self.ocb, datagram) else {
+			return Flow::Continue;
+		};
+		self.last_heard = Instant::now();
+		self.saved_timestamp = Some((timestamp, Instant::now()));
+		if timestamp_reply != u16::MAX {
+			let sample = f64::from(self.timestamp().wrapping_sub(timestamp_reply));
+			// Ignore nonsense from a clock wrap or a long-held datagram.
+			if sample < 5000.0 {
+				self.rtt = Some(match self.rtt {
+					None => (sample, sample / 2.0),
+					Some((srtt, rttvar)) => (
+						0.875 * srtt + 0.125 * sample,
+						0.75 * rttvar + 0.25 * (srtt - sample).abs(),
+					),
+				});
+			}
+		}
+		let Some(inst) = self
+			.assembly
+			.add(&fragment)
+			.and_then(|payload| Instruction::unpack(&payload))
+		else {
+			return Flow::Continue;
+		};
+
+		if self.shutdown_since.is_some() && inst.ack_num == u64::MAX {
+			return Flow::Done;
+		}
+		if inst.ack_num > self.acked_num && inst.ack_num <= self.current_num {
+			self.acked_num = inst.ack_num;
+			while self
+				.pending
+				.front()
+				.is_some_and(|(n, _)| *n <= inst.ack_num)
+			{
+				self.pending.pop_front();
+			}
+		}
+
+		if inst.new_num == self.remote_num || inst.old_num != self.remote_num {
+			// A duplicate, or a diff from a screen we don't have (see the
+			// module docs) — just an acknowledgement for us.
+			return Flow::Continue;
+		}
+		let Some(bytes) = wire::decode_host_diff(&inst.diff) else {
+			return Flow::Continue;
+		};
+		if !bytes.is_empty() && self.output.send(Bytes::from(bytes)).await.is_err() {
+			// The session is gone; wind the server down too.
+			self.shutdown_since.get_or_insert_with(Instant::now);
+		}
+		self.remote_num = inst.new_num;
+		self.ack_due = true;
+		if inst.new_num == u64::MAX {
+			// The remote shell exited: confirm, and we're done.
+			self.send().await;
+			return Flow::Done;
+		}
+		Flow::Continue
+	}
+
+	async fn on_tick(&mut self) -> Flow {
+		if self
+			.shutdown_since
+			.is_some_and(|since| since.elapsed() > SHUTDOWN_GRACE)
+		{
+			return Flow::Done;
+		}
+		if self.last_heard.elapsed() > PORT_HOP && self.last_hop.elapsed() > PORT_HOP {
+			self.last_hop = Instant::now();
+			if let Ok(socket) = bind(self.server).await {
+				self.socket = socket;
+			}
+		}
+		let unacked = self.shutdown_since.is_some() || self.current_num > self.acked_num;
+		let since = self.last_sent.elapsed();
+		if self.ack_due || (unacked && since >= self.rto()) || since >= HEARTBEAT {
+			self.send().await;
+		}
+		Flow::Continue
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn parses_mosh_server_output() {
+		let out = "\r\nMOSH CONNECT 60001 4NeCCgvZFe2RnPgrcU1PQw\r\n";
+		let connect = MoshConnect::parse(out).unwrap();
+		assert_eq!(connect.port, 60001);
+		assert_eq!(connect.key[..3], [0xe0, 0xd7, 0x82]);
+		assert_eq!(MoshConnect::parse("mosh-server: command not found"), None);
+		assert_eq!(MoshConnect::parse("MOSH CONNECT 60001 tooshort"), None);
+	}
+
+	/// A scripted server on loopback: checks the client's first datagram is
+	/// an authenticated resize, answers with a screen, then shuts down.
+	#[test]
+	fn talks_ssp_to_a_loopback_server() {
+		runtime::handle().block_on(async {
+			let server = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+			let port = server.local_addr().unwrap().port();
+			let key = [9u8; 16];
+			let ocb = Ocb::new(&key);
+			let (mut reader, writer) = start(
+				IpAddr::from([127, 0, 0, 1]),
+				MoshConnect { port, key },
+				80,
+				24,
+			)
+			.await
+			.unwrap();
+
+			let mut buf = vec![0u8; 2048];
+			let (n, client) = server.recv_from(&mut buf).await.unwrap();
+			let (_, reply, fragment) = server_open(&ocb, &buf[..n]);
+			assert_eq!(reply, u16::MAX);
+			let first = Instruction::unpack(&fragment[10..]).unwrap();
+			assert_eq!((first.old_num, first.new_num), (0, 1));
+			assert_eq!(
+				first.diff,
+				wire::encode_user_diff(&[UserEvent::Resize { cols: 80, rows: 24 }])
+			);
+
+			let screen = host_diff(b"\x1b[Hhello");
+			let reply = Instruction {
+				old_num: 0,
+				new_num: 1,
+				ack_num: 1,
+				throwaway_num: 0,
+				diff: screen,
+			};
+			server
+				.send_to(&server_seal(&ocb, 0, &reply), client)
+				.await
+				.unwrap();
+			assert_eq!(&reader.recv().await.unwrap()[..], b"\x1b[Hhello");
+
+			writer.send_data(b"exit\r").unwrap();
+			let keys = loop {
+				let (n, _) = server.recv_from(&mut buf).await.unwrap();
+				let (_, _, fragment) = server_open(&ocb, &buf[..n]);
+				let inst = Instruction::unpack(&fragment[10..]).unwrap();
+				if inst.new_num == 2 {
+					break inst;
+				}
+			};
+			// Diffed from the acknowledged state 1: only the new keystrokes.
+			assert_eq!(keys.old_num, 1);
+			assert_eq!(keys.ack_num, 1);
+			assert_eq!(
+				keys.diff,
+				wire::encode_user_diff(&[UserEvent::Keys(b"exit\r".to_vec())])
+			);
+
+			let bye = Instruction {
+				old_num: 1,
+				new_num: u64::MAX,
+				ack_num: 2,
+				throwaway_num: 1,
+				diff: Vec::new(),
+			};
+			server
+				.send_to(&server_seal(&ocb, 1, &bye), client)
+				.await
+				.unwrap();
+			assert_eq!(reader.recv().await, None);
+		});
+	}
+
+	fn host_diff(bytes: &[u8]) -> Vec<u8> {
+		// HostMessage { instruction { hostbytes { hoststring: bytes } } }
+		let wrap = |field: u8, inner: &[u8]| {
+			let mut out = vec![field << 3 | 2, inner.len() as u8];
+			out.extend_from_slice(inner);
+			out
+		};
+		wrap(1, &wrap(2, &wrap(4, bytes)))
+	}
+
+	fn server_seal(ocb: &Ocb, seq: u64, inst: &Instruction) -> Vec<u8> {
+		let mut nonce = [0u8; 12];
+		nonce[4..].copy_from_slice(&(1u64 << 63 | seq).to_be_bytes());
+		let mut plain = vec![0, 0, 0xff, 0xff];
+		plain.extend_from_slice(&wire::fragment(seq, &inst.pack())[0]);
+		let mut out = nonce[4..].to_vec();
+		out.extend_from_slice(&ocb.encrypt(&nonce, &plain));
+		out
+	}
+
+	fn server_open(ocb: &Ocb, datagram: &[u8]) -> (u16, u16, Vec<u8>) {
+		let mut nonce = [0u8; 12];
+		nonce[4..].copy_from_slice(&datagram[..8]);
+		assert_eq!(
+			nonce[4] & 0x80,
+			0,
+			"client datagrams have the direction bit clear"
+		);
+		let plain = ocb.decrypt(&nonce, &datagram[8..]).expect("authentic");
+		(
+			u16::from_be_bytes([plain[0], plain[1]]),
+			u16::from_be_bytes([plain[2], plain[3]]),
+			plain[4..].to_vec(),
+		)
+	}
+}
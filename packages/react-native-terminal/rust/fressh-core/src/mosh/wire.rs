@@ -0,0 +1,405 @@
+//! Mosh's wire format, bottom up: the encrypted datagram (nonce ‖ OCB), the
+//! packet (two 16-bit timestamps ‖ fragment), the fragment (id ‖ number ‖
+//! slice of a zlib-compressed `TransportInstruction`), and the protobuf
+//! messages themselves — hand-rolled, since there are four tiny ones.
+
+use std::io::{Read, Write};
+
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+
+use super::ocb::{Ocb, NONCE_LEN};
+
+/// `MOSH_PROTOCOL_VERSION`; the server drops anything else.
+const PROTOCOL_VERSION: u64 = 2;
+
+/// Nonce bit 63: set on server → client datagrams.
+const TO_CLIENT: u64 = 1 << 63;
+
+/// Fragment header: 64-bit instruction id, 16-bit number (top bit = final).
+const FRAGMENT_HEADER: usize = 10;
+
+/// Largest fragment we send, header included. Leaves room for the datagram
+/// overhead (8 nonce + 4 timestamps + 16 tag) under a 1280-byte IPv6 MTU.
+pub(super) const FRAGMENT_MAX: usize = 1200;
+
+/// A decompressed instruction can't be larger than this (a full-screen
+/// repaint is a few KiB); stops a zlib bomb from the other end.
+const INSTRUCTION_MAX: u64 = 1 << 20;
+
+// ── protobuf ───────────────────────────────────────────────────────────────
+
+fn put_varint(out: &mut Vec<u8>, mut v: u64) {
+	while v >= 0x80 {
+		out.push(v as u8 | 0x80);
+		v >>= 7;
+	}
+	out.push(v as u8);
+}
+
+fn put_uint(out: &mut Vec<u8>, field: u32, v: u64) {
+	put_varint(out, u64::from(field) << 3);
+	put_varint(out, v);
+}
+
+fn put_bytes(out: &mut Vec<u8>, field: u32, bytes: &[u8]) {
+	put_varint(out, u64::from(field) << 3 | 2);
+	put_varint(out, bytes.len() as u64);
+	out.extend_from_slice(bytes);
+}
+
+enum Value<'a> {
+	Uint(u64),
+	Bytes(&'a [u8]),
+}
+
+/// Walk a message's fields. `None` on malformed input; fixed-width fields
+/// (none in mosh's messages) are skipped.
+fn fields(mut buf: &[u8]) -> Option<Vec<(u32, Value<'_>)>> {
+	fn varint(buf: &mut &[u8]) -> Option<u64> {
+		let mut v = 0u64;
+		for shift in (0..64).step_by(7) {
+			let (&byte, rest) = buf.split_first()?;
+			*buf = rest;
+			v |= u64::from(byte & 0x7f) << shift;
+			if byte & 0x80 == 0 {
+				return Some(v);
+			}
+		}
+		None
+	}
+	let mut out = Vec::new();
+	while !buf.is_empty() {
+		let key = varint(&mut buf)?;
+		let field = u32::try_from(key >> 3).ok()?;
+		let value = match key & 7 {
+			0 => Value::Uint(varint(&mut buf)?),
+			1 => {
+				buf = buf.get(8..)?;
+				continue;
+			}
+			2 => {
+				let len = usize::try_from(varint(&mut buf)?).ok()?;
+				let (bytes, rest) = (buf.get(..len)?, &buf[len..]);
+				buf = rest;
+				Value::Bytes(bytes)
+			}
+			5 => {
+				buf = buf.get(4..)?;
+				continue;
+			}
+			_ => return None,
+		};
+		out.push((field, value));
+	}
+	Some(out)
+}
+
+/// `TransportBuffers.Instruction`: "apply `diff` to state `old_num` to get
+/// state `new_num`", plus the sender's view of the other direction.
+#[derive(Debug, Default, PartialEq)]
+pub(super) struct Instruction {
+	pub old_num: u64,
+	pub new_num: u64,
+	/// The newest of *our* states the sender has.
+	pub ack_num: u64,
+	/// The sender will never diff from a state older than this again.
+	pub throwaway_num: u64,
+	pub diff: Vec<u8>,
+}
+
+impl Instruction {
+	fn encode(&self) -> Vec<u8> {
+		let mut out = Vec::with_capacity(self.diff.len() + 32);
+		put_uint(&mut out, 1, PROTOCOL_VERSION);
+		put_uint(&mut out, 2, self.old_num);
+		put_uint(&mut out, 3, self.new_num);
+		put_uint(&mut out, 4, self.ack_num);
+		put_uint(&mut out, 5, self.throwaway_num);
+		put_bytes(&mut out, 6, &self.diff);
+		out
+	}
+
+	fn decode(buf: &[u8]) -> Option<Self> {
+		let mut inst = Self::default();
+		let mut version = None;
+		for (field, value) in fields(buf)? {
+			match (field, value) {
+				(1, Value::Uint(v)) => version = Some(v),
+				(2, Value::Uint(v)) => inst.old_num = v,
+				(3, Value::Uint(v)) => inst.new_num = v,
+				(4, Value::Uint(v)) => inst.ack_num = v,
+				(5, Value::Uint(v)) => inst.throwaway_num = v,
+				(6, Value::Bytes(b)) => inst.diff = b.to_vec(),
+				_ => {}
+			}
+		}
+		(version == Some(PROTOCOL_VERSION)).then_some(inst)
+	}
+
+	/// Serialize and compress: the payload that gets fragmented.
+	pub(super) fn pack(&self) -> Vec<u8> {
+		let mut z = ZlibEncoder::new(Vec::new(), Compression::default());
+		z.write_all(&self.encode()).expect("writing to a Vec");
+		z.finish().expect("writing to a Vec")
+	}
+
+	/// Decompress and parse a reassembled payload.
+	pub(super) fn unpack(payload: &[u8]) -> Option<Self> {
+		let mut raw = Vec::new();
+		ZlibDecoder::new(payload)
+			.take(INSTRUCTION_MAX)
+			.read_to_end(&mut raw)
+			.ok()?;
+		Self::decode(&raw)
+	}
+}
+
+/// One entry of the client's user stream (`ClientBuffers.UserMessage`).
+#[derive(Clone, Debug, PartialEq)]
+pub(super) enum UserEvent {
+	Keys(Vec<u8>),
+	Resize { cols: u32, rows: u32 },
+}
+
+/// A user-stream diff: the events to append to the server's copy of our state.
+pub(super) fn encode_user_diff<'a>(events: impl IntoIterator<Item = &'a UserEvent>) -> Vec<u8> {
+	let mut out = Vec::new();
+	for event in events {
+		let mut inst = Vec::new();
+		match event {
+			UserEvent::Keys(keys) => {
+				let mut keystroke = Vec::new();
+				put_bytes(&mut keystroke, 4, keys);
+				put_bytes(&mut inst, 2, &keystroke);
+			}
+			UserEvent::Resize { cols, rows } => {
+				let mut resize = Vec::new();
+				put_uint(&mut resize, 5, u64::from(*cols));
+				put_uint(&mut resize, 6, u64::from(*rows));
+				put_bytes(&mut inst, 3, &resize);
+			}
+		}
+		put_bytes(&mut out, 1, &inst);
+	}
+	out
+}
+
+/// A host-stream diff (`HostBuffers.HostMessage`) → the terminal bytes in it.
+/// These are escape sequences that take a screen in the old state to the new
+/// one; the server's resize and echo-ack entries carry nothing we need.
+pub(super) fn decode_host_diff(diff: &[u8]) -> Option<Vec<u8>> {
+	let mut out = Vec::new();
+	for (field, value) in fields(diff)? {
+		let (1, Value::Bytes(inst)) = (field, value) else {
+			continue;
+		};
+		for (field, value) in fields(inst)? {
+			let (2, Value::Bytes(host_bytes)) = (field, value) else {
+				continue;
+			};
+			for (field, value) in fields(host_bytes)? {
+				if let (4, Value::Bytes(s)) = (field, value) {
+					out.extend_from_slice(s);
+				}
+			}
+		}
+	}
+	Some(out)
+}
+
+// ── fragments ──────────────────────────────────────────────────────────────
+
+/// Split a packed instruction into fragments of at most [`FRAGMENT_MAX`].
+pub(super) fn fragment(id: u64, payload: &[u8]) -> Vec<Vec<u8>> {
+	let chunk = FRAGMENT_MAX - FRAGMENT_HEADER;
+	let count = payload.len().div_ceil(chunk).max(1);
+	(0..count)
+		.map(|n| {
+			let body =
+				&payload[(n * chunk).min(payload.len())..((n + 1) * chunk).min(payload.len())];
+			let mut number = n as u16;
+			if n + 1 == count {
+				number |= 0x8000;
+			}
+			let mut out = Vec::with_capacity(FRAGMENT_HEADER + body.len());
+			out.extend_from_slice(&id.to_be_bytes());
+			out.extend_from_slice(&number.to_be_bytes());
+			out.extend_from_slice(body);
+			out
+		})
+		.collect()
+}
+
+/// Reassembles the fragments of one instruction at a time; a fragment of a
+/// newer instruction abandons a partial older one (as mosh's does).
+#[derive(Default)]
+pub(super) struct Assembly {
+	id: Option<u64>,
+	parts: Vec<Option<Vec<u8>>>,
+	total: Option<usize>,
+}
+
+impl Assembly {
+	/// Add a fragment; returns the payload once all of its parts are in.
+	pub(super) fn add(&mut self, fragment: &[u8]) -> Option<Vec<u8>> {
+		let id = u64::from_be_bytes(fragment.get(..8)?.try_into().ok()?);
+		let number = u16::from_be_bytes(fragment.get(8..10)?.try_into().ok()?);
+		let (index, last) = (usize::from(number & 0x7fff), number & 0x8000 != 0);
+		if self.id != Some(id) {
+			*self = Self {
+				id: Some(id),
+				..Self::default()
+			};
+		}
+		if self.parts.len() <= index {
+			self.parts.resize(index + 1, None);
+		}
+		self.parts[index] = Some(fragment[FRAGMENT_HEADER..].to_vec());
+		if last {
+			self.total = Some(index + 1);
+		}
+		let total = self.total?;
+		if self.parts.len() < total || self.parts[..total].iter().any(Option::is_none) {
+			return None;
+		}
+		let payload = self.parts[..total]
+			.iter()
+			.flatten()
+			.flatten()
+			.copied()
+			.collect();
+		*self = Self::default();
+		Some(payload)
+	}
+}
+
+// ── datagrams ──────────────────────────────────────────────────────────────
+
+fn nonce(direction_seq: u64) -> [u8; NONCE_LEN] {
+	let mut nonce = [0u8; NONCE_LEN];
+	nonce[4..].copy_from_slice(&direction_seq.to_be_bytes());
+	nonce
+}
+
+/// A client → server datagram: 8 bytes of nonce ‖ OCB(timestamps ‖ fragment).
+/// `timestamp_reply` is `u16::MAX` when there's nothing to echo.
+pub(super) fn seal(
+	ocb: &Ocb,
+	seq: u64,
+	timestamp: u16,
+	timestamp_reply: u16,
+	fragment: &[u8],
+) -> Vec<u8> {
+	let nonce = nonce(seq & !TO_CLIENT);
+	let mut plain = Vec::with_capacity(4 + fragment.len());
+	plain.extend_from_slice(&timestamp.to_be_bytes());
+	plain.extend_from_slice(&timestamp_reply.to_be_bytes());
+	plain.extend_from_slice(fragment);
+	let mut out = nonce[4..].to_vec();
+	out.extend_from_slice(&ocb.encrypt(&nonce, &plain));
+	out
+}
+
+/// A server → client datagram opened: `(timestamp, timestamp_reply,
+/// fragment)`, or `None` if it's forged, garbled, or going the wrong way.
+pub(super) fn open(ocb: &Ocb, datagram: &[u8]) -> Option<(u16, u16, Vec<u8>)> {
+	let direction_seq = u64::from_be_bytes(datagram.get(..8)?.try_into().ok()?);
+	if direction_seq & TO_CLIENT == 0 {
+		return None;
+	}
+	let plain = ocb.decrypt(&nonce(direction_seq), &datagram[8..])?;
+	let timestamp = u16::from_be_bytes(plain.get(..2)?.try_into().ok()?);
+	let timestamp_reply = u16::from_be_bytes(plain.get(2..4)?.try_into().ok()?);
+	Some((timestamp, timestamp_reply, plain[4..].to_vec()))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn instruction_round_trips_through_pack() {
+		let inst = Instruction {
+			old_num: 3,
+			new_num: u64::MAX,
+			ack_num: 300,
+			throwaway_num: 2,
+			diff: b"some diff".repeat(50),
+		};
+		assert_eq!(Instruction::unpack(&inst.pack()), Some(inst));
+		assert_eq!(Instruction::unpack(b"not zlib"), None);
+	}
+
+	#[test]
+	fn host_diff_yields_the_terminal_bytes() {
+		// HostMessage { instruction { hostbytes { "\x1b[H" } } instruction {
+		// resize { 80 24 } } instruction { hostbytes { "hi" } } }
+		let mut hb = Vec::new();
+		put_bytes(&mut hb, 4, b"\x1b[H");
+		let mut first = Vec::new();
+		put_bytes(&mut first, 2, &hb);
+		let mut resize = Vec::new();
+		put_uint(&mut resize, 5, 80);
+		put_uint(&mut resize, 6, 24);
+		let mut second = Vec::new();
+		put_bytes(&mut second, 3, &resize);
+		let mut hb = Vec::new();
+		put_bytes(&mut hb, 4, b"hi");
+		let mut third = Vec::new();
+		put_bytes(&mut third, 2, &hb);
+		let mut msg = Vec::new();
+		for inst in [first, second, third] {
+			put_bytes(&mut msg, 1, &inst);
+		}
+		assert_eq!(decode_host_diff(&msg).unwrap(), b"\x1b[Hhi");
+		assert_eq!(decode_host_diff(&[0x0a, 0x05, 0x01]), None);
+	}
+
+	#[test]
+	fn user_diff_encodes_keys_and_resizes() {
+		let diff = encode_user_diff(&[
+			UserEvent::Keys(b"ls\r".to_vec()),
+			UserEvent::Resize { cols: 80, rows: 24 },
+		]);
+		assert_eq!(
+			diff,
+			[
+				0x0a, 0x07, 0x12, 0x05, 0x22, 0x03, b'l', b's', b'\r', //
+				0x0a, 0x06, 0x1a, 0x04, 0x28, 80, 0x30, 24,
+			]
+		);
+	}
+
+	#[test]
+	fn fragments_reassemble_in_any_order() {
+		let payload: Vec<u8> = (0..3000u32).map(|i| i as u8).collect();
+		let mut parts = fragment(9, &payload);
+		assert_eq!(parts.len(), 3);
+		assert!(parts.iter().all(|p| p.len() <= FRAGMENT_MAX));
+		parts.swap(0, 2);
+		let mut assembly = Assembly::default();
+		// A stale fragment of an older instruction is dropped on the floor.
+		assert_eq!(
+			assembly.add(&fragment(8, b"old")[0][..]),
+			Some(b"old".to_vec())
+		);
+		assert_eq!(assembly.add(&parts[0]), None);
+		assert_eq!(assembly.add(&parts[1]), None);
+		assert_eq!(assembly.add(&parts[2]), Some(payload));
+		assert_eq!(fragment(1, b"").len(), 1);
+	}
+
+	#[test]
+	fn datagrams_only_open_in_the_server_to_client_direction() {
+		let ocb = Ocb::new(&[5; 16]);
+		let ours = seal(&ocb, 7, 100, u16::MAX, b"frag");
+		assert_eq!(open(&ocb, &ours), None);
+		// What the server would send: same layout, direction bit set.
+		let nonce = nonce(TO_CLIENT | 7);
+		let mut theirs = nonce[4..].to_vec();
+		theirs.extend_from_slice(&ocb.encrypt(&nonce, b"\x00\x01\x00\x02frag"));
+		assert_eq!(open(&ocb, &theirs), Some((1, 2, b"frag".to_vec())));
+	}
+}
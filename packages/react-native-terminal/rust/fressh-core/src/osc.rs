@@ -15,6 +15,8 @@
 //! add ~one match. No fork, no hand-rolled parser. See
 //! docs/projects/terminal-semantic-events.md for the full decision + perf notes.
 
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
 use std::time::Instant;
 
 // vte is re-exported by alacritty_terminal (`pub use vte;`), so we reach its
@@ -22,14 +24,32 @@ use std::time::Instant;
 // same `vte` instance the engine already pulls in.
 use alacritty_terminal::vte;
 
+use fressh_ssh::EnvReport;
+
 use crate::events::{self, CoreEvent};
 
+/// What a shell last reported about itself: its cwd, and the variables its
+/// integration was asked to report (`633;EnvSingle*`). Kept on the session so
+/// a one-off command can run "in" the shell.
+#[derive(Debug, Clone, Default)]
+pub struct ShellContext {
+	pub cwd: Option<String>,
+	pub env: BTreeMap<String, String>,
+}
+
+/// Written by the scanner in the reader loop, read by the control plane.
+pub type SharedContext = Arc<Mutex<ShellContext>>;
+
 /// Per-shell OSC scanner. Implements [`vte::Perform`] but acts only on OSC 7
 /// (cwd) and OSC 133 (semantic prompt). Driven by a [`vte::Parser`] owned by the
 /// reader loop; holds the small lifecycle state needed to derive command
 /// duration and to debounce the command-start marker across dialects.
 pub struct OscScanner {
 	shell_id: String,
+	context: SharedContext,
+	/// Which `633;EnvSingle*` reports are genuine. `None`: we injected no
+	/// integration, so every env report is someone else's bytes.
+	env_report: Option<EnvReport>,
 	/// `Instant` of the most recent `CommandStart`, for the `CommandFinished`
 	/// duration. `None` between commands (idle at a prompt).
 	command_started_at: Option<Instant>,
@@ -39,9 +59,11 @@ pub struct OscScanner {
 }
 
 impl OscScanner {
-	pub fn new(shell_id: String) -> Self {
+	pub fn new(shell_id: String, context: SharedContext, env_report: Option<EnvReport>) -> Self {
 		Self {
 			shell_id,
+			context,
+			env_report,
 			command_started_at: None,
 			in_command: false,
 		}
@@ -81,6 +103,28 @@ impl OscScanner {
 		});
 	}
 
+	fn on_cwd(&mut self, path: String) {
+		self.lock_context().cwd = Some(path.clone());
+		events::emit(CoreEvent::WorkingDirectoryChanged {
+			shell_id: self.shell_id.clone(),
+			path,
+		});
+	}
+
+	fn lock_context(&self) -> MutexGuard<'_, ShellContext> {
+		self.context.lock().unwrap_or_else(|p| p.into_inner())
+	}
+
+	/// True when `nonce` is this session's (and `name`, if given, was asked
+	/// for). Output the shell merely prints can't know the nonce.
+	fn is_genuine_env(&self, nonce: Option<&[u8]>, name: Option<&[u8]>) -> bool {
+		let Some(report) = &self.env_report else {
+			return false;
+		};
+		nonce == Some(report.nonce.as_bytes())
+			&& name.is_none_or(|name| report.names.iter().any(|n| n.as_bytes() == name))
+	}
+
 	fn on_command_text(&mut self, command: String) {
 		events::emit(CoreEvent::CommandText {
 			shell_id: self.shell_id.clone(),
@@ -95,10 +139,7 @@ impl vte::Perform for OscScanner {
 			// OSC 7 ; file://host/path  — report cwd.
 			Some(b"7") => {
 				if let Some(path) = parse_osc7_path(params) {
-					events::emit(CoreEvent::WorkingDirectoryChanged {
-						shell_id: self.shell_id.clone(),
-						path,
-					});
+					self.on_cwd(path);
 				}
 			}
 			// OSC 133 ; {A|B|C|D} [; ...]  — FinalTerm / iTerm2 semantic prompt.
@@ -110,11 +151,12 @@ impl vte::Perform for OscScanner {
 				Some(b"D") => self.on_command_finished(parse_exit_code(params.get(2).copied())),
 				_ => {}
 			},
-			// OSC 633 ; {A|B|C|D|E|P} [; ...]  — VS Code's private superset, emitted
-			// by the scripts fressh auto-injects. A=prompt start, B=prompt end (idle,
-			// ignored), C=command executed, D=finished+exit, E=command line text,
-			// P;Cwd=cwd. Unlike 133, B does NOT mean "running" here (it fires at every
-			// idle prompt), so only C drives CommandStart.
+			// OSC 633 ; {A|B|C|D|E|P|EnvSingle*} [; ...]  — VS Code's private
+			// superset, emitted by the scripts fressh auto-injects. A=prompt start,
+			// B=prompt end (idle, ignored), C=command executed, D=finished+exit,
+			// E=command line text, P;Cwd=cwd, EnvSingleStart/Entry=the reported
+			// variables (kept, not emitted). Unlike 133, B does NOT mean "running"
+			// here (it fires at every idle prompt), so only C drives CommandStart.
 			Some(b"633") => match params.get(1).copied() {
 				Some(b"A") => self.on_prompt_start(),
 				Some(b"C") => self.on_command_start(),
@@ -126,10 +168,26 @@ impl vte::Perform for OscScanner {
 				}
 				Some(b"P") => {
 					if let Some(path) = parse_osc633_cwd(params.get(2).copied()) {
-						events::emit(CoreEvent::WorkingDirectoryChanged {
-							shell_id: self.shell_id.clone(),
-							path,
-						});
+						self.on_cwd(path);
+					}
+				}
+				// `EnvSingleStart ; <clear> ; <nonce>`: 1 = a full report follows.
+				Some(b"EnvSingleStart") => {
+					if params.get(2).copied() == Some(b"1")
+						&& self.is_genuine_env(params.get(3).copied(), None)
+					{
+						self.lock_context().env.clear();
+					}
+				}
+				// `EnvSingleEntry ; <name> ; <escaped value> ; <nonce>`. Only for
+				// a requested name, with the session nonce.
+				Some(b"EnvSingleEntry") => {
+					if let (Some(name), Some(value)) = (params.get(2), params.get(3)) {
+						if !self.is_genuine_env(params.get(4).copied(), Some(name)) {
+							return;
+						}
+						let name = String::from_utf8_lossy(name).into_owned();
+						self.lock_context().env.insert(name, unescape_osc633(value));
 					}
 				}
 				_ => {}
@@ -246,45 +304,22 @@ fn hex_val(b: u8) -> Option<u8> {
 #[cfg(test)]
 mod tests {
 	use super::*;
-	use crate::events::{self, CoreEvent, EventSink};
-	use std::sync::{Arc, Mutex};
-
-	/// Captures emitted events for assertions. Installed as the process-wide sink;
-	/// tests run serially (one sink slot) so they share `#[serial]`-like care via
-	/// a fresh capture per test + draining what THIS scanner emitted.
-	#[derive(Default)]
-	struct Capture(Mutex<Vec<CoreEvent>>);
-	impl EventSink for Capture {
-		fn emit(&self, event: CoreEvent) {
-			self.0.lock().unwrap().push(event);
-		}
-	}
+	use crate::events::testing::capture;
 
 	/// Feed `bytes` through a real `vte::Parser` + `OscScanner` and return the
 	/// events emitted for THIS shell id (filtered, so a shared global sink across
 	/// serially-run tests doesn't cross-contaminate assertions).
 	fn run(shell_id: &str, chunks: &[&[u8]]) -> Vec<CoreEvent> {
-		// The event sink is a process-wide static (last-writer-wins), so parallel
-		// tests would clobber each other's sink mid-run. Serialize them.
-		static TEST_LOCK: Mutex<()> = Mutex::new(());
-		let _guard = TEST_LOCK.lock().unwrap_or_else(|p| p.into_inner());
-
-		let cap = Arc::new(Capture::default());
-		events::set_event_sink(cap.clone());
+		let (_guard, cap) = capture();
 		let mut parser = vte::Parser::new();
-		let mut scanner = OscScanner::new(shell_id.to_string());
+		let mut scanner = OscScanner::new(shell_id.to_string(), SharedContext::default(), None);
 		for chunk in chunks {
 			parser.advance(&mut scanner, chunk);
 		}
-		let events = cap
-			.0
-			.lock()
-			.unwrap()
-			.iter()
+		cap.events()
+			.into_iter()
 			.filter(|e| event_shell_id(e) == Some(shell_id))
-			.cloned()
-			.collect();
-		events
+			.collect()
 	}
 
 	fn event_shell_id(e: &CoreEvent) -> Option<&str> {
@@ -424,6 +459,85 @@ mod tests {
 		));
 	}
 
+	fn report(nonce: &str, names: &[&str]) -> Option<EnvReport> {
+		Some(EnvReport {
+			nonce: nonce.into(),
+			names: names.iter().map(|n| n.to_string()).collect(),
+		})
+	}
+
+	#[test]
+	fn context_keeps_cwd_and_reported_env() {
+		let context = SharedContext::default();
+		let mut parser = vte::Parser::new();
+		let mut scanner = OscScanner::new(
+			"s-ctx".into(),
+			context.clone(),
+			report("n", &["VIRTUAL_ENV", "PS_X"]),
+		);
+		parser.advance(
+			&mut scanner,
+			concat!(
+				"\x1b]633;P;Cwd=/srv/app\x07",
+				"\x1b]633;EnvSingleStart;1;n\x07",
+				"\x1b]633;EnvSingleEntry;VIRTUAL_ENV;/srv/app/.venv;n\x07",
+				"\x1b]633;EnvSingleEntry;PS_X;a\\x3bb;n\x07",
+				"\x1b]633;EnvSingleEnd;n;\x07",
+			)
+			.as_bytes(),
+		);
+		let ctx = context.lock().unwrap().clone();
+		assert_eq!(ctx.cwd.as_deref(), Some("/srv/app"));
+		assert_eq!(ctx.env["VIRTUAL_ENV"], "/srv/app/.venv");
+		assert_eq!(ctx.env["PS_X"], "a;b");
+
+		// A full report replaces what was kept.
+		parser.advance(
+			&mut scanner,
+			b"\x1b]633;EnvSingleStart;1;n\x07\x1b]7;/tmp\x07",
+		);
+		let ctx = context.lock().unwrap().clone();
+		assert!(ctx.env.is_empty());
+		assert_eq!(ctx.cwd.as_deref(), Some("/tmp"));
+	}
+
+	#[test]
+	fn forged_env_reports_are_dropped() {
+		let context = SharedContext::default();
+		let mut parser = vte::Parser::new();
+		let mut scanner = OscScanner::new(
+			"s-forged".into(),
+			context.clone(),
+			report("n0nce", &["VIRTUAL_ENV"]),
+		);
+		parser.advance(
+			&mut scanner,
+			concat!(
+				"\x1b]633;EnvSingleStart;1;n0nce\x07",
+				"\x1b]633;EnvSingleEntry;VIRTUAL_ENV;/srv/.venv;n0nce\x07",
+				// Printed by something that isn't our integration: wrong nonce,
+				// no nonce, and an unrequested name with the right one.
+				"\x1b]633;EnvSingleEntry;VIRTUAL_ENV;/tmp/evil;guess\x07",
+				"\x1b]633;EnvSingleEntry;PATH;/tmp/evil\x07",
+				"\x1b]633;EnvSingleEntry;LD_PRELOAD;/tmp/evil.so;n0nce\x07",
+				"\x1b]633;EnvSingleStart;1;guess\x07",
+			)
+			.as_bytes(),
+		);
+		let env = context.lock().unwrap().env.clone();
+		assert_eq!(env.len(), 1);
+		assert_eq!(env["VIRTUAL_ENV"], "/srv/.venv");
+
+		// Without injected integration no report is genuine.
+		let context = SharedContext::default();
+		let mut scanner = OscScanner::new("s-forged-plain".into(), context.clone(), None);
+		parser.advance(
+			&mut scanner,
+			b"\x1b]633;EnvSingleEntry;VIRTUAL_ENV;/tmp/evil;n0nce\x07",
+		);
+		assert!(context.lock().unwrap().env.is_empty());
+	}
+
 	#[test]
 	fn osc633_p_non_cwd_property_ignored() {
 		let evs = run("s-633p", &[b"\x1b]633;P;IsWindows=False\x07"]);
@@ -0,0 +1,215 @@
+//! Automatic reconnect: keep the shells' `Term`s through a dropped connection.
+//!
+//! Switching Wi-Fi to LTE kills the TCP connection; without this every shell's
+//! reader loop hits EOF and its `Term` — all the scrollback — is dropped. When
+//! the connection was made with a [`ReconnectConfig`], a loss (the health
+//! monitor giving up, or a shell EOF on a connection that no longer answers)
+//! instead:
+//!   1. detaches every shell: the `Term` stays in the registry, unfed;
+//!   2. re-runs the connect with the stored details, backing off exponentially
+//!      and emitting `Reconnecting` before each attempt;
+//!   3. registers the new connection under the same id, opens a fresh channel
+//!      per shell and re-binds it to the same `shell_id` (a separator line marks
+//!      the seam in the `Term`), then emits `Reconnected`. A shell started with
+//!      a command runs it again (`tmux attach` re-attaches).
+//!
+//! Port forwards, SFTP sessions and streaming commands ride the old connection
+//! and are closed, not restored; mosh shells don't need the connection and are
+//! left alone. Giving up (attempts exhausted, or an auth/host-key failure that
+//! retrying won't fix) emits `ConnectionLost` and tears everything down, as
+//! without reconnect.
+
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Duration;
+
+use fressh_ssh::{ReconnectConfig, ShellExit, SshError};
+
+use crate::events::{self, CoreEvent};
+use crate::session::{ConnectionSession, ShellCloseReason, ShellEnd};
+use crate::source::ShellBackend;
+use crate::{control, registry, runtime};
+
+/// How long a shell EOF waits for the connection to answer a ping before
+/// concluding it dropped (rather than the shell having exited).
+const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// A connection is gone (`reason` says why). Reconnect if configured, else
+/// report it lost and tear it down. Idempotent while a reconnect is running.
+pub(crate) fn connection_lost(connection_id: String, reason: String) {
+	let Some(conn) = registry::connection(&connection_id) else {
+		return;
+	};
+	let Some(policy) = conn.inner.config.reconnect else {
+		give_up(connection_id, reason);
+		return;
+	};
+	if conn.reconnecting.swap(true, Ordering::SeqCst) {
+		return;
+	}
+	runtime::handle().spawn(supervise(conn, policy, reason));
+}
+
+/// A shell's reader hit EOF. If the server reported its exit, or its
+/// connection still answers, the shell simply exited; otherwise the connection
+/// dropped — reconnected if configured, else torn down with the shell reported
+/// as `ConnectionLost`. Only asked for a `rebindable` shell — a mosh shell's
+/// EOF is always its own.
+pub(crate) async fn shell_eof(
+	shell_id: String,
+	connection_id: String,
+	rebindable: bool,
+	exit: ShellExit,
+) {
+	let end = ShellEnd::from_channel(exit);
+	let maybe_lost = rebindable && end.reason == ShellCloseReason::Eof;
+	if let Some(conn) = registry::connection(&connection_id).filter(|_| maybe_lost) {
+		let answers = matches!(
+			tokio::time::timeout(PROBE_TIMEOUT, conn.inner.ping()).await,
+			Ok(Ok(_))
+		);
+		if !answers {
+			connection_lost(connection_id, "connection dropped".into());
+			return;
+		}
+	}
+	registry::remove_shell(&shell_id);
+	control::shell_closed(shell_id, end);
+}
+
+fn give_up(connection_id: String, reason: String) {
+	events::emit(CoreEvent::ConnectionLost {
+		connection_id: connection_id.clone(),
+		reason,
+	});
+	// On a task of its own: the teardown aborts the health monitor, which may
+	// be the caller.
+	runtime::handle().spawn(control::teardown_connection(
+		connection_id,
+		ShellCloseReason::ConnectionLost,
+	));
+}
+
+/// Whether `lost` is still the registered session — a `disconnect` (or a
+/// finished reconnect) replaces or removes it, and this reconnect must stop.
+fn still_current(lost: &Arc<ConnectionSession>) -> bool {
+	registry::connection(&lost.connection_id).is_some_and(|c| Arc::ptr_eq(&c, lost))
+}
+
+/// Errors retrying can't fix: the user (or the server) said no.
+fn is_final(e: &SshError) -> bool {
+	matches!(
+		e,
+		SshError::HostKeyRejected
+			| SshError::Auth(_)
+			| SshError::AuthRejected { .. }
+			| SshError::PassphraseRequired
+			| SshError::WrongPassphrase
+			| SshError::Certificate(_)
+	)
+}
+
+async fn supervise(lost: Arc<ConnectionSession>, policy: ReconnectConfig, mut reason: String) {
+	let connection_id = lost.connection_id.clone();
+	if let Some(task) = &lost.health_task {
+		task.abort();
+	}
+	control::close_channel_users(&connection_id).await;
+	// Mosh shells keep running over their own transport; leave them be.
+	let shells: Vec<_> = registry::shells_for_connection(&connection_id)
+		.into_iter()
+		.filter(|shell| shell.reopen_options().is_some())
+		.collect();
+	for shell in &shells {
+		shell.detach();
+	}
+
+	let mut attempt = 0u32;
+	let conn = loop {
+		attempt += 1;
+		if policy.max_attempts != 0 && attempt > policy.max_attempts {
+			if still_current(&lost) {
+				give_up(connection_id, reason);
+			}
+			return;
+		}
+		let delay = policy.delay(attempt);
+		events::emit(CoreEvent::Reconnecting {
+			connection_id: connection_id.clone(),
+			attempt,
+			delay_ms: u32::try_from(delay.as_millis()).unwrap_or(u32::MAX),
+			reason: reason.clone(),
+		});
+		tokio::time::sleep(delay).await;
+		if !still_current(&lost) {
+			return;
+		}
+		match control::open_connection(
+			&connection_id,
+			lost.inner.info.details.clone(),
+			lost.jump_hosts.clone(),
+			lost.inner.config.clone(),
+		)
+		.await
+		{
+			Ok(conn) => break conn,
+			Err(e) if is_final(&e) => {
+				if still_current(&lost) {
+					give_up(connection_id, e.to_string());
+				}
+				return;
+			}
+			Err(e) => reason = e.to_string(),
+		}
+	};
+	if !still_current(&lost) {
+		// Disconnected while the handshake was in flight.
+		let _ = conn.disconnect().await;
+		return;
+	}
+
+	control::register_connection(connection_id.clone(), conn, lost.jump_hosts.clone());
+	let Some(current) = registry::connection(&connection_id) else {
+		return;
+	};
+	for shell in shells {
+		let reopened = match shell.reopen_options() {
+			Some(opts) => current.inner.open_shell(opts).await,
+			None => Err(SshError::NotFound(shell.shell_id.clone())),
+		};
+		match reopened {
+			Ok(channel) => shell.rebind(ShellBackend::from_ssh(channel)),
+			Err(_) => {
+				registry::remove_shell(&shell.shell_id);
+				shell.close().await;
+				control::shell_closed(
+					shell.shell_id.clone(),
+					ShellEnd::new(ShellCloseReason::ConnectionLost),
+				);
+			}
+		}
+	}
+	events::emit(CoreEvent::Reconnected {
+		connection_id,
+		attempt,
+	});
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn backoff_doubles_up_to_the_cap() {
+		let policy = ReconnectConfig {
+			max_attempts: 0,
+			initial_delay_ms: 500,
+			max_delay_ms: 8_000,
+		};
+		let delays: Vec<u64> = (1..=7)
+			.map(|n| policy.delay(n).as_millis() as u64)
+			.collect();
+		assert_eq!(delays, [500, 1_000, 2_000, 4_000, 8_000, 8_000, 8_000]);
+		assert_eq!(policy.delay(u32::MAX).as_millis(), 8_000);
+	}
+}
@@ -1,5 +1,7 @@
-//! `DashMap<Id, Arc<…>>` for connections and shells. The registry OWNS session
-//! lifetime (§7): dropping a JS handle can't kill a session; only an explicit
+//! `DashMap<Id, Arc<…>>` for connections, shells, port forwards, SFTP sessions,
+//! file transfers and streaming commands — plus how recently closed shells
+//! ended, for a "session ended" banner. The registry OWNS session lifetime
+//! (§7): dropping a JS handle can't kill a session; only an explicit
 //! `disconnect`/`close_shell` removes the pin. This is what enables tmux-style
 //! reattach — the `Term` keeps living (and being fed) while no view is mounted.
 
@@ -8,10 +10,22 @@ use std::sync::Arc;
 use dashmap::DashMap;
 use once_cell::sync::Lazy;
 
-use crate::session::{ConnectionSession, SharedTerm, ShellSession};
+use crate::exec::ExecSession;
+use crate::forward::ForwardSession;
+use crate::session::{ConnectionSession, SharedTerm, ShellEnd, ShellSession};
+use crate::sftp::SftpSession;
+use crate::transfer::Transfer;
 
 static CONNECTIONS: Lazy<DashMap<String, Arc<ConnectionSession>>> = Lazy::new(DashMap::new);
 static SHELLS: Lazy<DashMap<String, Arc<ShellSession>>> = Lazy::new(DashMap::new);
+static FORWARDS: Lazy<DashMap<String, Arc<ForwardSession>>> = Lazy::new(DashMap::new);
+static SFTPS: Lazy<DashMap<String, Arc<SftpSession>>> = Lazy::new(DashMap::new);
+static TRANSFERS: Lazy<DashMap<String, Arc<Transfer>>> = Lazy::new(DashMap::new);
+static EXECS: Lazy<DashMap<String, Arc<ExecSession>>> = Lazy::new(DashMap::new);
+static ENDED_SHELLS: Lazy<DashMap<String, ShellEnd>> = Lazy::new(DashMap::new);
+
+/// Closed shells whose [`ShellEnd`] is kept; past this, the oldest is dropped.
+const MAX_ENDED_SHELLS: usize = 64;
 
 pub(crate) fn insert_connection(conn: Arc<ConnectionSession>) {
 	CONNECTIONS.insert(conn.connection_id.clone(), conn);
@@ -24,6 +38,8 @@ pub(crate) fn remove_connection(id: &str) -> Option<Arc<ConnectionSession>> {
 }
 
 pub(crate) fn insert_shell(shell: Arc<ShellSession>) {
+	// Channel ids are reused: a new shell may take a closed one's id.
+	ENDED_SHELLS.remove(&shell.shell_id);
 	SHELLS.insert(shell.shell_id.clone(), shell);
 }
 pub(crate) fn shell(id: &str) -> Option<Arc<ShellSession>> {
@@ -40,6 +56,83 @@ pub(crate) fn shells_for_connection(connection_id: &str) -> Vec<Arc<ShellSession
 		.collect()
 }
 
+pub(crate) fn insert_shell_end(shell_id: String, end: ShellEnd) {
+	ENDED_SHELLS.insert(shell_id, end);
+	if ENDED_SHELLS.len() > MAX_ENDED_SHELLS {
+		let oldest = ENDED_SHELLS
+			.iter()
+			.min_by(|a, b| a.ended_at_ms.total_cmp(&b.ended_at_ms))
+			.map(|e| e.key().clone());
+		if let Some(oldest) = oldest {
+			ENDED_SHELLS.remove(&oldest);
+		}
+	}
+}
+pub(crate) fn shell_end(id: &str) -> Option<ShellEnd> {
+	ENDED_SHELLS.get(id).map(|e| e.clone())
+}
+
+pub(crate) fn insert_forward(forward: Arc<ForwardSession>) {
+	FORWARDS.insert(forward.forward_id.clone(), forward);
+}
+pub(crate) fn remove_forward(id: &str) -> Option<Arc<ForwardSession>> {
+	FORWARDS.remove(id).map(|(_, v)| v)
+}
+pub(crate) fn forwards_for_connection(connection_id: &str) -> Vec<Arc<ForwardSession>> {
+	FORWARDS
+		.iter()
+		.filter(|e| e.connection_id == connection_id)
+		.map(|e| e.clone())
+		.collect()
+}
+
+pub(crate) fn insert_sftp(sftp: Arc<SftpSession>) {
+	SFTPS.insert(sftp.sftp_id.clone(), sftp);
+}
+pub(crate) fn sftp(id: &str) -> Option<Arc<SftpSession>> {
+	SFTPS.get(id).map(|e| e.clone())
+}
+pub(crate) fn remove_sftp(id: &str) -> Option<Arc<SftpSession>> {
+	SFTPS.remove(id).map(|(_, v)| v)
+}
+pub(crate) fn sftps_for_connection(connection_id: &str) -> Vec<Arc<SftpSession>> {
+	SFTPS
+		.iter()
+		.filter(|e| e.connection_id == connection_id)
+		.map(|e| e.clone())
+		.collect()
+}
+
+pub(crate) fn insert_transfer(transfer: Arc<Transfer>) {
+	TRANSFERS.insert(transfer.transfer_id.clone(), transfer);
+}
+pub(crate) fn transfer(id: &str) -> Option<Arc<Transfer>> {
+	TRANSFERS.get(id).map(|e| e.clone())
+}
+pub(crate) fn remove_transfer(id: &str) -> Option<Arc<Transfer>> {
+	TRANSFERS.remove(id).map(|(_, v)| v)
+}
+pub(crate) fn transfers() -> Vec<Arc<Transfer>> {
+	TRANSFERS.iter().map(|e| e.clone()).collect()
+}
+
+pub(crate) fn insert_exec(exec: Arc<ExecSession>) {
+	EXECS.insert(exec.exec_id.clone(), exec);
+}
+pub(crate) fn exec(id: &str) -> Option<Arc<ExecSession>> {
+	EXECS.get(id).map(|e| e.clone())
+}
+pub(crate) fn remove_exec(id: &str) -> Option<Arc<ExecSession>> {
+	EXECS.remove(id).map(|(_, v)| v)
+}
+pub(crate) fn execs_for_connection(connection_id: &str) -> Vec<Arc<ExecSession>> {
+	EXECS
+		.iter()
+		.filter(|e| e.connection_id == connection_id)
+		.map(|e| e.clone())
+		.collect()
+}
+
 /// Render-plane accessor: the shared `Term` for a shell id. The Nitro view's
 /// C-ABI looks this up by `shellId` to draw the current grid each frame, then
 /// drops the clone — the registry keeps the real one alive. (§10 render plane.)
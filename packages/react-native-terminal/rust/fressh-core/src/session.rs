@@ -2,7 +2,7 @@
 //! the parsed `Term` continuously in the background — view or no view — which is
 //! exactly what makes scrollback durable across view mount/unmount (§9).
 
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
@@ -16,12 +16,14 @@ use once_cell::sync::Lazy;
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 
-use fressh_ssh::{Connection as SshConnection, SshError, TerminalType};
+use fressh_ssh::{
+	Connection as SshConnection, ConnectionDetails, EnvReport, ShellExit, SshError,
+	StartShellOptions, TerminalSize, TerminalType,
+};
 
-use crate::events::{self, CoreEvent};
-use crate::osc::OscScanner;
-use crate::source::{ShellBackend, WriteSink};
-use crate::{registry, runtime};
+use crate::osc::{OscScanner, SharedContext};
+use crate::source::{ReadSource, ShellBackend, WriteSink};
+use crate::{reconnect, runtime};
 
 /// Shared, lockable parsed terminal state. The reader loop writes it; the render
 /// plane reads it (looked up from the registry by shell id). Std `Mutex` is fine
@@ -68,15 +70,84 @@ fn now_ms() -> u64 {
 	PROCESS_START.elapsed().as_millis() as u64
 }
 
+/// Written into a re-bound shell's `Term` so the seam between the lost session
+/// and the new one is visible in the scrollback.
+const RECONNECT_SEPARATOR: &[u8] =
+	b"\r\n\x1b[0;2m\xe2\x94\x80\xe2\x94\x80 reconnected \xe2\x94\x80\xe2\x94\x80\x1b[0m\r\n";
+
+/// Why a shell went away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShellCloseReason {
+	/// The shell (or its startup command) exited and the server said how.
+	RemoteExit,
+	/// The channel closed without an exit status or signal.
+	Eof,
+	/// `close_shell` or `disconnect`.
+	LocalClose,
+	/// The connection dropped and wasn't (or couldn't be) restored.
+	ConnectionLost,
+}
+
+/// How a shell ended: the payload of `ShellClosed`, kept in the registry
+/// afterwards for [`crate::control::shell_end`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShellEnd {
+	pub reason: ShellCloseReason,
+	pub exit_code: Option<i32>,
+	/// Signal name without `SIG`, when the shell was killed by one.
+	pub exit_signal: Option<String>,
+	pub core_dumped: bool,
+	pub error_message: Option<String>,
+	/// Wall-clock ms, like `created_at_ms`.
+	pub ended_at_ms: f64,
+}
+
+impl ShellEnd {
+	/// An end with nothing from the server.
+	pub(crate) fn new(reason: ShellCloseReason) -> Self {
+		Self::from_exit(reason, ShellExit::default())
+	}
+
+	/// The end of a shell whose channel closed: a remote exit if the server
+	/// reported one, else a bare EOF.
+	pub(crate) fn from_channel(exit: ShellExit) -> Self {
+		let reason = if exit.is_reported() {
+			ShellCloseReason::RemoteExit
+		} else {
+			ShellCloseReason::Eof
+		};
+		Self::from_exit(reason, exit)
+	}
+
+	fn from_exit(reason: ShellCloseReason, exit: ShellExit) -> Self {
+		ShellEnd {
+			reason,
+			exit_code: exit.status.map(|code| code as i32),
+			exit_signal: exit.signal,
+			core_dumped: exit.core_dumped,
+			error_message: exit.error_message,
+			ended_at_ms: fressh_ssh::now_ms(),
+		}
+	}
+}
+
 /// A live shell: the durable `Term`, a writer for stdin/resize, and the two
 /// background tasks (the reader loop feeding `Term`, and the PTY-response drain).
+///
+/// While its connection is being re-established the shell is *detached*: the
+/// `Term` stays in the registry (and keeps rendering) with no channel behind
+/// it, until [`Self::rebind`] attaches a fresh one under the same `shell_id`.
 pub struct ShellSession {
 	pub shell_id: String,
 	pub connection_id: String,
-	pub channel_id: u32,
+	/// The current channel; changes when a reconnect re-binds the shell.
+	pub channel_id: AtomicU32,
 	pub term_type: TerminalType,
 	pub created_at_ms: f64,
 	pub term: SharedTerm,
+	/// The cwd and variables the shell last reported (shell integration), for
+	/// `run_command_in_shell_context`. Survives a re-bind.
+	pub(crate) context: SharedContext,
 	/// Latest renderer cell metrics (physical px), set by the render plane on resize.
 	pub metrics: Mutex<RenderMetrics>,
 	/// Sub-cell scroll accumulator so slow drags still scroll smoothly (mirrors
@@ -94,8 +165,14 @@ pub struct ShellSession {
 	/// `default_cursor_style`. Tracked so we only call the (event-firing)
 	/// `set_options` when it actually changes.
 	default_cursor_blinking: AtomicBool,
-	writer: WriteSink,
-	reader_task: JoinHandle<()>,
+	/// What opened the channel, kept so a reconnect can open an identical one.
+	/// `None` for the preview and mosh shells (nothing to reopen: mosh rides
+	/// out a dropped connection on its own).
+	start_opts: Option<StartShellOptions>,
+	detached: AtomicBool,
+	/// Shared with the PTY-response drain, so a re-bind redirects both.
+	writer: Arc<Mutex<WriteSink>>,
+	reader_task: Mutex<JoinHandle<()>>,
 	pty_task: JoinHandle<()>,
 }
 
@@ -108,6 +185,7 @@ impl ShellSession {
 		shell_id: String,
 		connection_id: String,
 		backend: ShellBackend,
+		start_opts: Option<StartShellOptions>,
 		cols: usize,
 		rows: usize,
 		scrollback_lines: usize,
@@ -129,51 +207,43 @@ impl ShellSession {
 			channel_id,
 			term_type,
 			created_at_ms,
-			mut reader,
+			env_report,
+			reader,
 			writer,
 		} = backend;
 
-		// Reader loop: parse incoming bytes into the durable Term until EOF.
-		let term_for_reader = term.clone();
-		let shell_id_for_reader = shell_id.clone();
-		let reader_task = runtime::handle().spawn(async move {
-			let mut processor: Processor = Processor::new();
-			// Second, low-level vte pass for shell-integration OSCs (7/133). It
-			// touches no `Term` state — only emits `CoreEvent`s — so it runs
-			// OUTSIDE the term lock to keep that critical section tight. See
-			// osc.rs / docs/projects/terminal-semantic-events.md.
-			let mut osc_parser = OscParser::new();
-			let mut osc_scanner = OscScanner::new(shell_id_for_reader.clone());
-			while let Some(bytes) = reader.recv().await {
-				{
-					let mut term = term_for_reader.lock().unwrap_or_else(|p| p.into_inner());
-					processor.advance(&mut *term, &bytes);
-				}
-				osc_parser.advance(&mut osc_scanner, &bytes);
-			}
-			// EOF → the source closed; drop the session and notify JS. (The canned
-			// preview source never reaches here — it parks after its one snippet.)
-			registry::remove_shell(&shell_id_for_reader);
-			events::emit(CoreEvent::ShellClosed {
-				shell_id: shell_id_for_reader,
-			});
-		});
+		let context = SharedContext::default();
+		let reader_task = spawn_reader(
+			shell_id.clone(),
+			connection_id.clone(),
+			term.clone(),
+			context.clone(),
+			env_report,
+			reader,
+			start_opts.is_some(),
+		);
 
 		// PTY-response drain: write terminal query responses back to the server.
+		let writer = Arc::new(Mutex::new(writer));
 		let writer_for_pty = writer.clone();
 		let pty_task = runtime::handle().spawn(async move {
 			while let Some(bytes) = pty_rx.recv().await {
-				let _ = writer_for_pty.send_data(&bytes).await;
+				let writer = writer_for_pty
+					.lock()
+					.unwrap_or_else(|p| p.into_inner())
+					.clone();
+				let _ = writer.send_data(&bytes).await;
 			}
 		});
 
 		Arc::new(Self {
 			shell_id,
 			connection_id,
-			channel_id,
+			channel_id: AtomicU32::new(channel_id),
 			term_type,
 			created_at_ms,
 			term,
+			context,
 			metrics: Mutex::new(RenderMetrics::default()),
 			scroll_remainder: Mutex::new(0.0),
 			// Seed as "just active" so a fresh shell's cursor blinks immediately
@@ -182,12 +252,81 @@ impl ShellSession {
 			scrollback_lines,
 			// `Term` default is steady (false); the render plane seeds On/Always live.
 			default_cursor_blinking: AtomicBool::new(false),
+			start_opts,
+			detached: AtomicBool::new(false),
 			writer,
-			reader_task,
+			reader_task: Mutex::new(reader_task),
 			pty_task,
 		})
 	}
 
+	fn writer(&self) -> WriteSink {
+		self.writer
+			.lock()
+			.unwrap_or_else(|p| p.into_inner())
+			.clone()
+	}
+
+	/// Current grid size, `(cols, rows)`.
+	pub fn size(&self) -> (usize, usize) {
+		let term = self.term.lock().unwrap_or_else(|p| p.into_inner());
+		(term.columns(), term.screen_lines())
+	}
+
+	pub fn is_detached(&self) -> bool {
+		self.detached.load(Ordering::Relaxed)
+	}
+
+	/// The connection dropped: stop reading the dead channel and swallow the
+	/// PTY drain's writes. The `Term` stays as it is.
+	pub(crate) fn detach(&self) {
+		self.detached.store(true, Ordering::Relaxed);
+		self.reader_task
+			.lock()
+			.unwrap_or_else(|p| p.into_inner())
+			.abort();
+		*self.writer.lock().unwrap_or_else(|p| p.into_inner()) = WriteSink::Noop;
+	}
+
+	/// Options to reopen this shell's channel at its current size, or `None` if
+	/// it can't be (a preview).
+	pub(crate) fn reopen_options(&self) -> Option<StartShellOptions> {
+		let (cols, rows) = self.size();
+		let mut opts = self.start_opts.clone()?;
+		opts.terminal_size = Some(TerminalSize {
+			col_width: Some(cols as u32),
+			row_height: Some(rows as u32),
+		});
+		Some(opts)
+	}
+
+	/// Attach a fresh channel to a detached shell: mark the seam in the `Term`,
+	/// then resume the reader loop on the new source.
+	pub(crate) fn rebind(&self, backend: ShellBackend) {
+		{
+			let mut term = self.term.lock().unwrap_or_else(|p| p.into_inner());
+			let mut processor: Processor = Processor::new();
+			processor.advance(&mut *term, RECONNECT_SEPARATOR);
+		}
+		self.channel_id.store(backend.channel_id, Ordering::Relaxed);
+		*self.writer.lock().unwrap_or_else(|p| p.into_inner()) = backend.writer;
+		let reader_task = spawn_reader(
+			self.shell_id.clone(),
+			self.connection_id.clone(),
+			self.term.clone(),
+			self.context.clone(),
+			backend.env_report,
+			backend.reader,
+			true,
+		);
+		let old = std::mem::replace(
+			&mut *self.reader_task.lock().unwrap_or_else(|p| p.into_inner()),
+			reader_task,
+		);
+		old.abort();
+		self.detached.store(false, Ordering::Relaxed);
+	}
+
 	/// Seed the `Term`'s *default* cursor blink (what `On`/`Off` resolve to when no
 	/// program has set DECSCUSR). Driven live by the render plane on config/attach,
 	/// so switching the app's blink mode to `On` affects already-open shells — not
@@ -216,7 +355,10 @@ impl ShellSession {
 	pub async fn send_data(&self, data: &[u8]) -> Result<(), SshError> {
 		// Input is the activity that resets the cursor blink timeout/phase.
 		self.last_input_ms.store(now_ms(), Ordering::Relaxed);
-		self.writer.send_data(data).await
+		if self.is_detached() {
+			return Err(SshError::Disconnected);
+		}
+		self.writer().send_data(data).await
 	}
 
 	/// Milliseconds since the last user input — read by the render plane each
@@ -234,23 +376,71 @@ impl ShellSession {
 				screen_lines: rows.max(1),
 			});
 		}
-		self.writer.resize(cols as u32, rows as u32).await
+		// Detached: nothing to tell; the channel is reopened at the new size.
+		if self.is_detached() {
+			return Ok(());
+		}
+		self.writer().resize(cols as u32, rows as u32).await
 	}
 
 	/// Close the shell: stop the background tasks and close the channel. The
 	/// `Term` is dropped with the session (caller removes it from the registry).
 	pub async fn close(&self) {
-		self.reader_task.abort();
+		self.reader_task
+			.lock()
+			.unwrap_or_else(|p| p.into_inner())
+			.abort();
 		self.pty_task.abort();
-		let _ = self.writer.close().await;
+		let _ = self.writer().close().await;
 	}
 }
 
+/// The reader loop: parse a source's bytes into the durable `Term` until EOF.
+/// `rebindable`: the source is an SSH channel a reconnect could replace.
+fn spawn_reader(
+	shell_id: String,
+	connection_id: String,
+	term: SharedTerm,
+	context: SharedContext,
+	env_report: Option<EnvReport>,
+	mut reader: ReadSource,
+	rebindable: bool,
+) -> JoinHandle<()> {
+	runtime::handle().spawn(async move {
+		let mut processor: Processor = Processor::new();
+		// Second, low-level vte pass for shell-integration OSCs (7/133). It
+		// touches no `Term` state — only emits `CoreEvent`s — so it runs
+		// OUTSIDE the term lock to keep that critical section tight. See
+		// osc.rs / docs/projects/terminal-semantic-events.md.
+		let mut osc_parser = OscParser::new();
+		let mut osc_scanner = OscScanner::new(shell_id.clone(), context, env_report);
+		while let Some(bytes) = reader.recv().await {
+			{
+				let mut term = term.lock().unwrap_or_else(|p| p.into_inner());
+				processor.advance(&mut *term, &bytes);
+			}
+			osc_parser.advance(&mut osc_scanner, &bytes);
+		}
+		// EOF → the source closed: the shell exited, or its connection dropped
+		// (which a reconnect may recover). The canned preview source never gets
+		// here — it parks after its one snippet.
+		let exit = reader.exit();
+		reconnect::shell_eof(shell_id, connection_id, rebindable, exit).await;
+	})
+}
+
 /// A live connection plus its registry id. Shells are tracked in the registry
 /// keyed by their own ids, not nested here (flat id-keyed model, §7).
 pub struct ConnectionSession {
 	pub connection_id: String,
 	pub inner: Arc<SshConnection>,
+	/// The keepalive monitor (`health.rs`), if keepalives are configured.
+	pub health_task: Option<JoinHandle<()>>,
+	/// What `connect` tunnelled through, kept (with `inner`'s details and
+	/// config) so a reconnect can dial the same way.
+	pub jump_hosts: Vec<ConnectionDetails>,
+	/// Set once a reconnect is under way, so only one runs per loss.
+	pub(crate) reconnecting: AtomicBool,
 }
 
 /// Grid dimensions for `Term::new`. History is configured separately via
@@ -275,6 +465,29 @@ impl Dimensions for GridDims {
 mod tests {
 	use super::*;
 
+	#[test]
+	fn a_channel_close_is_a_remote_exit_only_if_the_server_said_so() {
+		let killed = ShellEnd::from_channel(ShellExit {
+			status: None,
+			signal: Some("KILL".into()),
+			core_dumped: true,
+			error_message: None,
+		});
+		assert_eq!(killed.reason, ShellCloseReason::RemoteExit);
+		assert_eq!(killed.exit_signal.as_deref(), Some("KILL"));
+		assert!(killed.core_dumped);
+		let exited = ShellEnd::from_channel(ShellExit {
+			status: Some(3),
+			..ShellExit::default()
+		});
+		assert_eq!(
+			(exited.reason, exited.exit_code),
+			(ShellCloseReason::RemoteExit, Some(3))
+		);
+		let bare = ShellEnd::from_channel(ShellExit::default());
+		assert_eq!((bare.reason, bare.exit_code), (ShellCloseReason::Eof, None));
+	}
+
 	/// The `Term`'s replies to queries (here a Device Status Report) must flow
 	/// through `CoreListener` onto the PTY-response channel so the drain task can
 	/// write them back to the server. This is the seam the reader loop relies on.
@@ -0,0 +1,23 @@
+//! SFTP sessions: long-lived registry entries, like forwards.
+//!
+//! `open_sftp` starts one on a connection and returns its id; the file
+//! operations in [`crate::control`] then address it by that id until
+//! `close_sftp`, or until its connection goes away (a reconnect doesn't carry
+//! it over — the app reopens). Directory listings and small reads/writes cross
+//! to JS; bulk transfers to and from device files stay native.
+
+use fressh_ssh::Sftp;
+
+pub struct SftpSession {
+	pub sftp_id: String,
+	pub connection_id: String,
+	pub(crate) inner: Sftp,
+}
+
+impl SftpSession {
+	/// End the session, closing its channel. The caller removes it from the
+	/// registry.
+	pub async fn close(&self) {
+		let _ = self.inner.close().await;
+	}
+}
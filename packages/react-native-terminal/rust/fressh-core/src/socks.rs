@@ -0,0 +1,319 @@
+//! The server side of the SOCKS handshake, for dynamic forwarding (`-D`).
+//!
+//! Just enough SOCKS to learn where a local client wants to go: SOCKS5 (RFC 1928)
+//! with the "no authentication" method and SOCKS4/4a, `CONNECT` only. Once the
+//! target is known the caller opens a `direct-tcpip` channel to it, answers with
+//! [`send_reply`], and from then on the socket is a plain byte pipe. The server
+//! resolves hostnames (SOCKS5 domain / SOCKS4a), so DNS happens on the far side —
+//! which is what makes internal names work.
+
+use std::io;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::time::Duration;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const SOCKS4: u8 = 0x04;
+const SOCKS5: u8 = 0x05;
+const CMD_CONNECT: u8 = 0x01;
+const NO_AUTH: u8 = 0x00;
+const NO_ACCEPTABLE_METHOD: u8 = 0xFF;
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_DOMAIN: u8 = 0x03;
+const ATYP_IPV6: u8 = 0x04;
+const REP5_SUCCEEDED: u8 = 0x00;
+/// The SOCKS5 failure code for "the server couldn't open the channel".
+pub(crate) const REP5_HOST_UNREACHABLE: u8 = 0x04;
+const REP5_COMMAND_NOT_SUPPORTED: u8 = 0x07;
+const REP4_GRANTED: u8 = 0x5A;
+const REP4_REJECTED: u8 = 0x5B;
+
+/// How long a client gets to finish the handshake; a silent one would
+/// otherwise hold its socket and task forever.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Longest SOCKS4 user id / SOCKS4a hostname we buffer (both are NUL-terminated
+/// with no length prefix, so a bound is needed).
+const MAX_SOCKS4_FIELD: usize = 255;
+
+/// Which dialect the client spoke; the reply must match it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SocksVersion {
+	V4,
+	V5,
+}
+
+/// A client's `CONNECT` target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct SocksRequest {
+	pub version: SocksVersion,
+	pub host: String,
+	pub port: u16,
+}
+
+fn invalid(msg: &str) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, format!("socks: {msg}"))
+}
+
+/// Run the handshake up to (not including) the reply: negotiate the method and
+/// read the `CONNECT` request. Anything we don't support is refused on the wire
+/// before the error is returned; a client that takes longer than
+/// [`HANDSHAKE_TIMEOUT`] gets `TimedOut`.
+pub(crate) async fn read_request<S>(stream: &mut S) -> io::Result<SocksRequest>
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	read_request_within(stream, HANDSHAKE_TIMEOUT).await
+}
+
+async fn read_request_within<S>(stream: &mut S, limit: Duration) -> io::Result<SocksRequest>
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	let handshake = async {
+		match stream.read_u8().await? {
+			SOCKS5 => read_socks5_request(stream).await,
+			SOCKS4 => read_socks4_request(stream).await,
+			other => Err(invalid(&format!("unsupported version {other:#04x}"))),
+		}
+	};
+	tokio::time::timeout(limit, handshake)
+		.await
+		.map_err(|_| io::Error::new(io::ErrorKind::TimedOut, "socks: handshake timed out"))?
+}
+
+async fn read_socks5_request<S>(stream: &mut S) -> io::Result<SocksRequest>
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	let n_methods = stream.read_u8().await? as usize;
+	let mut methods = vec![0u8; n_methods];
+	stream.read_exact(&mut methods).await?;
+	if !methods.contains(&NO_AUTH) {
+		stream.write_all(&[SOCKS5, NO_ACCEPTABLE_METHOD]).await?;
+		return Err(invalid("client requires authentication"));
+	}
+	stream.write_all(&[SOCKS5, NO_AUTH]).await?;
+
+	let mut head = [0u8; 4];
+	stream.read_exact(&mut head).await?;
+	let [version, cmd, _reserved, atyp] = head;
+	if version != SOCKS5 {
+		return Err(invalid("bad request version"));
+	}
+	let host = match atyp {
+		ATYP_IPV4 => {
+			let mut ip = [0u8; 4];
+			stream.read_exact(&mut ip).await?;
+			Ipv4Addr::from(ip).to_string()
+		}
+		ATYP_IPV6 => {
+			let mut ip = [0u8; 16];
+			stream.read_exact(&mut ip).await?;
+			Ipv6Addr::from(ip).to_string()
+		}
+		ATYP_DOMAIN => {
+			let len = stream.read_u8().await? as usize;
+			let mut name = vec![0u8; len];
+			stream.read_exact(&mut name).await?;
+			String::from_utf8(name).map_err(|_| invalid("hostname is not UTF-8"))?
+		}
+		other => return Err(invalid(&format!("unknown address type {other:#04x}"))),
+	};
+	let port = stream.read_u16().await?;
+	if cmd != CMD_CONNECT {
+		// BIND / UDP ASSOCIATE have no `direct-tcpip` equivalent.
+		send_reply(stream, SocksVersion::V5, false, REP5_COMMAND_NOT_SUPPORTED).await?;
+		return Err(invalid(&format!("unsupported command {cmd:#04x}")));
+	}
+	Ok(SocksRequest {
+		version: SocksVersion::V5,
+		host,
+		port,
+	})
+}
+
+async fn read_socks4_request<S>(stream: &mut S) -> io::Result<SocksRequest>
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	let cmd = stream.read_u8().await?;
+	let port = stream.read_u16().await?;
+	let mut ip = [0u8; 4];
+	stream.read_exact(&mut ip).await?;
+	let _user_id = read_nul_terminated(stream).await?;
+	// SOCKS4a: an IP of 0.0.0.x (x ≠ 0) means "hostname follows".
+	let host = if ip[..3] == [0, 0, 0] && ip[3] != 0 {
+		String::from_utf8(read_nul_terminated(stream).await?)
+			.map_err(|_| invalid("hostname is not UTF-8"))?
+	} else {
+		Ipv4Addr::from(ip).to_string()
+	};
+	if cmd != CMD_CONNECT {
+		send_reply(stream, SocksVersion::V4, false, REP5_COMMAND_NOT_SUPPORTED).await?;
+		return Err(invalid(&format!("unsupported command {cmd:#04x}")));
+	}
+	Ok(SocksRequest {
+		version: SocksVersion::V4,
+		host,
+		port,
+	})
+}
+
+async fn read_nul_terminated<S>(stream: &mut S) -> io::Result<Vec<u8>>
+where
+	S: AsyncRead + Unpin,
+{
+	let mut out = Vec::new();
+	loop {
+		match stream.read_u8().await? {
+			0 => return Ok(out),
+			b if out.len() < MAX_SOCKS4_FIELD => out.push(b),
+			_ => return Err(invalid("field too long")),
+		}
+	}
+}
+
+/// Answer a `CONNECT`: granted once the channel is open, refused if it failed.
+/// `rep5` is the SOCKS5 failure code (SOCKS4 only has granted/rejected). The
+/// bound address is reported as zeros — clients don't use it for `CONNECT`.
+pub(crate) async fn send_reply<S>(
+	stream: &mut S,
+	version: SocksVersion,
+	granted: bool,
+	rep5: u8,
+) -> io::Result<()>
+where
+	S: AsyncWrite + Unpin,
+{
+	match version {
+		SocksVersion::V5 => {
+			let rep = if granted { REP5_SUCCEEDED } else { rep5 };
+			stream
+				.write_all(&[SOCKS5, rep, 0, ATYP_IPV4, 0, 0, 0, 0, 0, 0])
+				.await
+		}
+		SocksVersion::V4 => {
+			let rep = if granted { REP4_GRANTED } else { REP4_REJECTED };
+			stream.write_all(&[0, rep, 0, 0, 0, 0, 0, 0]).await
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	/// Run `read_request` against `client_bytes`, then return the parsed request
+	/// and everything the server wrote back.
+	fn handshake(client_bytes: &[u8]) -> (io::Result<SocksRequest>, Vec<u8>) {
+		crate::runtime::handle().block_on(async {
+			let (mut server, mut client) = tokio::io::duplex(512);
+			client.write_all(client_bytes).await.unwrap();
+			let req = read_request(&mut server).await;
+			drop(server);
+			let mut written = Vec::new();
+			client.read_to_end(&mut written).await.unwrap();
+			(req, written)
+		})
+	}
+
+	#[test]
+	fn a_stalled_handshake_times_out() {
+		crate::runtime::handle().block_on(async {
+			let (mut server, mut client) = tokio::io::duplex(512);
+			// The greeting, then nothing.
+			client.write_all(&[5, 1, 0]).await.unwrap();
+			let err = read_request_within(&mut server, Duration::from_millis(50))
+				.await
+				.unwrap_err();
+			assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+		});
+	}
+
+	#[test]
+	fn socks5_domain_connect() {
+		let mut bytes = vec![5, 1, 0, 5, 1, 0, 3, 11];
+		bytes.extend_from_slice(b"example.com");
+		bytes.extend_from_slice(&443u16.to_be_bytes());
+		let (req, written) = handshake(&bytes);
+		assert_eq!(
+			req.unwrap(),
+			SocksRequest {
+				version: SocksVersion::V5,
+				host: "example.com".into(),
+				port: 443,
+			}
+		);
+		assert_eq!(written, [5, 0], "must select the no-auth method");
+	}
+
+	#[test]
+	fn socks5_ipv4_and_ipv6_connect() {
+		let (req, _) = handshake(&[5, 1, 0, 5, 1, 0, 1, 10, 0, 0, 7, 0, 22]);
+		let req = req.unwrap();
+		assert_eq!((req.host.as_str(), req.port), ("10.0.0.7", 22));
+
+		let mut bytes = vec![5, 1, 0, 5, 1, 0, 4];
+		bytes.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+		bytes.extend_from_slice(&[0, 80]);
+		let (req, _) = handshake(&bytes);
+		assert_eq!(req.unwrap().host, "::1");
+	}
+
+	#[test]
+	fn socks5_auth_only_client_is_refused() {
+		// Offers only username/password (0x02).
+		let (req, written) = handshake(&[5, 1, 2]);
+		assert!(req.is_err());
+		assert_eq!(written, [5, 0xFF]);
+	}
+
+	#[test]
+	fn socks5_bind_is_refused_with_command_not_supported() {
+		let (req, written) = handshake(&[5, 1, 0, 5, 2, 0, 1, 127, 0, 0, 1, 0, 80]);
+		assert!(req.is_err());
+		assert_eq!(written[..2], [5, 0]);
+		assert_eq!(written[2..4], [5, REP5_COMMAND_NOT_SUPPORTED]);
+	}
+
+	#[test]
+	fn socks4_ip_connect() {
+		let (req, written) = handshake(&[4, 1, 0, 80, 192, 168, 1, 2, b'u', 0]);
+		assert_eq!(
+			req.unwrap(),
+			SocksRequest {
+				version: SocksVersion::V4,
+				host: "192.168.1.2".into(),
+				port: 80,
+			}
+		);
+		assert!(written.is_empty(), "SOCKS4 has no method negotiation");
+	}
+
+	#[test]
+	fn socks4a_hostname_connect() {
+		let mut bytes = vec![4, 1, 0x1F, 0x90, 0, 0, 0, 1, 0];
+		bytes.extend_from_slice(b"intranet.local\0");
+		let (req, _) = handshake(&bytes);
+		let req = req.unwrap();
+		assert_eq!((req.host.as_str(), req.port), ("intranet.local", 8080));
+	}
+
+	#[test]
+	fn replies_match_the_dialect() {
+		crate::runtime::handle().block_on(async {
+			let mut out = Vec::new();
+			send_reply(&mut out, SocksVersion::V5, true, REP5_HOST_UNREACHABLE)
+				.await
+				.unwrap();
+			assert_eq!(out[..2], [5, 0]);
+
+			let mut out = Vec::new();
+			send_reply(&mut out, SocksVersion::V4, false, REP5_HOST_UNREACHABLE)
+				.await
+				.unwrap();
+			assert_eq!(out[..2], [0, REP4_REJECTED]);
+		});
+	}
+}
@@ -7,6 +7,8 @@
 //! **write half** (stdin / resize / close). This module factors those behind two
 //! small *closed* enums so one `Term` can be driven by:
 //!   - SSH today ([`ReadSource::Ssh`] / [`WriteSink::Ssh`]),
+//!   - mosh, SSH-bootstrapped but then its own UDP transport
+//!     ([`ReadSource::Mosh`] / [`WriteSink::Mosh`], see [`crate::mosh`]),
 //!   - a canned snippet for the Terminal-settings live preview
 //!     ([`ReadSource::Canned`] / [`WriteSink::Noop`]),
 //!   - and a local PTY tomorrow (the on-device-shell project adds `Local` arms).
@@ -16,13 +18,19 @@
 
 use bytes::Bytes;
 
-use fressh_ssh::{Shell, ShellReader, ShellWriter, SshError, TerminalType};
+use fressh_ssh::{
+	now_ms, EnvReport, Shell, ShellExit, ShellReader, ShellWriter, SshError, TerminalType,
+};
+
+use crate::mosh::{MoshReader, MoshWriter};
 
 /// The read half: the reader loop awaits [`recv`](ReadSource::recv) until it
 /// returns `None` (EOF → the session is torn down). Each variant is one source.
 pub enum ReadSource {
 	/// Live SSH channel output.
 	Ssh(ShellReader),
+	/// Screen updates from a mosh server, as terminal bytes.
+	Mosh(MoshReader),
 	/// A fixed snippet delivered once, then the stream parks forever — so a
 	/// preview `Term` is fed its demo content and then stays alive and bound like
 	/// a real shell instead of hitting the EOF branch that removes it.
@@ -30,11 +38,12 @@ pub enum ReadSource {
 }
 
 impl ReadSource {
-	/// Await the next output chunk. `None` means EOF — only the SSH arm ever
-	/// returns it; the canned arm yields its snippet once and then never resolves.
+	/// Await the next output chunk. `None` means EOF — only the live arms ever
+	/// return it; the canned arm yields its snippet once and then never resolves.
 	pub async fn recv(&mut self) -> Option<Bytes> {
 		match self {
 			ReadSource::Ssh(reader) => reader.recv().await.map(|chunk| chunk.bytes),
+			ReadSource::Mosh(reader) => reader.recv().await,
 			ReadSource::Canned(pending) => match pending.take() {
 				Some(bytes) => Some(bytes),
 				// Already delivered: park forever so the reader loop never sees EOF
@@ -43,6 +52,15 @@ impl ReadSource {
 			},
 		}
 	}
+
+	/// How the source said the shell exited before its EOF. Only an SSH
+	/// channel says anything.
+	pub fn exit(&self) -> ShellExit {
+		match self {
+			ReadSource::Ssh(reader) => reader.exit().clone(),
+			ReadSource::Mosh(_) | ReadSource::Canned(_) => ShellExit::default(),
+		}
+	}
 }
 
 /// The write half: stdin, terminal resize, and channel close. Cheap to clone (the
@@ -51,6 +69,9 @@ impl ReadSource {
 pub enum WriteSink {
 	/// Live SSH channel input.
 	Ssh(ShellWriter),
+	/// Input to a mosh server. Never blocks on the network: mosh queues and
+	/// retransmits until the server has it.
+	Mosh(MoshWriter),
 	/// A sink that swallows everything — a canned preview has nowhere to write
 	/// (no stdin, no remote to inform of a resize).
 	Noop,
@@ -61,6 +82,7 @@ impl WriteSink {
 	pub async fn send_data(&self, data: &[u8]) -> Result<(), SshError> {
 		match self {
 			WriteSink::Ssh(writer) => writer.send_data(data).await,
+			WriteSink::Mosh(writer) => writer.send_data(data),
 			WriteSink::Noop => Ok(()),
 		}
 	}
@@ -70,6 +92,7 @@ impl WriteSink {
 	pub async fn resize(&self, cols: u32, rows: u32) -> Result<(), SshError> {
 		match self {
 			WriteSink::Ssh(writer) => writer.resize(cols, rows, 0, 0).await,
+			WriteSink::Mosh(writer) => writer.resize(cols, rows),
 			WriteSink::Noop => Ok(()),
 		}
 	}
@@ -78,6 +101,7 @@ impl WriteSink {
 	pub async fn close(&self) -> Result<(), SshError> {
 		match self {
 			WriteSink::Ssh(writer) => writer.close().await,
+			WriteSink::Mosh(writer) => writer.close(),
 			WriteSink::Noop => Ok(()),
 		}
 	}
@@ -90,6 +114,9 @@ pub struct ShellBackend {
 	pub channel_id: u32,
 	pub term_type: TerminalType,
 	pub created_at_ms: f64,
+	/// How to tell the shell's genuine env reports from forged ones; `None`
+	/// (no integration) keeps none.
+	pub env_report: Option<EnvReport>,
 	pub reader: ReadSource,
 	pub writer: WriteSink,
 }
@@ -101,11 +128,25 @@ impl ShellBackend {
 			channel_id: shell.channel_id,
 			term_type: shell.term,
 			created_at_ms: shell.created_at_ms,
+			env_report: shell.env_report,
 			reader: ReadSource::Ssh(shell.reader),
 			writer: WriteSink::Ssh(shell.writer),
 		}
 	}
 
+	/// Wrap a started mosh transport. `port` (the server's UDP port) stands in
+	/// for the channel id — a mosh shell has no channel.
+	pub fn from_mosh(port: u16, reader: MoshReader, writer: MoshWriter) -> Self {
+		Self {
+			channel_id: u32::from(port),
+			term_type: TerminalType::Xterm256,
+			created_at_ms: now_ms(),
+			env_report: None,
+			reader: ReadSource::Mosh(reader),
+			writer: WriteSink::Mosh(writer),
+		}
+	}
+
 	/// A non-SSH source that feeds `demo` once and then idles, with a no-op writer.
 	/// Used by the Terminal-settings live preview. The metadata is synthetic — a
 	/// preview has no channel — but harmless: it lives in the registry under a
@@ -115,6 +156,7 @@ impl ShellBackend {
 			channel_id: 0,
 			term_type: TerminalType::Xterm256,
 			created_at_ms: 0.0,
+			env_report: None,
 			reader: ReadSource::Canned(Some(demo)),
 			writer: WriteSink::Noop,
 		}
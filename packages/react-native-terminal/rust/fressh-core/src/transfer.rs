@@ -0,0 +1,367 @@
+//! File transfers between device files and the remote host, over an SFTP
+//! session or — where the server has no `sftp` subsystem — `scp`.
+//!
+//! Each transfer is a registry entry with its own task on the core runtime; at
+//! most [`MAX_RUNNING`] move bytes at once, the rest wait their turn. Like the
+//! shell data plane, the bytes never reach JS: it names the two paths and then
+//! only sees throttled `TransferProgress` events, then `TransferFinished` or
+//! `TransferFailed`.
+//!
+//! Pausing aborts the task but keeps the entry. Resuming restarts it from what
+//! the destination already holds — the device file's length for a download,
+//! the remote file's size for an upload — rather than from our own counter, so
+//! a chunk written but not yet acknowledged isn't skipped. When the copy ends,
+//! the destination's size is checked against the source's.
+//!
+//! SCP has no offsets, so a resumed SCP transfer starts over; it can also copy
+//! whole directories. Its total is known up front only for a single-file
+//! upload.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use once_cell::sync::Lazy;
+use tokio::fs::{File, OpenOptions};
+use tokio::io::{AsyncSeekExt, SeekFrom};
+use tokio::sync::Semaphore;
+use tokio::task::JoinHandle;
+
+use fressh_ssh::{ScpOptions, SshError};
+
+use crate::events::{self, CoreEvent};
+use crate::{registry, runtime};
+
+/// Transfers moving bytes at once; more are queued.
+const MAX_RUNNING: usize = 3;
+
+/// Minimum gap between two `TransferProgress` events for one transfer.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+static RUNNING: Lazy<Semaphore> = Lazy::new(|| Semaphore::new(MAX_RUNNING));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferDirection {
+	/// Remote file → device file.
+	Download,
+	/// Device file → remote file.
+	Upload,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TransferState {
+	/// Waiting for one of the [`MAX_RUNNING`] slots.
+	Queued,
+	Running,
+	Paused,
+}
+
+/// What carries a transfer's bytes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransferVia {
+	Sftp {
+		sftp_id: String,
+	},
+	/// An `scp` exec channel per run, opened on the connection.
+	Scp {
+		connection_id: String,
+		options: ScpOptions,
+	},
+}
+
+/// A snapshot of a transfer for the control plane (`list_transfers`).
+#[derive(Debug, Clone)]
+pub struct TransferInfo {
+	pub transfer_id: String,
+	pub via: TransferVia,
+	pub direction: TransferDirection,
+	pub remote_path: String,
+	pub local_path: String,
+	pub state: TransferState,
+	/// Bytes at the destination so far.
+	pub bytes: u64,
+	/// The source's size, once known.
+	pub total: Option<u64>,
+}
+
+pub struct Transfer {
+	pub transfer_id: String,
+	pub via: TransferVia,
+	pub direction: TransferDirection,
+	pub remote_path: String,
+	pub local_path: String,
+	bytes: AtomicU64,
+	total: Mutex<Option<u64>>,
+	state: Mutex<TransferState>,
+	task: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl Transfer {
+	pub(crate) fn new(
+		transfer_id: String,
+		via: TransferVia,
+		direction: TransferDirection,
+		remote_path: String,
+		local_path: String,
+	) -> Arc<Self> {
+		Arc::new(Transfer {
+			transfer_id,
+			via,
+			direction,
+			remote_path,
+			local_path,
+			bytes: AtomicU64::new(0),
+			total: Mutex::new(None),
+			state: Mutex::new(TransferState::Queued),
+			task: Mutex::new(None),
+		})
+	}
+
+	pub fn info(&self) -> TransferInfo {
+		TransferInfo {
+			transfer_id: self.transfer_id.clone(),
+			via: self.via.clone(),
+			direction: self.direction,
+			remote_path: self.remote_path.clone(),
+			local_path: self.local_path.clone(),
+			state: self.state(),
+			bytes: self.bytes.load(Ordering::Relaxed),
+			total: *self.total.lock().unwrap_or_else(|p| p.into_inner()),
+		}
+	}
+
+	pub fn state(&self) -> TransferState {
+		*self.state.lock().unwrap_or_else(|p| p.into_inner())
+	}
+
+	fn set_state(&self, state: TransferState) {
+		*self.state.lock().unwrap_or_else(|p| p.into_inner()) = state;
+	}
+
+	/// Queue the copy: from scratch, or (`resume`) from what the destination
+	/// already holds.
+	pub(crate) fn start(self: &Arc<Self>, resume: bool) {
+		self.set_state(TransferState::Queued);
+		let task = runtime::handle().spawn(run(self.clone(), resume));
+		*self.task.lock().unwrap_or_else(|p| p.into_inner()) = Some(task);
+	}
+
+	/// Stop moving bytes, keeping what was copied. The entry stays in the
+	/// registry for a later [`Self::start`] with `resume`.
+	pub(crate) fn pause(&self) {
+		self.abort();
+		self.set_state(TransferState::Paused);
+	}
+
+	pub(crate) fn abort(&self) {
+		if let Some(task) = self.task.lock().unwrap_or_else(|p| p.into_inner()).take() {
+			task.abort();
+		}
+	}
+}
+
+/// Stop and drop every transfer carried by `via`'s session or connection,
+/// which is going away, reporting each as failed with `message`.
+pub(crate) fn fail_riding(via: impl Fn(&TransferVia) -> bool, message: &str) {
+	for transfer in registry::transfers() {
+		if !via(&transfer.via) {
+			continue;
+		}
+		if let Some(transfer) = registry::remove_transfer(&transfer.transfer_id) {
+			transfer.abort();
+			events::emit(CoreEvent::TransferFailed {
+				transfer_id: transfer.transfer_id.clone(),
+				message: message.to_string(),
+			});
+		}
+	}
+}
+
+/// Lets one progress report through per [`PROGRESS_INTERVAL`].
+struct Throttle {
+	last: Option<Instant>,
+}
+
+impl Throttle {
+	fn ready(&mut self, now: Instant) -> bool {
+		if self
+			.last
+			.is_some_and(|last| now.duration_since(last) < PROGRESS_INTERVAL)
+		{
+			return false;
+		}
+		self.last = Some(now);
+		true
+	}
+}
+
+async fn run(transfer: Arc<Transfer>, resume: bool) {
+	let Ok(_slot) = RUNNING.acquire().await else {
+		return;
+	};
+	transfer.set_state(TransferState::Running);
+	let result = copy(&transfer, resume).await;
+	// Gone already ⇒ [`fail_riding`] beat us to it and has reported it.
+	if registry::remove_transfer(&transfer.transfer_id).is_none() {
+		return;
+	}
+	match result {
+		Ok(bytes) => events::emit(CoreEvent::TransferFinished {
+			transfer_id: transfer.transfer_id.clone(),
+			bytes,
+		}),
+		Err(e) => events::emit(CoreEvent::TransferFailed {
+			transfer_id: transfer.transfer_id.clone(),
+			message: e.to_string(),
+		}),
+	}
+}
+
+/// Copy in `transfer`'s direction and check the result. Returns the size.
+async fn copy(transfer: &Transfer, resume: bool) -> Result<u64, SshError> {
+	let throttle = Mutex::new(Throttle { last: None });
+	let progress = |bytes: u64| {
+		transfer.bytes.store(bytes, Ordering::Relaxed);
+		if throttle
+			.lock()
+			.unwrap_or_else(|p| p.into_inner())
+			.ready(Instant::now())
+		{
+			events::emit(CoreEvent::TransferProgress {
+				transfer_id: transfer.transfer_id.clone(),
+				bytes,
+				total: *transfer.total.lock().unwrap_or_else(|p| p.into_inner()),
+			});
+		}
+	};
+
+	let sftp_id = match &transfer.via {
+		TransferVia::Sftp { sftp_id } => sftp_id,
+		TransferVia::Scp {
+			connection_id,
+			options,
+		} => return copy_scp(transfer, connection_id, *options, &progress).await,
+	};
+	let sftp = registry::sftp(sftp_id).ok_or_else(|| SshError::NotFound(sftp_id.clone()))?;
+	let sftp = &sftp.inner;
+	let remote = transfer.remote_path.as_str();
+	let (expected, written) = match transfer.direction {
+		TransferDirection::Download => {
+			let expected = sftp.stat(remote).await?.size;
+			*transfer.total.lock().unwrap_or_else(|p| p.into_inner()) = expected;
+			let mut file = OpenOptions::new()
+				.create(true)
+				.write(true)
+				.truncate(!resume)
+				.open(&transfer.local_path)
+				.await?;
+			let offset = file.seek(SeekFrom::End(0)).await?;
+			progress(offset);
+			sftp.download(remote, offset, &mut file, &progress).await?;
+			file.sync_all().await?;
+			(expected, file.metadata().await?.len())
+		}
+		TransferDirection::Upload => {
+			let mut file = File::open(&transfer.local_path).await?;
+			let expected = file.metadata().await?.len();
+			*transfer.total.lock().unwrap_or_else(|p| p.into_inner()) = Some(expected);
+			let offset = if resume {
+				// A missing remote file just means nothing arrived yet.
+				let arrived = sftp.stat(remote).await.ok().and_then(|s| s.size);
+				arrived.unwrap_or(0).min(expected)
+			} else {
+				0
+			};
+			file.seek(SeekFrom::Start(offset)).await?;
+			progress(offset);
+			sftp.upload(&mut file, remote, offset, &progress).await?;
+			(Some(expected), sftp.stat(remote).await?.size.unwrap_or(0))
+		}
+	};
+	match expected {
+		Some(expected) if expected != written => Err(SshError::Sftp(format!(
+			"size mismatch after transfer: expected {expected} bytes, got {written}"
+		))),
+		_ => Ok(written),
+	}
+}
+
+/// The SCP leg of [`copy`]: always from the start. Returns the file bytes
+/// moved; the remote `scp` acknowledges each file, so there is nothing to
+/// check afterwards.
+async fn copy_scp(
+	transfer: &Transfer,
+	connection_id: &str,
+	options: ScpOptions,
+	progress: &(dyn Fn(u64) + Sync),
+) -> Result<u64, SshError> {
+	let conn = registry::connection(connection_id)
+		.ok_or_else(|| SshError::NotFound(connection_id.to_string()))?;
+	let local = std::path::Path::new(&transfer.local_path);
+	let remote = transfer.remote_path.as_str();
+	progress(0);
+	match transfer.direction {
+		TransferDirection::Download => {
+			conn.inner
+				.scp_download(remote, local, options, progress)
+				.await
+		}
+		TransferDirection::Upload => {
+			let meta = tokio::fs::metadata(local).await?;
+			let total = meta.is_file().then_some(meta.len());
+			*transfer.total.lock().unwrap_or_else(|p| p.into_inner()) = total;
+			conn.inner
+				.scp_upload(local, remote, options, progress)
+				.await
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn progress_is_throttled() {
+		let mut throttle = Throttle { last: None };
+		let start = Instant::now();
+		let passed: Vec<bool> = [0, 100, 240, 260, 400, 600]
+			.iter()
+			.map(|ms| throttle.ready(start + Duration::from_millis(*ms)))
+			.collect();
+		assert_eq!(passed, [true, false, false, true, false, true]);
+	}
+
+	#[test]
+	fn only_the_closed_sessions_transfers_fail() {
+		let on_sftp = Transfer::new(
+			"me@host:22#1/sftp1/xfer1".to_string(),
+			TransferVia::Sftp {
+				sftp_id: "me@host:22#1/sftp1".to_string(),
+			},
+			TransferDirection::Download,
+			"/remote".to_string(),
+			"/local".to_string(),
+		);
+		let on_scp = Transfer::new(
+			"me@host:22#1/xfer2".to_string(),
+			TransferVia::Scp {
+				connection_id: "me@host:22#1".to_string(),
+				options: ScpOptions::default(),
+			},
+			TransferDirection::Upload,
+			"/remote".to_string(),
+			"/local".to_string(),
+		);
+		registry::insert_transfer(on_sftp.clone());
+		registry::insert_transfer(on_scp.clone());
+
+		fail_riding(
+			|via| matches!(via, TransferVia::Sftp { sftp_id } if sftp_id == "me@host:22#1/sftp1"),
+			"SFTP session closed",
+		);
+		assert!(registry::transfer(&on_sftp.transfer_id).is_none());
+		assert!(registry::transfer(&on_scp.transfer_id).is_some());
+		registry::remove_transfer(&on_scp.transfer_id);
+	}
+}
@@ -0,0 +1,343 @@
+//! Algorithm preferences (what we offer in KEXINIT) and the negotiated result.
+//!
+//! Preferences map onto russh's [`Preferred`]; an empty list keeps russh's
+//! default for that slot, so old gear can be reached by *adding* e.g.
+//! `diffie-hellman-group14-sha1` / `ssh-rsa`, and a hardened profile by listing
+//! only what's allowed.
+//!
+//! russh doesn't expose what it negotiated, but the negotiation is public
+//! knowledge: both KEXINITs travel in the clear and RFC 4253 §7.1 picks the
+//! client's first choice the server also offers. So [`KexInitTap`] records the
+//! server's KEXINIT off the wire and [`negotiate`] re-runs russh's selection.
+
+use std::borrow::Cow;
+use std::io;
+use std::pin::Pin;
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use russh::keys::Algorithm;
+use russh::{cipher, compression, kex, mac, Preferred};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+
+use crate::connection::Transport;
+use crate::utils::SshError;
+
+/// Algorithm names to offer, most preferred first, per slot. Empty ⇒ russh's
+/// default list for that slot.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AlgorithmPreferences {
+	pub kex: Vec<String>,
+	/// Host-key signature algorithms (`ssh-ed25519`, `rsa-sha2-512`, `ssh-rsa`…).
+	pub host_key: Vec<String>,
+	pub cipher: Vec<String>,
+	pub mac: Vec<String>,
+	pub compression: Vec<String>,
+}
+
+impl AlgorithmPreferences {
+	/// The russh preference lists, or `UnsupportedAlgorithm` naming the first
+	/// entry russh doesn't implement.
+	pub(crate) fn preferred(&self) -> Result<Preferred, SshError> {
+		let mut preferred = Preferred::default();
+		if !self.kex.is_empty() {
+			let mut names = parse(&self.kex, |s| kex::Name::try_from(s).ok())?;
+			// Not algorithms but capability flags riding the kex list; dropping
+			// them would silently lose strict kex (the Terrapin fix).
+			for ext in [
+				kex::EXTENSION_SUPPORT_AS_CLIENT,
+				kex::EXTENSION_OPENSSH_STRICT_KEX_AS_CLIENT,
+			] {
+				if !names.contains(&ext) {
+					names.push(ext);
+				}
+			}
+			preferred.kex = Cow::Owned(names);
+		}
+		if !self.host_key.is_empty() {
+			preferred.key = Cow::Owned(parse(&self.host_key, |s| Algorithm::from_str(s).ok())?);
+		}
+		if !self.cipher.is_empty() {
+			preferred.cipher = Cow::Owned(parse(&self.cipher, |s| cipher::Name::try_from(s).ok())?);
+		}
+		if !self.mac.is_empty() {
+			preferred.mac = Cow::Owned(parse(&self.mac, |s| mac::Name::try_from(s).ok())?);
+		}
+		if !self.compression.is_empty() {
+			preferred.compression = Cow::Owned(parse(&self.compression, |s| {
+				compression::Name::try_from(s).ok()
+			})?);
+		}
+		Ok(preferred)
+	}
+}
+
+fn parse<T>(names: &[String], f: impl Fn(&str) -> Option<T>) -> Result<Vec<T>, SshError> {
+	names
+		.iter()
+		.map(|name| f(name.trim()).ok_or_else(|| SshError::UnsupportedAlgorithm(name.clone())))
+		.collect()
+}
+
+/// What a connection ended up using. MACs are `None` under an AEAD cipher
+/// (chacha20-poly1305, AES-GCM), which authenticates on its own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NegotiatedAlgorithms {
+	pub kex: String,
+	pub host_key: String,
+	/// russh uses the client → server choice in both directions.
+	pub cipher: String,
+	pub mac_client_to_server: Option<String>,
+	pub mac_server_to_client: Option<String>,
+	pub compression_client_to_server: String,
+	pub compression_server_to_client: String,
+}
+
+/// The server's KEXINIT name-lists, in wire order: kex, host key, cipher ×2,
+/// MAC ×2, compression ×2 (languages are dropped).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ServerKexInit(Vec<Vec<String>>);
+
+impl ServerKexInit {
+	/// Find the KEXINIT in the first bytes the server sent: optional banner
+	/// lines, the `SSH-` version line, then the (unencrypted) first packet.
+	/// `None` until enough has arrived — or if it isn't a KEXINIT at all.
+	fn parse(data: &[u8]) -> Option<Self> {
+		let mut rest = data;
+		loop {
+			let end = rest.iter().position(|&b| b == b'\n')?;
+			let line = &rest[..end];
+			rest = &rest[end + 1..];
+			if line.starts_with(b"SSH-") {
+				break;
+			}
+		}
+		let len = u32::from_be_bytes(rest.get(..4)?.try_into().ok()?) as usize;
+		let packet = rest.get(4..4 + len)?;
+		let padding = usize::from(*packet.first()?);
+		let payload = packet.get(1..len.checked_sub(padding)?)?;
+		// SSH_MSG_KEXINIT, then a 16-byte cookie.
+		if payload.first() != Some(&20) {
+			return None;
+		}
+		let mut r = payload.get(17..)?;
+		let mut lists = Vec::with_capacity(8);
+		for _ in 0..8 {
+			let n = u32::from_be_bytes(r.get(..4)?.try_into().ok()?) as usize;
+			let list = std::str::from_utf8(r.get(4..4 + n)?).ok()?;
+			lists.push(
+				list.split(',')
+					.filter(|s| !s.is_empty())
+					.map(String::from)
+					.collect(),
+			);
+			r = &r[4 + n..];
+		}
+		Some(Self(lists))
+	}
+}
+
+/// Pick, per slot, our first preference the server also offers — russh's
+/// client-side selection. `None` if a required slot has no overlap (russh
+/// would have failed the handshake then).
+pub(crate) fn negotiate(ours: &Preferred, server: &ServerKexInit) -> Option<NegotiatedAlgorithms> {
+	fn select<S: AsRef<str>>(ours: &[S], theirs: &[String]) -> Option<String> {
+		ours.iter()
+			.map(AsRef::as_ref)
+			.find(|c| theirs.iter().any(|s| s == c))
+			.map(String::from)
+	}
+	let lists = &server.0;
+	let host_keys: Vec<String> = ours.key.iter().map(|a| a.to_string()).collect();
+	let cipher = select(&ours.cipher, &lists[2])?;
+	let aead = matches!(
+		cipher.as_str(),
+		"chacha20-poly1305@openssh.com" | "aes128-gcm@openssh.com" | "aes256-gcm@openssh.com"
+	);
+	let mac = |list: &[String]| {
+		if aead {
+			Some(None)
+		} else {
+			select(&ours.mac, list).map(Some)
+		}
+	};
+	Some(NegotiatedAlgorithms {
+		kex: select(&ours.kex, &lists[0])?,
+		host_key: select(&host_keys, &lists[1])?,
+		cipher,
+		mac_client_to_server: mac(&lists[4])?,
+		mac_server_to_client: mac(&lists[5])?,
+		compression_client_to_server: select(&ours.compression, &lists[6])?,
+		compression_server_to_client: select(&ours.compression, &lists[7])?,
+	})
+}
+
+/// The server's KEXINIT is normally in its first few hundred bytes; stop
+/// looking after this much.
+const TAP_LIMIT: usize = 64 * 1024;
+
+/// A pass-through transport that records the server's KEXINIT as it goes by.
+pub(crate) struct KexInitTap {
+	inner: Box<dyn Transport>,
+	seen: Vec<u8>,
+	found: Arc<Mutex<Option<ServerKexInit>>>,
+	done: bool,
+}
+
+impl KexInitTap {
+	/// Wrap `inner`; the KEXINIT lands in the returned slot once read.
+	pub(crate) fn new(inner: Box<dyn Transport>) -> (Self, Arc<Mutex<Option<ServerKexInit>>>) {
+		let found = Arc::new(Mutex::new(None));
+		let tap = Self {
+			inner,
+			seen: Vec::new(),
+			found: found.clone(),
+			done: false,
+		};
+		(tap, found)
+	}
+}
+
+impl AsyncRead for KexInitTap {
+	fn poll_read(
+		self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+		buf: &mut ReadBuf<'_>,
+	) -> Poll<io::Result<()>> {
+		let this = self.get_mut();
+		let before = buf.filled().len();
+		let poll = Pin::new(&mut this.inner).poll_read(cx, buf);
+		if !this.done && buf.filled().len() > before {
+			this.seen.extend_from_slice(&buf.filled()[before..]);
+			if let Some(kexinit) = ServerKexInit::parse(&this.seen) {
+				*this.found.lock().unwrap_or_else(|p| p.into_inner()) = Some(kexinit);
+				this.done = true;
+			} else if this.seen.len() > TAP_LIMIT {
+				this.done = true;
+			}
+			if this.done {
+				this.seen = Vec::new();
+			}
+		}
+		poll
+	}
+}
+
+impl AsyncWrite for KexInitTap {
+	fn poll_write(
+		self: Pin<&mut Self>,
+		cx: &mut Context<'_>,
+		buf: &[u8],
+	) -> Poll<io::Result<usize>> {
+		Pin::new(&mut self.get_mut().inner).poll_write(cx, buf)
+	}
+
+	fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		Pin::new(&mut self.get_mut().inner).poll_flush(cx)
+	}
+
+	fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+		Pin::new(&mut self.get_mut().inner).poll_shutdown(cx)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn kexinit_stream(lists: [&str; 10]) -> Vec<u8> {
+		let mut payload = vec![20u8];
+		payload.extend_from_slice(&[0xaa; 16]);
+		for list in lists {
+			payload.extend_from_slice(&(list.len() as u32).to_be_bytes());
+			payload.extend_from_slice(list.as_bytes());
+		}
+		payload.extend_from_slice(&[0, 0, 0, 0, 0]);
+		let padding = 4;
+		let len = 1 + payload.len() + padding;
+		let mut out = b"a banner line\r\nSSH-2.0-OpenSSH_5.3\r\n".to_vec();
+		out.extend_from_slice(&(len as u32).to_be_bytes());
+		out.push(padding as u8);
+		out.extend_from_slice(&payload);
+		out.extend_from_slice(&[0; 4]);
+		out
+	}
+
+	const OLD_SERVER: [&str; 10] = [
+		"diffie-hellman-group14-sha1,diffie-hellman-group1-sha1",
+		"ssh-rsa",
+		"aes128-ctr,aes128-cbc",
+		"aes128-ctr,aes128-cbc",
+		"hmac-sha1",
+		"hmac-sha1",
+		"none,zlib@openssh.com",
+		"none",
+		"",
+		"",
+	];
+
+	#[test]
+	fn parses_the_server_kexinit_once_it_has_arrived() {
+		let stream = kexinit_stream(OLD_SERVER);
+		assert_eq!(ServerKexInit::parse(&stream[..stream.len() - 20]), None);
+		let kexinit = ServerKexInit::parse(&stream).unwrap();
+		assert_eq!(kexinit.0[1], ["ssh-rsa"]);
+		assert_eq!(kexinit.0[6], ["none", "zlib@openssh.com"]);
+	}
+
+	#[test]
+	fn defaults_cannot_reach_old_gear_but_preferences_can() {
+		let server = ServerKexInit::parse(&kexinit_stream(OLD_SERVER)).unwrap();
+		assert_eq!(negotiate(&Preferred::default(), &server), None);
+
+		let prefs = AlgorithmPreferences {
+			kex: vec![
+				"curve25519-sha256".into(),
+				"diffie-hellman-group14-sha1".into(),
+			],
+			host_key: vec!["ssh-ed25519".into(), "ssh-rsa".into()],
+			..Default::default()
+		};
+		let preferred = prefs.preferred().unwrap();
+		assert!(preferred
+			.kex
+			.contains(&kex::EXTENSION_OPENSSH_STRICT_KEX_AS_CLIENT));
+		assert_eq!(
+			negotiate(&preferred, &server),
+			Some(NegotiatedAlgorithms {
+				kex: "diffie-hellman-group14-sha1".into(),
+				host_key: "ssh-rsa".into(),
+				cipher: "aes128-ctr".into(),
+				mac_client_to_server: Some("hmac-sha1".into()),
+				mac_server_to_client: Some("hmac-sha1".into()),
+				compression_client_to_server: "none".into(),
+				compression_server_to_client: "none".into(),
+			})
+		);
+	}
+
+	#[test]
+	fn aead_ciphers_report_no_mac() {
+		let mut lists = OLD_SERVER;
+		lists[0] = "curve25519-sha256";
+		lists[1] = "ssh-ed25519";
+		lists[2] = "chacha20-poly1305@openssh.com";
+		let server = ServerKexInit::parse(&kexinit_stream(lists)).unwrap();
+		let negotiated = negotiate(&Preferred::default(), &server).unwrap();
+		assert_eq!(negotiated.cipher, "chacha20-poly1305@openssh.com");
+		assert_eq!(negotiated.mac_client_to_server, None);
+	}
+
+	#[test]
+	fn unknown_names_are_rejected() {
+		let prefs = AlgorithmPreferences {
+			cipher: vec!["aes128-ctr".into(), "rot13".into()],
+			..Default::default()
+		};
+		assert!(matches!(
+			prefs.preferred(),
+			Err(SshError::UnsupportedAlgorithm(name)) if name == "rot13"
+		));
+	}
+}
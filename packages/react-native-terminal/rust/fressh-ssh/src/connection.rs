@@ -1,29 +1,69 @@
 //! Connect + authenticate + open shell channels. Ported from the uniffi-russh
 //! crate, with the uniffi callback traits replaced by plain-Rust seams:
 //!   - host-key verification -> [`HostKeyVerifier`] (async, returns bool)
+//!   - keyboard-interactive   -> [`AuthPrompter`] (async, returns the answers)
 //!   - connection progress    -> a plain `Fn` callback
-//! `fressh-core` implements the verifier via host-key park/resume. (§7)
+//!
+//! `fressh-core` implements the verifier and the prompter via park/resume. (§7)
+//!
+//! A connection may be tunnelled through an ordered chain of [`JumpHost`]s
+//! (OpenSSH's `ProxyJump`): each hop is a full SSH session of its own, and the
+//! next hop — or the final target — is reached over a `direct-tcpip` channel on
+//! the previous one. The target's handshake never knows it isn't on raw TCP.
 
+use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use futures::future::BoxFuture;
-use russh::client::{self, Config, Handle as ClientHandle};
+use russh::client::{self, Config, Handle as ClientHandle, KeyboardInteractiveAuthResponse};
 use russh::keys::{PrivateKeyWithHashAlg, PublicKeyBase64};
-use russh::Disconnect;
+use russh::{Disconnect, MethodKind};
+use tokio::io::{AsyncRead, AsyncWrite, BufReader};
 
-use crate::private_key::normalize_openssh_ed25519_seed_key;
+use crate::agent::{AgentConfirmer, ForwardedAgent, KeyAgent};
+use crate::algorithms::{self, AlgorithmPreferences, KexInitTap, NegotiatedAlgorithms};
+use crate::dial::{self, ConnectTimeouts, IpPreference, PromptClock};
+use crate::exec::Exec;
+use crate::forward::{ForwardedConnection, RemoteForward, RemoteForwardRoutes};
+use crate::private_key::{parse_private_key, validate_user_certificate};
+use crate::proxy::{self, UpstreamProxy};
+use crate::scp::{self, ScpOptions};
+use crate::sftp::Sftp;
 use crate::shell::{
 	Shell, ShellReader, ShellWriter, StartShellOptions, DEFAULT_TERMINAL_MODES,
 	DEFAULT_TERM_COL_WIDTH, DEFAULT_TERM_PIXEL_HEIGHT, DEFAULT_TERM_PIXEL_WIDTH,
 	DEFAULT_TERM_ROW_HEIGHT,
 };
 use crate::utils::{now_ms, SshError};
-use tokio::sync::Mutex as AsyncMutex;
+use tokio::sync::{Mutex as AsyncMutex, RwLock};
 
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum Security {
-	Password { password: String },
-	Key { private_key_content: String },
+	Password {
+		password: String,
+	},
+	Key {
+		private_key_content: String,
+		/// Required if the key is encrypted.
+		passphrase: Option<String>,
+		/// An OpenSSH user certificate (`id_*-cert.pub`) for this key. When set,
+		/// the key is offered as the certificate instead of as a bare key.
+		certificate: Option<String>,
+	},
+	/// `keyboard-interactive` (RFC 4256): the server drives one or more rounds of
+	/// prompts (PAM passwords, OTP codes, …), answered via the [`AuthPrompter`].
+	KeyboardInteractive,
+}
+
+impl Security {
+	fn method(&self) -> MethodKind {
+		match self {
+			Security::Password { .. } => MethodKind::Password,
+			Security::Key { .. } => MethodKind::PublicKey,
+			Security::KeyboardInteractive => MethodKind::KeyboardInteractive,
+		}
+	}
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
@@ -31,12 +71,29 @@ pub struct ConnectionDetails {
 	pub host: String,
 	pub port: u16,
 	pub username: String,
-	pub security: Security,
+	/// Tried in order (e.g. several keys, then a password, then
+	/// keyboard-interactive) until the server lets us in. Credentials for a
+	/// method the server has stopped offering are skipped; after a partial
+	/// success (`AuthenticationMethods publickey,password`) the chain simply
+	/// carries on with the next one, as it does past a key that won't load.
+	pub credentials: Vec<Security>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SshConnectionProgressEvent {
+	// For the one real TCP dial — the first jump host's, or the target's when
+	// connecting directly; the proxy's, if there is one. `addresses` is in the
+	// order they'll be tried.
+	DnsResolved { addresses: Vec<String> },
+	TcpAttempt { address: String },
+	TcpAttemptFailed { address: String, error: String },
+	// Per jump host, in chain order (`hop` indexes `ConnectOptions::jump_hosts`).
+	// Absent when connecting directly.
+	JumpHostTcpConnected { hop: u32 },
+	JumpHostSshHandshake { hop: u32 },
+	JumpHostAuthenticated { hop: u32 },
 	// Before any progress events, assume: TcpConnecting.
+	// Through jump hosts, "TCP" is the `direct-tcpip` tunnel to the target.
 	TcpConnected,
 	SshHandshake,
 	// If the connect call has not resolved, assume: Authenticating.
@@ -66,6 +123,12 @@ pub struct SshConnectionInfo {
 	pub created_at_ms: f64,
 	pub connected_at_ms: f64,
 	pub progress_timings: SshConnectionInfoProgressTimings,
+	/// The target's address, as dialed. `None` through a jump host or proxy,
+	/// where we never learn it.
+	pub remote_ip: Option<String>,
+	/// What the handshake with the target settled on; `None` if the server's
+	/// KEXINIT couldn't be read back (see [`crate::algorithms`]).
+	pub algorithms: Option<NegotiatedAlgorithms>,
 }
 
 /// Async host-key decision. `fressh-core` implements this as park/resume: emit a
@@ -74,13 +137,125 @@ pub trait HostKeyVerifier: Send + Sync {
 	fn verify(&self, info: ServerPublicKeyInfo) -> BoxFuture<'static, bool>;
 }
 
+/// One question in a keyboard-interactive round. `echo` is false for secrets
+/// (the answer should be masked).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthPrompt {
+	pub prompt: String,
+	pub echo: bool,
+}
+
+/// One keyboard-interactive round (`SSH_MSG_USERAUTH_INFO_REQUEST`). `round`
+/// counts from 0; a server may ask any number of times (e.g. password, then OTP).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthPromptRequest {
+	pub round: u32,
+	pub name: String,
+	pub instruction: String,
+	pub prompts: Vec<AuthPrompt>,
+}
+
+/// Async answers to a keyboard-interactive round, one per prompt in order.
+/// `None` cancels the authentication. `fressh-core` implements this as
+/// park/resume, like [`HostKeyVerifier`].
+pub trait AuthPrompter: Send + Sync {
+	fn prompt(&self, request: AuthPromptRequest) -> BoxFuture<'static, Option<Vec<String>>>;
+}
+
 /// Connection-progress sink (TcpConnected / SshHandshake). One-way, rare.
 pub type ProgressCallback = Arc<dyn Fn(SshConnectionProgressEvent) + Send + Sync>;
 
+/// One bastion in a `ProxyJump` chain. A hop is authenticated with its own
+/// credentials and has its host key checked by its own verifier before anything
+/// is tunnelled through it.
+pub struct JumpHost {
+	pub details: ConnectionDetails,
+	pub verifier: Arc<dyn HostKeyVerifier>,
+	/// Required only if the hop uses [`Security::KeyboardInteractive`].
+	pub prompter: Option<Arc<dyn AuthPrompter>>,
+}
+
+/// Keepalive policy: a `keepalive@openssh.com` request every `interval_secs`;
+/// after `max_missed` unanswered in a row the link is dead.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeepaliveConfig {
+	pub interval_secs: u32,
+	pub max_missed: u32,
+}
+
+impl KeepaliveConfig {
+	pub fn interval(&self) -> Duration {
+		Duration::from_secs(self.interval_secs.max(1).into())
+	}
+}
+
+/// Automatic reconnect policy: retry with exponential backoff, starting at
+/// `initial_delay_ms` and doubling up to `max_delay_ms`. `max_attempts` 0 =
+/// keep trying until disconnected.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ReconnectConfig {
+	pub max_attempts: u32,
+	pub initial_delay_ms: u32,
+	pub max_delay_ms: u32,
+}
+
+impl ReconnectConfig {
+	/// How long to wait before `attempt` (1-based).
+	pub fn delay(&self, attempt: u32) -> Duration {
+		let factor = 1u64 << attempt.saturating_sub(1).min(32);
+		let ms = u64::from(self.initial_delay_ms).saturating_mul(factor);
+		Duration::from_millis(ms.min(self.max_delay_ms.into()))
+	}
+}
+
+/// Connection tunables — plain data, kept on the [`Connection`] so the same
+/// settings can be reapplied later. Applies to every hop of a jump chain.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ConnectConfig {
+	/// `None` ⇒ no keepalives (a silently dropped network goes unnoticed).
+	/// Like `reconnect`, only carried here: the embedder runs the pings.
+	pub keepalive: Option<KeepaliveConfig>,
+	/// `None` ⇒ a lost connection is torn down. This crate only carries it;
+	/// the embedder (`fressh-core`) does the reconnecting.
+	pub reconnect: Option<ReconnectConfig>,
+	/// What to offer in the key exchange; empty lists keep russh's defaults.
+	pub algorithms: AlgorithmPreferences,
+	/// All `None` by default: a dead host can hang the connect for minutes.
+	pub timeouts: ConnectTimeouts,
+	pub ip_preference: IpPreference,
+	/// Dial the first TCP hop through this instead of directly.
+	pub proxy: Option<UpstreamProxy>,
+	/// Ask for agent forwarding on every shell, and serve the channels the
+	/// target opens back from [`ConnectOptions::agent`]. Never on jump hosts.
+	pub forward_agent: bool,
+}
+
+impl ConnectConfig {
+	fn russh_config(&self) -> Result<Config, SshError> {
+		// No russh `keepalive_interval`: the embedder's monitor pings through
+		// [`Connection::ping`], and a second loop would double the traffic.
+		Ok(Config {
+			preferred: self.algorithms.preferred()?,
+			..Config::default()
+		})
+	}
+}
+
 pub struct ConnectOptions {
 	pub details: ConnectionDetails,
 	pub verifier: Arc<dyn HostKeyVerifier>,
+	/// Required only if `details` uses [`Security::KeyboardInteractive`].
+	pub prompter: Option<Arc<dyn AuthPrompter>>,
 	pub on_progress: Option<ProgressCallback>,
+	/// Bastions to tunnel through, in order: the first is dialed over TCP, each
+	/// later hop (and finally `details`) through a `direct-tcpip` channel on the
+	/// previous one. Empty ⇒ connect directly.
+	pub jump_hosts: Vec<JumpHost>,
+	pub config: ConnectConfig,
+	/// The keys forwarded when `config.forward_agent` is set.
+	pub agent: Option<KeyAgent>,
+	/// Asked before a `confirm` agent key signs; without it they never do.
+	pub agent_confirmer: Option<Arc<dyn AgentConfirmer>>,
 }
 
 fn server_public_key_to_info(
@@ -99,12 +274,19 @@ fn server_public_key_to_info(
 	}
 }
 
-/// russh `client::Handler` that defers the host-key decision to a [`HostKeyVerifier`].
+/// russh `client::Handler` that defers the host-key decision to a [`HostKeyVerifier`],
+/// routes server-opened `forwarded-tcpip` channels to their `-R` forward and
+/// serves `auth-agent@openssh.com` channels from the forwarded agent.
 pub(crate) struct Handler {
 	verifier: Arc<dyn HostKeyVerifier>,
 	host: String,
 	port: u16,
 	remote_ip: Option<String>,
+	remote_forwards: RemoteForwardRoutes,
+	/// `None` unless agent forwarding is on: such channels are then dropped.
+	agent: Option<ForwardedAgent>,
+	/// Stopped while the verifier decides, which may mean asking the user.
+	prompt_clock: PromptClock,
 }
 
 impl client::Handler for Handler {
@@ -121,7 +303,46 @@ impl client::Handler for Handler {
 			self.remote_ip.clone(),
 			server_public_key,
 		);
-		async move { Ok(verifier.verify(info).await) }
+		let clock = self.prompt_clock.clone();
+		async move { Ok(clock.exclude(verifier.verify(info)).await) }
+	}
+
+	fn server_channel_open_forwarded_tcpip(
+		&mut self,
+		channel: russh::Channel<client::Msg>,
+		connected_address: &str,
+		connected_port: u32,
+		originator_address: &str,
+		originator_port: u32,
+		_session: &mut client::Session,
+	) -> impl std::future::Future<Output = Result<(), Self::Error>> + Send {
+		// Unrouted (nobody asked for it, or it was just cancelled) ⇒ the stream is
+		// dropped, which closes the channel.
+		self.remote_forwards.dispatch(ForwardedConnection {
+			stream: channel.into_stream(),
+			connected_address: connected_address.to_string(),
+			connected_port: connected_port as u16,
+			originator_address: originator_address.to_string(),
+			originator_port: originator_port as u16,
+		});
+		async { Ok(()) }
+	}
+
+	fn server_channel_open_agent_forward(
+		&mut self,
+		channel: russh::Channel<client::Msg>,
+		_session: &mut client::Session,
+	) -> impl std::future::Future<Output = Result<(), Self::Error>> + Send {
+		if let Some(ForwardedAgent { agent, confirmer }) = self.agent.clone() {
+			let (host, port) = (self.host.clone(), self.port);
+			tokio::spawn(async move {
+				// A broken agent channel only fails that one `ssh-add -l`/signature.
+				let _ = agent
+					.serve(channel.into_stream(), confirmer, host, port)
+					.await;
+			});
+		}
+		async { Ok(()) }
 	}
 }
 
@@ -135,6 +356,10 @@ pub struct CommandOutput {
 	pub exit_code: Option<i32>,
 }
 
+/// A `direct-tcpip` channel as a plain byte stream (`AsyncRead + AsyncWrite`).
+/// Shutting down the write side sends the channel EOF.
+pub type TunnelStream = russh::ChannelStream<client::Msg>;
+
 /// Soft cap on captured stdout/stderr (each) for a one-off command, so a runaway
 /// command (`cat huge`, `yes`) can't OOM the app. Excess is dropped; the channel is
 /// still drained so it closes cleanly.
@@ -148,21 +373,57 @@ fn append_capped(buf: &mut Vec<u8>, data: &[u8]) {
 	buf.extend_from_slice(&data[..data.len().min(room)]);
 }
 
+/// Wait for the reply to a channel request sent with `want_reply`.
+async fn request_accepted(ch: &mut russh::Channel<client::Msg>) -> Result<bool, SshError> {
+	loop {
+		match ch.wait().await {
+			Some(russh::ChannelMsg::Success) => return Ok(true),
+			Some(russh::ChannelMsg::Failure) => return Ok(false),
+			// Window adjustments and the like before the reply.
+			Some(_) => {}
+			None => return Err(SshError::Russh("channel closed before replying".into())),
+		}
+	}
+}
+
 /// A live, authenticated SSH connection. Lifetime is owned by `fressh-core`'s
 /// registry, not by a JS handle. (§7)
 pub struct Connection {
 	pub info: SshConnectionInfo,
-	client_handle: AsyncMutex<ClientHandle<Handler>>,
+	pub config: ConnectConfig,
+	/// Read-locked for everything but `tcpip_forward` (russh wants `&mut`
+	/// there), so a slow keepalive round trip doesn't stall opening channels.
+	client_handle: RwLock<ClientHandle<Handler>>,
+	/// Shared with this connection's [`Handler`]: where `forwarded-tcpip`
+	/// channels for each `-R` forward are delivered.
+	remote_forwards: RemoteForwardRoutes,
+	/// Sessions to the jump hosts this connection is tunnelled through, in chain
+	/// order. Held only to keep the tunnels alive; torn down after the target.
+	jump_handles: AsyncMutex<Vec<ClientHandle<Handler>>>,
 }
 
 impl Connection {
 	/// Open a PTY + shell channel.
 	pub async fn open_shell(&self, opts: StartShellOptions) -> Result<Shell, SshError> {
 		let started_at_ms = now_ms();
-		let handle = self.client_handle.lock().await;
-		let ch = handle.channel_open_session().await?;
+		let mut ch = {
+			let handle = self.client_handle.read().await;
+			handle.channel_open_session().await?
+		};
 		let channel_id: u32 = ch.id().into();
 
+		// All the `env` requests go out before waiting on any reply; the
+		// replies come back in order.
+		for (name, value) in &opts.env {
+			ch.set_env(true, name.as_str(), value.as_str()).await?;
+		}
+		let mut rejected_env = Vec::new();
+		for (name, _) in &opts.env {
+			if !request_accepted(&mut ch).await? {
+				rejected_env.push(name.clone());
+			}
+		}
+
 		let mut modes: Vec<(russh::Pty, u32)> = DEFAULT_TERMINAL_MODES.to_vec();
 		if let Some(overrides) = &opts.terminal_mode {
 			for m in overrides {
@@ -204,23 +465,49 @@ impl Connection {
 		)
 		.await?;
 
-		if opts.shell_integration {
+		if self.config.forward_agent {
+			// No reply wanted: a server that refuses just never opens the channel.
+			ch.agent_forward(false).await?;
+		}
+
+		let env_report = if opts.shell_integration {
 			// Launch the interactive shell with OSC 633 shell integration injected
 			// (cwd / command lifecycle / exit code / command text), nothing touched
 			// permanently on the host. The bootstrap self-falls-back to a plain login
 			// shell for shells it can't inject. See crate::shell_integration.
-			let nonce = crate::shell_integration::generate_nonce();
-			let exec_command = crate::shell_integration::build_exec_command(&nonce);
+			let report = crate::shell_integration::EnvReport::new(
+				crate::shell_integration::generate_nonce(),
+				&opts.report_env,
+			);
+			let fallback_env: Vec<(String, String)> = opts
+				.env
+				.iter()
+				.filter(|(name, _)| rejected_env.contains(name))
+				.cloned()
+				.collect();
+			let exec_command = crate::shell_integration::build_exec_command(
+				&report.nonce,
+				&report.names,
+				&fallback_env,
+				opts.command.as_deref(),
+			);
 			ch.exec(true, exec_command).await?;
+			Some(report)
+		} else if let Some(command) = &opts.command {
+			ch.exec(true, command.as_bytes().to_vec()).await?;
+			None
 		} else {
 			ch.request_shell(true).await?;
-		}
+			None
+		};
 
 		let (reader, writer) = ch.split();
 		Ok(Shell {
 			channel_id,
 			term: opts.term,
 			created_at_ms: started_at_ms,
+			rejected_env,
+			env_report,
 			reader: ShellReader::new(reader),
 			writer: ShellWriter::new(writer),
 		})
@@ -237,7 +524,7 @@ impl Connection {
 		// returned channel is owned, so the read loop below doesn't block the shell's
 		// writes (which use their own channel write-half, not this lock).
 		let mut ch = {
-			let handle = self.client_handle.lock().await;
+			let handle = self.client_handle.read().await;
 			handle.channel_open_session().await?
 		};
 		ch.exec(true, command.as_bytes().to_vec()).await?;
@@ -267,70 +554,593 @@ impl Connection {
 		})
 	}
 
-	/// Disconnect the SSH session (closes all channels server-side).
+	/// Start `command` on a sibling session channel (no PTY) and hand back its
+	/// halves, for output that should stream rather than be collected — `tail
+	/// -f`, a long build. Like [`Self::exec_command`], the handle lock is held
+	/// only for the open; a server that refuses the command just closes the
+	/// channel without an exit status.
+	pub async fn open_exec(&self, command: &str) -> Result<Exec, SshError> {
+		let ch = {
+			let handle = self.client_handle.read().await;
+			handle.channel_open_session().await?
+		};
+		ch.exec(true, command.as_bytes().to_vec()).await?;
+		Ok(Exec::new(ch))
+	}
+
+	/// Copy `local` (a file, or with `options.recursive` a directory) to
+	/// `remote` by running `scp -t` there, for servers without SFTP. Returns
+	/// the file bytes sent; `progress` gets the running total.
+	pub async fn scp_upload(
+		&self,
+		local: &Path,
+		remote: &str,
+		options: ScpOptions,
+		progress: &(dyn Fn(u64) + Sync),
+	) -> Result<u64, SshError> {
+		let mut stream = self.open_scp(&options.command('t', remote)).await?;
+		scp::send(&mut stream, local, options, progress).await
+	}
+
+	/// Copy `remote` to `local` by running `scp -f` there; see
+	/// [`Self::scp_upload`].
+	pub async fn scp_download(
+		&self,
+		remote: &str,
+		local: &Path,
+		options: ScpOptions,
+		progress: &(dyn Fn(u64) + Sync),
+	) -> Result<u64, SshError> {
+		let mut stream = self.open_scp(&options.command('f', remote)).await?;
+		scp::receive(&mut stream, local, options, progress).await
+	}
+
+	async fn open_scp(
+		&self,
+		command: &str,
+	) -> Result<BufReader<russh::ChannelStream<client::Msg>>, SshError> {
+		let ch = {
+			let handle = self.client_handle.read().await;
+			handle.channel_open_session().await?
+		};
+		ch.exec(true, command.as_bytes().to_vec()).await?;
+		Ok(BufReader::new(ch.into_stream()))
+	}
+
+	/// Start an SFTP session on a sibling session channel (the `sftp`
+	/// subsystem). Fails with [`SshError::Sftp`] if the server has none.
+	pub async fn open_sftp(&self) -> Result<Sftp, SshError> {
+		let mut ch = {
+			let handle = self.client_handle.read().await;
+			handle.channel_open_session().await?
+		};
+		ch.request_subsystem(true, "sftp").await?;
+		loop {
+			match ch.wait().await {
+				Some(russh::ChannelMsg::Success) => break,
+				Some(russh::ChannelMsg::Failure) | None => {
+					return Err(SshError::Sftp("no sftp subsystem on the server".into()))
+				}
+				// Window adjustments and the like before the reply.
+				Some(_) => {}
+			}
+		}
+		Sftp::start(ch.into_stream()).await
+	}
+
+	/// Open a `direct-tcpip` channel asking the server to connect to `host:port`
+	/// (the building block of `-L`/`-D` forwarding). `originator` is the local
+	/// peer that triggered it — informational only; servers just log it. Like
+	/// [`Self::exec_command`], the handle lock is held only for the open.
+	pub async fn open_direct_tcpip(
+		&self,
+		host: &str,
+		port: u16,
+		originator: std::net::SocketAddr,
+	) -> Result<TunnelStream, SshError> {
+		let ch = {
+			let handle = self.client_handle.read().await;
+			handle
+				.channel_open_direct_tcpip(
+					host,
+					port as u32,
+					originator.ip().to_string(),
+					originator.port() as u32,
+				)
+				.await?
+		};
+		Ok(ch.into_stream())
+	}
+
+	/// Ask the server to listen on `bind_address:bind_port` (`tcpip-forward`, the
+	/// `-R` request) and deliver each connection it accepts there as a
+	/// [`ForwardedConnection`] on the returned queue. Port 0 lets the server pick;
+	/// the real port is in [`RemoteForward::bound_port`].
+	pub async fn request_remote_forward(
+		&self,
+		bind_address: &str,
+		bind_port: u16,
+	) -> Result<RemoteForward, SshError> {
+		let (tx, incoming) = tokio::sync::mpsc::unbounded_channel();
+		// The server may open channels on the new listener before its reply
+		// reaches us, so the route must exist before we ask.
+		if bind_port != 0 {
+			// A second forward on the same bind would be refused anyway.
+			if !self.remote_forwards.insert(bind_address, bind_port, tx) {
+				return Err(russh::Error::RequestDenied.into());
+			}
+			if let Err(e) = self.tcpip_forward(bind_address, bind_port).await {
+				self.remote_forwards.remove(bind_address, bind_port);
+				return Err(e);
+			}
+			return Ok(RemoteForward {
+				bound_port: bind_port,
+				incoming,
+			});
+		}
+		// Port 0: we can't route what we can't name yet, so hold unrouted
+		// channels until the reply says which port the server picked.
+		let awaiting = self.remote_forwards.await_port();
+		let bound_port = self.tcpip_forward(bind_address, 0).await? as u16;
+		if !awaiting.bound(bind_address, bound_port, tx) {
+			return Err(russh::Error::RequestDenied.into());
+		}
+		Ok(RemoteForward {
+			bound_port,
+			incoming,
+		})
+	}
+
+	/// Send `tcpip-forward`; russh returns the bound port (meaningful for 0).
+	async fn tcpip_forward(&self, bind_address: &str, bind_port: u16) -> Result<u32, SshError> {
+		let mut handle = self.client_handle.write().await;
+		Ok(handle.tcpip_forward(bind_address, bind_port as u32).await?)
+	}
+
+	/// Stop a `-R` forward (`cancel-tcpip-forward`). Its queue closes whether or
+	/// not the server acknowledges — we stop routing to it either way.
+	pub async fn cancel_remote_forward(
+		&self,
+		bind_address: &str,
+		bound_port: u16,
+	) -> Result<(), SshError> {
+		self.remote_forwards.remove(bind_address, bound_port);
+		let handle = self.client_handle.read().await;
+		handle
+			.cancel_tcpip_forward(bind_address, bound_port as u32)
+			.await?;
+		Ok(())
+	}
+
+	/// Round-trip a keepalive (`keepalive@openssh.com`, want-reply) and time it.
+	/// Only shares the session lock, so it blocks nothing but a `-R` bind;
+	/// callers should still bound it with a timeout. `Disconnected` if the
+	/// session died meanwhile.
+	pub async fn ping(&self) -> Result<Duration, SshError> {
+		let handle = self.client_handle.read().await;
+		if handle.is_closed() {
+			return Err(SshError::Disconnected);
+		}
+		let started = Instant::now();
+		handle.send_ping().await?;
+		// A session that dies mid-ping drops the reply channel, which also
+		// resolves the ping: tell the two apart.
+		if handle.is_closed() {
+			return Err(SshError::Disconnected);
+		}
+		Ok(started.elapsed())
+	}
+
+	/// Disconnect the SSH session (closes all channels server-side), then the
+	/// jump hosts it was tunnelled through, innermost first.
 	pub async fn disconnect(&self) -> Result<(), SshError> {
-		let handle = self.client_handle.lock().await;
+		let handle = self.client_handle.read().await;
 		handle
 			.disconnect(Disconnect::ByApplication, "bye", "")
 			.await?;
+		for jump in self.jump_handles.lock().await.iter().rev() {
+			let _ = jump.disconnect(Disconnect::ByApplication, "bye", "").await;
+		}
 		Ok(())
 	}
 }
 
-/// Establish a TCP connection, perform the SSH handshake (host key verified via
-/// `opts.verifier`), and authenticate.
-pub async fn connect(opts: ConnectOptions) -> Result<Connection, SshError> {
-	let started_at_ms = now_ms();
-	let details = opts.details;
+/// Any byte stream the SSH transport can run over: a TCP socket, or a
+/// `direct-tcpip` channel through a jump host.
+pub(crate) trait Transport: AsyncRead + AsyncWrite + Unpin + Send {}
+impl<T: AsyncRead + AsyncWrite + Unpin + Send> Transport for T {}
 
-	let addr = format!("{}:{}", details.host, details.port);
-	let socket = tokio::net::TcpStream::connect(&addr).await?;
-	let local_port = socket.local_addr()?.port();
-	let remote_ip = socket.peer_addr().ok().map(|a| a.ip().to_string());
+/// A freshly dialed transport plus what we know about its far end.
+struct Dialed {
+	stream: Box<dyn Transport>,
+	/// Our local TCP port; `None` for a tunnel (there is no local socket).
+	local_port: Option<u16>,
+	remote_ip: Option<String>,
+}
 
-	let tcp_established_at_ms = now_ms();
-	if let Some(cb) = opts.on_progress.as_ref() {
-		cb(SshConnectionProgressEvent::TcpConnected);
+/// Reach `host:port`: over TCP (Happy Eyeballs across its addresses) when
+/// `via` is `None`, else through a `direct-tcpip` channel on the jump host `via`.
+async fn dial(
+	via: Option<&ClientHandle<Handler>>,
+	host: &str,
+	port: u16,
+	config: &ConnectConfig,
+	progress: &(dyn Fn(SshConnectionProgressEvent) + Sync),
+) -> Result<Dialed, SshError> {
+	dial::deadline(
+		config.timeouts.connect_ms,
+		&format!("connect to {host}:{port}"),
+		dial_unbounded(via, host, port, config, progress),
+	)
+	.await
+}
+
+async fn dial_unbounded(
+	via: Option<&ClientHandle<Handler>>,
+	host: &str,
+	port: u16,
+	config: &ConnectConfig,
+	progress: &(dyn Fn(SshConnectionProgressEvent) + Sync),
+) -> Result<Dialed, SshError> {
+	match (via, &config.proxy) {
+		(None, Some(upstream)) => {
+			let mut socket = dial::connect_tcp(
+				&upstream.host,
+				upstream.port,
+				config.ip_preference,
+				progress,
+			)
+			.await?;
+			proxy::connect(upstream, &mut socket, host, port).await?;
+			let local_port = socket.local_addr()?.port();
+			// The peer is the proxy; the target's address is never learned.
+			Ok(Dialed {
+				stream: Box::new(socket),
+				local_port: Some(local_port),
+				remote_ip: None,
+			})
+		}
+		(None, None) => {
+			let socket = dial::connect_tcp(host, port, config.ip_preference, progress).await?;
+			let local_port = socket.local_addr()?.port();
+			let remote_ip = socket.peer_addr().ok().map(|a| a.ip().to_string());
+			Ok(Dialed {
+				stream: Box::new(socket),
+				local_port: Some(local_port),
+				remote_ip,
+			})
+		}
+		(Some(jump), _) => {
+			// Originator is informational only; OpenSSH's `-J` sends loopback too.
+			let ch = jump
+				.channel_open_direct_tcpip(host, port as u32, "127.0.0.1", 0)
+				.await?;
+			Ok(Dialed {
+				stream: Box::new(ch.into_stream()),
+				local_port: None,
+				remote_ip: None,
+			})
+		}
 	}
+}
 
-	let cfg = Arc::new(Config::default());
-	let mut handle = client::connect_stream(
-		cfg,
-		socket,
+/// Run the SSH handshake over `stream`; the host key is checked by `verifier`.
+/// Also returns the algorithms it negotiated, when they could be worked out.
+async fn handshake(
+	stream: Box<dyn Transport>,
+	details: &ConnectionDetails,
+	config: &ConnectConfig,
+	verifier: Arc<dyn HostKeyVerifier>,
+	remote_ip: Option<String>,
+	remote_forwards: RemoteForwardRoutes,
+	agent: Option<ForwardedAgent>,
+) -> Result<(ClientHandle<Handler>, Option<NegotiatedAlgorithms>), SshError> {
+	let cfg = Arc::new(config.russh_config()?);
+	let (stream, server_kexinit) = KexInitTap::new(stream);
+	let prompt_clock = PromptClock::default();
+	let connecting = client::connect_stream(
+		cfg.clone(),
+		stream,
 		Handler {
-			verifier: opts.verifier.clone(),
+			verifier,
 			host: details.host.clone(),
 			port: details.port,
 			remote_ip,
+			remote_forwards,
+			agent,
+			prompt_clock: prompt_clock.clone(),
 		},
+	);
+	let handle = dial::deadline_excluding(
+		config.timeouts.handshake_ms,
+		&format!("SSH handshake with {}:{}", details.host, details.port),
+		&prompt_clock,
+		connecting,
 	)
 	.await?;
+	let algorithms = server_kexinit
+		.lock()
+		.unwrap_or_else(|p| p.into_inner())
+		.as_ref()
+		.and_then(|kexinit| algorithms::negotiate(&cfg.preferred, kexinit));
+	Ok((handle, algorithms))
+}
 
-	let ssh_handshake_at_ms = now_ms();
-	if let Some(cb) = opts.on_progress.as_ref() {
-		cb(SshConnectionProgressEvent::SshHandshake);
+/// Authenticate `handle` by walking `details.credentials`. A `none` probe first
+/// learns which methods the server offers (and lets through hosts that need no
+/// auth at all); after that, each failure's `remaining_methods` is the new word
+/// on what is worth trying. A credential we can't even use (an unreadable key,
+/// a wrong passphrase, a certificate that doesn't fit) is passed over; if
+/// nothing gets us in, the first such error is returned rather than
+/// `AuthRejected`. `prompter` answers keyboard-interactive rounds, with `clock`
+/// stopped.
+async fn authenticate(
+	handle: &mut ClientHandle<Handler>,
+	details: &ConnectionDetails,
+	prompter: Option<&dyn AuthPrompter>,
+	clock: &PromptClock,
+) -> Result<(), SshError> {
+	let username = details.username.as_str();
+	let mut remaining = match handle.authenticate_none(username).await? {
+		client::AuthResult::Success => return Ok(()),
+		client::AuthResult::Failure {
+			remaining_methods, ..
+		} => remaining_methods,
+	};
+	let mut partial_success = false;
+	let mut unusable = None;
+	for credential in &details.credentials {
+		// An empty list is a server not telling us; try everything then.
+		if !remaining.is_empty() && !remaining.contains(&credential.method()) {
+			continue;
+		}
+		match try_credential(handle, username, credential, prompter, clock).await {
+			Ok(client::AuthResult::Success) => return Ok(()),
+			Ok(client::AuthResult::Failure {
+				remaining_methods,
+				partial_success: partial,
+			}) => {
+				partial_success |= partial;
+				remaining = remaining_methods;
+			}
+			Err(e) if is_unusable_credential(&e) => {
+				unusable.get_or_insert(e);
+			}
+			Err(e) => return Err(e),
+		}
 	}
+	Err(unusable.unwrap_or_else(|| SshError::AuthRejected {
+		remaining_methods: remaining.iter().map(String::from).collect(),
+		partial_success,
+	}))
+}
 
-	let auth_result = match &details.security {
-		Security::Password { password } => {
-			handle
-				.authenticate_password(details.username.clone(), password.clone())
-				.await?
-		}
+/// Errors from loading a key credential, before anything reaches the server.
+fn is_unusable_credential(e: &SshError) -> bool {
+	matches!(
+		e,
+		SshError::RusshKeys(_)
+			| SshError::PassphraseRequired
+			| SshError::WrongPassphrase
+			| SshError::Certificate(_)
+	)
+}
+
+/// One attempt with one credential.
+async fn try_credential(
+	handle: &mut ClientHandle<Handler>,
+	username: &str,
+	credential: &Security,
+	prompter: Option<&dyn AuthPrompter>,
+	clock: &PromptClock,
+) -> Result<client::AuthResult, SshError> {
+	match credential {
+		Security::Password { password } => Ok(handle
+			.authenticate_password(username, password.clone())
+			.await?),
 		Security::Key {
 			private_key_content,
+			passphrase,
+			certificate,
 		} => {
-			let (_canonical, parsed) = normalize_openssh_ed25519_seed_key(private_key_content)?;
+			let (_canonical, parsed) =
+				parse_private_key(private_key_content, passphrase.as_deref())?;
+			if let Some(certificate) = certificate {
+				let now = (now_ms() / 1000.0) as u64;
+				let cert = validate_user_certificate(certificate, &parsed, username, now)?;
+				return Ok(handle
+					.authenticate_openssh_cert(username, Arc::new(parsed), cert)
+					.await?);
+			}
 			let pk_with_hash = PrivateKeyWithHashAlg::new(Arc::new(parsed), None);
-			handle
-				.authenticate_publickey(details.username.clone(), pk_with_hash)
-				.await?
+			Ok(handle
+				.authenticate_publickey(username, pk_with_hash)
+				.await?)
+		}
+		Security::KeyboardInteractive => {
+			let prompter = prompter.ok_or_else(|| {
+				SshError::Auth("keyboard-interactive needs a prompter".to_string())
+			})?;
+			authenticate_keyboard_interactive(handle, username, prompter, clock).await
+		}
+	}
+}
+
+/// Drive keyboard-interactive rounds until the server accepts or rejects us.
+/// Cancelling a round (the prompter answers `None`) aborts the whole chain.
+/// Rounds with no prompts (some PAM stacks send a bare banner) are answered
+/// without bothering the prompter.
+async fn authenticate_keyboard_interactive(
+	handle: &mut ClientHandle<Handler>,
+	username: &str,
+	prompter: &dyn AuthPrompter,
+	clock: &PromptClock,
+) -> Result<client::AuthResult, SshError> {
+	let mut response = handle
+		.authenticate_keyboard_interactive_start(username, None::<String>)
+		.await?;
+	let mut round = 0u32;
+	loop {
+		match response {
+			KeyboardInteractiveAuthResponse::Success => return Ok(client::AuthResult::Success),
+			KeyboardInteractiveAuthResponse::Failure {
+				remaining_methods,
+				partial_success,
+			} => {
+				return Ok(client::AuthResult::Failure {
+					remaining_methods,
+					partial_success,
+				});
+			}
+			KeyboardInteractiveAuthResponse::InfoRequest {
+				name,
+				instructions,
+				prompts,
+			} => {
+				let answers = if prompts.is_empty() {
+					Vec::new()
+				} else {
+					let expected = prompts.len();
+					let answers = clock
+						.exclude(
+							prompter.prompt(AuthPromptRequest {
+								round,
+								name,
+								instruction: instructions,
+								prompts: prompts
+									.into_iter()
+									.map(|p| AuthPrompt {
+										prompt: p.prompt,
+										echo: p.echo,
+									})
+									.collect(),
+							}),
+						)
+						.await
+						.ok_or_else(|| SshError::Auth("cancelled".to_string()))?;
+					if answers.len() != expected {
+						return Err(SshError::Auth(format!(
+							"expected {expected} answers, got {}",
+							answers.len()
+						)));
+					}
+					round += 1;
+					answers
+				};
+				response = handle
+					.authenticate_keyboard_interactive_respond(answers)
+					.await?;
+			}
+		}
+	}
+}
+
+/// Establish a TCP connection (directly, or tunnelled through
+/// `opts.jump_hosts`), perform the SSH handshake (host key verified via
+/// `opts.verifier`), and authenticate.
+pub async fn connect(opts: ConnectOptions) -> Result<Connection, SshError> {
+	let started_at_ms = now_ms();
+	let ConnectOptions {
+		details,
+		verifier,
+		prompter,
+		on_progress,
+		jump_hosts,
+		config,
+		agent,
+		agent_confirmer,
+	} = opts;
+	let progress = |event| {
+		if let Some(cb) = on_progress.as_ref() {
+			cb(event);
 		}
 	};
-	if !matches!(auth_result, client::AuthResult::Success) {
-		return Err(auth_result.into());
+
+	// Walk the chain: each hop is dialed through the one before it. The local
+	// port we report is the first (only) real TCP socket's.
+	let mut jump_handles: Vec<ClientHandle<Handler>> = Vec::with_capacity(jump_hosts.len());
+	let mut local_port = None;
+	for (hop, jump) in (0u32..).zip(&jump_hosts) {
+		let dialed = dial(
+			jump_handles.last(),
+			&jump.details.host,
+			jump.details.port,
+			&config,
+			&progress,
+		)
+		.await?;
+		local_port = local_port.or(dialed.local_port);
+		progress(SshConnectionProgressEvent::JumpHostTcpConnected { hop });
+
+		// Nothing is ever `-R`-forwarded on a jump host, nor the agent: give it
+		// an empty table and no agent.
+		let (mut handle, _) = handshake(
+			dialed.stream,
+			&jump.details,
+			&config,
+			jump.verifier.clone(),
+			dialed.remote_ip,
+			RemoteForwardRoutes::default(),
+			None,
+		)
+		.await?;
+		progress(SshConnectionProgressEvent::JumpHostSshHandshake { hop });
+
+		let clock = PromptClock::default();
+		dial::deadline_excluding(
+			config.timeouts.auth_ms,
+			"authentication",
+			&clock,
+			authenticate(&mut handle, &jump.details, jump.prompter.as_deref(), &clock),
+		)
+		.await?;
+		progress(SshConnectionProgressEvent::JumpHostAuthenticated { hop });
+		jump_handles.push(handle);
 	}
 
+	let dialed = dial(
+		jump_handles.last(),
+		&details.host,
+		details.port,
+		&config,
+		&progress,
+	)
+	.await?;
+	let local_port = local_port.or(dialed.local_port).unwrap_or_default();
+
+	let tcp_established_at_ms = now_ms();
+	progress(SshConnectionProgressEvent::TcpConnected);
+
+	let remote_ip = dialed.remote_ip;
+	let remote_forwards = RemoteForwardRoutes::default();
+	let (mut handle, algorithms) = handshake(
+		dialed.stream,
+		&details,
+		&config,
+		verifier,
+		remote_ip.clone(),
+		remote_forwards.clone(),
+		agent
+			.filter(|_| config.forward_agent)
+			.map(|agent| ForwardedAgent {
+				agent,
+				confirmer: agent_confirmer,
+			}),
+	)
+	.await?;
+
+	let ssh_handshake_at_ms = now_ms();
+	progress(SshConnectionProgressEvent::SshHandshake);
+
+	let clock = PromptClock::default();
+	dial::deadline_excluding(
+		config.timeouts.auth_ms,
+		"authentication",
+		&clock,
+		authenticate(&mut handle, &details, prompter.as_deref(), &clock),
+	)
+	.await?;
+
 	Ok(Connection {
 		info: SshConnectionInfo {
 			details,
@@ -341,7 +1151,513 @@ pub async fn connect(opts: ConnectOptions) -> Result<Connection, SshError> {
 				tcp_established_at_ms,
 				ssh_handshake_at_ms,
 			},
+			remote_ip,
+			algorithms,
 		},
-		client_handle: AsyncMutex::new(handle),
+		config,
+		client_handle: RwLock::new(handle),
+		remote_forwards,
+		jump_handles: AsyncMutex::new(jump_handles),
 	})
 }
+
+#[cfg(test)]
+mod tests {
+	use std::borrow::Cow;
+
+	use russh::keys::{Algorithm, PrivateKey};
+	use russh::server::{self, Auth, Response};
+	use std::sync::atomic::{AtomicBool, Ordering};
+
+	use tokio::io::{AsyncReadExt, AsyncWriteExt};
+	use tokio::net::{TcpListener, TcpStream};
+
+	use super::*;
+
+	/// Lets in `password` "hunter2", or keyboard-interactive answered "123456"
+	/// (then "Token: " answered "654321", with `second_round`).
+	/// Grants `-R` forwards (never on port 22), and like a busy server opens a
+	/// connection on each new listener before its reply goes out. Tunnels
+	/// `direct-tcpip` (as a jump host), logging each `host:port`.
+	#[derive(Clone, Default)]
+	struct TestServer {
+		tunnelled: Arc<std::sync::Mutex<Vec<String>>>,
+		second_round: bool,
+		code_ok: bool,
+	}
+
+	impl server::Handler for TestServer {
+		type Error = russh::Error;
+
+		async fn auth_password(
+			&mut self,
+			_user: &str,
+			password: &str,
+		) -> Result<Auth, Self::Error> {
+			Ok(if password == "hunter2" {
+				Auth::Accept
+			} else {
+				Auth::reject()
+			})
+		}
+
+		async fn auth_keyboard_interactive<'a>(
+			&'a mut self,
+			_user: &str,
+			_submethods: &str,
+			response: Option<Response<'a>>,
+		) -> Result<Auth, Self::Error> {
+			Ok(match response {
+				None => Auth::Partial {
+					name: Cow::Borrowed(""),
+					instructions: Cow::Borrowed(""),
+					prompts: Cow::Owned(vec![(Cow::Borrowed("Code: "), false)]),
+				},
+				Some(mut answers) => match answers.next() {
+					Some(code) if !self.code_ok && code.as_ref() == b"123456" => {
+						self.code_ok = true;
+						if self.second_round {
+							Auth::Partial {
+								name: Cow::Borrowed(""),
+								instructions: Cow::Borrowed(""),
+								prompts: Cow::Owned(vec![(Cow::Borrowed("Token: "), true)]),
+							}
+						} else {
+							Auth::Accept
+						}
+					}
+					Some(token) if self.code_ok && token.as_ref() == b"654321" => Auth::Accept,
+					_ => Auth::reject(),
+				},
+			})
+		}
+
+		async fn channel_open_direct_tcpip(
+			&mut self,
+			channel: russh::Channel<server::Msg>,
+			host_to_connect: &str,
+			port_to_connect: u32,
+			_originator_address: &str,
+			_originator_port: u32,
+			_session: &mut server::Session,
+		) -> Result<bool, Self::Error> {
+			let target = format!("{host_to_connect}:{port_to_connect}");
+			let mut socket = TcpStream::connect(&target).await?;
+			self.tunnelled.lock().unwrap().push(target);
+			tokio::spawn(async move {
+				let mut stream = channel.into_stream();
+				let _ = tokio::io::copy_bidirectional(&mut stream, &mut socket).await;
+			});
+			Ok(true)
+		}
+
+		async fn tcpip_forward(
+			&mut self,
+			address: &str,
+			port: &mut u32,
+			session: &mut server::Session,
+		) -> Result<bool, Self::Error> {
+			if *port == 22 {
+				return Ok(false);
+			}
+			if *port == 0 {
+				*port = 4712;
+			}
+			// Some servers echo a normalized bind address back.
+			let address = if address == "localhost" {
+				"127.0.0.1"
+			} else {
+				address
+			};
+			session.channel_open_forwarded_tcpip(address, *port, "10.0.0.9", 50000)?;
+			Ok(true)
+		}
+
+		async fn cancel_tcpip_forward(
+			&mut self,
+			address: &str,
+			port: u32,
+			session: &mut server::Session,
+		) -> Result<bool, Self::Error> {
+			// A connection that raced the cancel.
+			session.channel_open_forwarded_tcpip(address, port, "10.0.0.9", 50001)?;
+			Ok(true)
+		}
+	}
+
+	fn host_key() -> PrivateKey {
+		PrivateKey::random(&mut rand::rngs::OsRng, Algorithm::Ed25519).unwrap()
+	}
+
+	/// Serve one connection on a fresh local port; returns the port.
+	async fn serve_once() -> u16 {
+		serve(TestServer::default(), host_key()).await
+	}
+
+	async fn serve(server: TestServer, key: PrivateKey) -> u16 {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let port = listener.local_addr().unwrap().port();
+		let config = Arc::new(server::Config {
+			keys: vec![key],
+			auth_rejection_time: Duration::ZERO,
+			..Default::default()
+		});
+		tokio::spawn(async move {
+			let (socket, _) = listener.accept().await.unwrap();
+			let session = server::run_stream(config, socket, server).await.unwrap();
+			let _ = session.await;
+		});
+		port
+	}
+
+	/// Relay one connection to the server on `port`. While `stalled` is set,
+	/// nothing the server sends gets through: a dead network, not a closed one.
+	async fn relay(port: u16, stalled: Arc<AtomicBool>) -> u16 {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let relay_port = listener.local_addr().unwrap().port();
+		tokio::spawn(async move {
+			let (client, _) = listener.accept().await.unwrap();
+			let server = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+			let (mut client_read, mut client_write) = client.into_split();
+			let (mut server_read, mut server_write) = server.into_split();
+			tokio::spawn(async move {
+				let _ = tokio::io::copy(&mut client_read, &mut server_write).await;
+			});
+			let mut buf = vec![0; 32 * 1024];
+			while let Ok(n @ 1..) = server_read.read(&mut buf).await {
+				while stalled.load(Ordering::Relaxed) {
+					tokio::time::sleep(Duration::from_millis(10)).await;
+				}
+				if client_write.write_all(&buf[..n]).await.is_err() {
+					break;
+				}
+			}
+		});
+		relay_port
+	}
+
+	/// Accepts any key, after `delay` (a user reading the fingerprint).
+	struct SlowVerifier(Duration);
+
+	impl HostKeyVerifier for SlowVerifier {
+		fn verify(&self, _info: ServerPublicKeyInfo) -> BoxFuture<'static, bool> {
+			let delay = self.0;
+			Box::pin(async move {
+				tokio::time::sleep(delay).await;
+				true
+			})
+		}
+	}
+
+	/// Accepts any key, noting each one's port and fingerprint.
+	#[derive(Default)]
+	struct Recorder(std::sync::Mutex<Vec<(u16, String)>>);
+
+	impl HostKeyVerifier for Arc<Recorder> {
+		fn verify(&self, info: ServerPublicKeyInfo) -> BoxFuture<'static, bool> {
+			self.0
+				.lock()
+				.unwrap()
+				.push((info.port, info.fingerprint_sha256));
+			Box::pin(async { true })
+		}
+	}
+
+	/// Answers every prompt "123456", after `delay` (a user finding their OTP).
+	struct SlowPrompter(Duration);
+
+	impl AuthPrompter for SlowPrompter {
+		fn prompt(&self, request: AuthPromptRequest) -> BoxFuture<'static, Option<Vec<String>>> {
+			let delay = self.0;
+			Box::pin(async move {
+				tokio::time::sleep(delay).await;
+				Some(vec!["123456".to_string(); request.prompts.len()])
+			})
+		}
+	}
+
+	/// Answers each round from `answers` in turn, noting what was asked.
+	struct Scripted {
+		answers: std::sync::Mutex<Vec<Option<&'static str>>>,
+		asked: std::sync::Mutex<Vec<(u32, String, bool)>>,
+	}
+
+	impl Scripted {
+		fn new(answers: &[Option<&'static str>]) -> Arc<Self> {
+			Arc::new(Self {
+				answers: std::sync::Mutex::new(answers.iter().rev().copied().collect()),
+				asked: Default::default(),
+			})
+		}
+	}
+
+	impl AuthPrompter for Scripted {
+		fn prompt(&self, request: AuthPromptRequest) -> BoxFuture<'static, Option<Vec<String>>> {
+			let mut asked = self.asked.lock().unwrap();
+			for p in request.prompts {
+				asked.push((request.round, p.prompt, p.echo));
+			}
+			let answer = self.answers.lock().unwrap().pop().flatten();
+			Box::pin(async move { answer.map(|a| vec![a.to_string()]) })
+		}
+	}
+
+	fn options(port: u16, credentials: Vec<Security>) -> ConnectOptions {
+		ConnectOptions {
+			details: ConnectionDetails {
+				host: "127.0.0.1".to_string(),
+				port,
+				username: "me".to_string(),
+				credentials,
+			},
+			verifier: Arc::new(SlowVerifier(Duration::ZERO)),
+			prompter: None,
+			on_progress: None,
+			jump_hosts: Vec::new(),
+			config: ConnectConfig::default(),
+			agent: None,
+			agent_confirmer: None,
+		}
+	}
+
+	#[tokio::test]
+	async fn an_unusable_key_does_not_end_the_chain() {
+		let bad_key = Security::Key {
+			private_key_content: "not a key".to_string(),
+			passphrase: None,
+			certificate: None,
+		};
+		let password = |password: &str| Security::Password {
+			password: password.to_string(),
+		};
+
+		let port = serve_once().await;
+		connect(options(port, vec![bad_key.clone(), password("hunter2")]))
+			.await
+			.unwrap();
+
+		// With nothing else getting in, the key's own error is the one reported.
+		let port = serve_once().await;
+		let err = connect(options(port, vec![bad_key, password("wrong")]))
+			.await
+			.err()
+			.unwrap();
+		assert!(matches!(err, SshError::RusshKeys(_)), "{err:?}");
+	}
+
+	#[tokio::test]
+	async fn ping_waits_out_a_server_that_stops_answering() {
+		let stalled = Arc::new(AtomicBool::new(false));
+		let port = relay(serve_once().await, stalled.clone()).await;
+		let conn = connect(options(
+			port,
+			vec![Security::Password {
+				password: "hunter2".to_string(),
+			}],
+		))
+		.await
+		.unwrap();
+		let wait = Duration::from_secs(5);
+
+		let rtt = tokio::time::timeout(wait, conn.ping())
+			.await
+			.unwrap()
+			.unwrap();
+		assert!(rtt < wait);
+
+		stalled.store(true, Ordering::Relaxed);
+		let silent = tokio::time::timeout(Duration::from_millis(300), conn.ping()).await;
+		assert!(silent.is_err(), "answered while silent: {silent:?}");
+
+		stalled.store(false, Ordering::Relaxed);
+		tokio::time::timeout(wait, conn.ping())
+			.await
+			.unwrap()
+			.unwrap();
+
+		conn.disconnect().await.unwrap();
+		let gone = tokio::time::timeout(wait, conn.ping()).await.unwrap();
+		assert!(gone.is_err(), "{gone:?}");
+	}
+
+	#[tokio::test]
+	async fn a_two_hop_chain_tunnels_and_verifies_each_hop() {
+		let fingerprint = |key: &PrivateKey| {
+			key.public_key()
+				.fingerprint(russh::keys::ssh_key::HashAlg::Sha256)
+				.to_string()
+		};
+		let password = || {
+			vec![Security::Password {
+				password: "hunter2".to_string(),
+			}]
+		};
+		let keys = [host_key(), host_key(), host_key()];
+		let (first, second) = (TestServer::default(), TestServer::default());
+		let ports = [
+			serve(first.clone(), keys[0].clone()).await,
+			serve(second.clone(), keys[1].clone()).await,
+			serve(TestServer::default(), keys[2].clone()).await,
+		];
+		let recorders: [Arc<Recorder>; 3] = Default::default();
+		let events = Arc::new(std::sync::Mutex::new(Vec::new()));
+
+		let mut opts = options(ports[2], password());
+		opts.verifier = Arc::new(recorders[2].clone());
+		opts.jump_hosts = (0..2)
+			.map(|hop| JumpHost {
+				details: options(ports[hop], password()).details,
+				verifier: Arc::new(recorders[hop].clone()),
+				prompter: None,
+			})
+			.collect();
+		let sink = events.clone();
+		opts.on_progress = Some(Arc::new(move |event| sink.lock().unwrap().push(event)));
+		let conn = connect(opts).await.unwrap();
+
+		// Each hop was checked by its own verifier, against its own key.
+		for hop in 0..3 {
+			assert_eq!(
+				*recorders[hop].0.lock().unwrap(),
+				[(ports[hop], fingerprint(&keys[hop]))],
+				"hop {hop}"
+			);
+		}
+		// The first hop tunnelled to the second, the second to the target.
+		assert_eq!(
+			*first.tunnelled.lock().unwrap(),
+			[format!("127.0.0.1:{}", ports[1])]
+		);
+		assert_eq!(
+			*second.tunnelled.lock().unwrap(),
+			[format!("127.0.0.1:{}", ports[2])]
+		);
+		use SshConnectionProgressEvent as P;
+		let steps: Vec<P> = events
+			.lock()
+			.unwrap()
+			.iter()
+			.filter(|e| !matches!(e, P::DnsResolved { .. } | P::TcpAttempt { .. }))
+			.cloned()
+			.collect();
+		assert_eq!(
+			steps,
+			[
+				P::JumpHostTcpConnected { hop: 0 },
+				P::JumpHostSshHandshake { hop: 0 },
+				P::JumpHostAuthenticated { hop: 0 },
+				P::JumpHostTcpConnected { hop: 1 },
+				P::JumpHostSshHandshake { hop: 1 },
+				P::JumpHostAuthenticated { hop: 1 },
+				P::TcpConnected,
+				P::SshHandshake,
+			]
+		);
+		// And the session at the end of it answers.
+		conn.ping().await.unwrap();
+	}
+
+	#[tokio::test]
+	async fn keyboard_interactive_answers_every_round() {
+		let server = TestServer {
+			second_round: true,
+			..Default::default()
+		};
+		let port = serve(server, host_key()).await;
+		let prompter = Scripted::new(&[Some("123456"), Some("654321")]);
+		let mut opts = options(port, vec![Security::KeyboardInteractive]);
+		opts.prompter = Some(prompter.clone());
+		connect(opts).await.unwrap();
+		assert_eq!(
+			*prompter.asked.lock().unwrap(),
+			[
+				(0, "Code: ".to_string(), false),
+				(1, "Token: ".to_string(), true)
+			]
+		);
+	}
+
+	#[tokio::test]
+	async fn a_cancelled_round_ends_authentication() {
+		let server = TestServer {
+			second_round: true,
+			..Default::default()
+		};
+		let port = serve(server, host_key()).await;
+		let prompter = Scripted::new(&[Some("123456"), None]);
+		let mut opts = options(port, vec![Security::KeyboardInteractive]);
+		opts.prompter = Some(prompter.clone());
+		let err = connect(opts).await.err().unwrap();
+		assert!(
+			matches!(&err, SshError::Auth(m) if m == "cancelled"),
+			"{err:?}"
+		);
+		assert_eq!(prompter.asked.lock().unwrap().len(), 2);
+	}
+
+	#[tokio::test]
+	async fn prompts_are_not_charged_to_the_deadlines() {
+		let port = serve_once().await;
+		let slow = Duration::from_millis(400);
+		let mut opts = options(port, vec![Security::KeyboardInteractive]);
+		opts.verifier = Arc::new(SlowVerifier(slow));
+		opts.prompter = Some(Arc::new(SlowPrompter(slow)));
+		opts.config.timeouts = ConnectTimeouts {
+			connect_ms: None,
+			handshake_ms: Some(200),
+			auth_ms: Some(200),
+		};
+		connect(opts).await.unwrap();
+	}
+
+	async fn recv(forward: &mut RemoteForward) -> Option<ForwardedConnection> {
+		tokio::time::timeout(Duration::from_secs(5), forward.incoming.recv())
+			.await
+			.unwrap()
+	}
+
+	#[tokio::test]
+	async fn remote_forwards_route_connections_that_beat_the_reply() {
+		let port = serve_once().await;
+		let conn = connect(options(
+			port,
+			vec![Security::Password {
+				password: "hunter2".to_string(),
+			}],
+		))
+		.await
+		.unwrap();
+
+		let mut fixed = conn
+			.request_remote_forward("127.0.0.1", 4711)
+			.await
+			.unwrap();
+		assert_eq!(fixed.bound_port, 4711);
+		let first = recv(&mut fixed).await.unwrap();
+		assert_eq!(
+			(first.connected_address.as_str(), first.connected_port),
+			("127.0.0.1", 4711)
+		);
+		assert_eq!(first.originator_address, "10.0.0.9");
+		let err = conn.request_remote_forward("127.0.0.1", 4711).await.err();
+		assert!(err.is_some(), "a bind can only be forwarded once");
+
+		// Echoed as 127.0.0.1: the only route on that port takes it.
+		let mut normalized = conn
+			.request_remote_forward("localhost", 4713)
+			.await
+			.unwrap();
+		assert_eq!(recv(&mut normalized).await.unwrap().connected_port, 4713);
+
+		// Port 0: held until the reply names the port.
+		let mut picked = conn.request_remote_forward("127.0.0.1", 0).await.unwrap();
+		assert_eq!(picked.bound_port, 4712);
+		assert_eq!(recv(&mut picked).await.unwrap().connected_port, 4712);
+
+		assert!(conn.request_remote_forward("127.0.0.1", 22).await.is_err());
+
+		// Cancelled: the queue closes, and a straggler isn't delivered.
+		conn.cancel_remote_forward("127.0.0.1", 4711).await.unwrap();
+		assert!(recv(&mut fixed).await.is_none());
+	}
+}
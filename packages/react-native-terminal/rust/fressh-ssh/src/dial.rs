@@ -0,0 +1,330 @@
+//! TCP dialing: DNS resolution filtered/ordered by [`IpPreference`], then an
+//! RFC 8305 ("Happy Eyeballs v2") race across the resolved addresses, so one
+//! dead address family or black-holed address doesn't stall the connect.
+//!
+//! Attempts are started one at a time, [`ATTEMPT_DELAY`] apart (or as soon as
+//! the previous one fails), in alternating address-family order; the first
+//! socket to connect wins and the rest are dropped.
+
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::stream::{FuturesUnordered, StreamExt};
+use tokio::net::TcpStream;
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+use crate::connection::SshConnectionProgressEvent;
+use crate::utils::SshError;
+
+/// RFC 8305 §8's recommended "Connection Attempt Delay".
+const ATTEMPT_DELAY: Duration = Duration::from_millis(250);
+
+/// Which address families to dial, and which to try first.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IpPreference {
+	/// Both, led by whichever family the resolver put first.
+	#[default]
+	Any,
+	Ipv4First,
+	Ipv6First,
+	Ipv4Only,
+	Ipv6Only,
+}
+
+/// Per-phase connect deadlines, in milliseconds; `None` waits indefinitely.
+/// Each applies per hop of a jump chain.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ConnectTimeouts {
+	/// DNS plus the TCP race (or opening the tunnel, through a jump host).
+	pub connect_ms: Option<u32>,
+	/// The SSH handshake. The clock stops while the host key is with the
+	/// verifier, so a user deciding on an unknown key can take their time.
+	pub handshake_ms: Option<u32>,
+	/// The whole credential chain, less the time spent in keyboard-interactive
+	/// prompts.
+	pub auth_ms: Option<u32>,
+}
+
+/// Run `fut`, failing with [`SshError::Timeout`] naming `phase` if it takes
+/// longer than `limit_ms`.
+pub(crate) async fn deadline<T>(
+	limit_ms: Option<u32>,
+	phase: &str,
+	fut: impl Future<Output = Result<T, SshError>>,
+) -> Result<T, SshError> {
+	match limit_ms {
+		None => fut.await,
+		Some(ms) => tokio::time::timeout(Duration::from_millis(ms.into()), fut)
+			.await
+			.map_err(|_| SshError::Timeout(format!("{phase} after {ms} ms")))?,
+	}
+}
+
+/// Time spent waiting on a person (a host-key decision, an OTP), which
+/// [`deadline_excluding`] leaves out of its phase's budget. Clones share it.
+#[derive(Clone, Default)]
+pub(crate) struct PromptClock(Arc<watch::Sender<Paused>>);
+
+#[derive(Clone, Copy, Default)]
+struct Paused {
+	/// Prompts in flight; the clock runs only at 0.
+	depth: u32,
+	/// Finished pauses, summed.
+	total: Duration,
+	since: Option<Instant>,
+}
+
+impl PromptClock {
+	/// Run `fut` with the clock stopped.
+	pub(crate) async fn exclude<T>(&self, fut: impl Future<Output = T>) -> T {
+		struct Resume<'a>(&'a watch::Sender<Paused>);
+		impl Drop for Resume<'_> {
+			fn drop(&mut self) {
+				self.0.send_modify(|p| {
+					p.depth -= 1;
+					if p.depth == 0 {
+						p.total += p.since.take().map_or(Duration::ZERO, |t| t.elapsed());
+					}
+				});
+			}
+		}
+		self.0.send_modify(|p| {
+			if p.depth == 0 {
+				p.since = Some(Instant::now());
+			}
+			p.depth += 1;
+		});
+		let _resume = Resume(&self.0);
+		fut.await
+	}
+}
+
+/// [`deadline`], but not counting the time `clock` spends stopped.
+pub(crate) async fn deadline_excluding<T>(
+	limit_ms: Option<u32>,
+	phase: &str,
+	clock: &PromptClock,
+	fut: impl Future<Output = Result<T, SshError>>,
+) -> Result<T, SshError> {
+	let Some(ms) = limit_ms else {
+		return fut.await;
+	};
+	let started = Instant::now();
+	let limit = Duration::from_millis(ms.into());
+	let mut changes = clock.0.subscribe();
+	let earlier = changes.borrow_and_update().total;
+	let mut fut = pin!(fut);
+	loop {
+		let paused = *changes.borrow_and_update();
+		let expiry = started + limit + (paused.total - earlier);
+		tokio::select! {
+			out = &mut fut => return out,
+			// The sender lives in `clock`, which outlives this loop.
+			_ = changes.changed() => {}
+			() = tokio::time::sleep_until(expiry), if paused.depth == 0 => {
+				return Err(SshError::Timeout(format!("{phase} after {ms} ms")));
+			}
+		}
+	}
+}
+
+/// Apply `preference` to resolver output: drop excluded families, then
+/// interleave the rest (RFC 8305 §4, "First Address Family Count" of 1),
+/// keeping the resolver's order within each family. `Any` leads with the
+/// resolver's first family: it has already applied RFC 6724.
+pub(crate) fn order_addresses(addrs: Vec<SocketAddr>, preference: IpPreference) -> Vec<SocketAddr> {
+	let v6_first = match preference {
+		IpPreference::Any => addrs.first().is_none_or(SocketAddr::is_ipv6),
+		IpPreference::Ipv4First | IpPreference::Ipv4Only => false,
+		IpPreference::Ipv6First | IpPreference::Ipv6Only => true,
+	};
+	let (v6, v4): (Vec<_>, Vec<_>) = addrs.into_iter().partition(SocketAddr::is_ipv6);
+	let (first, second) = match preference {
+		IpPreference::Ipv4Only => return v4,
+		IpPreference::Ipv6Only => return v6,
+		_ if v6_first => (v6, v4),
+		_ => (v4, v6),
+	};
+	let mut out = Vec::with_capacity(first.len() + second.len());
+	let (mut first, mut second) = (first.into_iter(), second.into_iter());
+	loop {
+		match (first.next(), second.next()) {
+			(None, None) => return out,
+			(a, b) => out.extend(a.into_iter().chain(b)),
+		}
+	}
+}
+
+/// Resolve `host:port` and race TCP connects across the result.
+pub(crate) async fn connect_tcp(
+	host: &str,
+	port: u16,
+	preference: IpPreference,
+	progress: &(dyn Fn(SshConnectionProgressEvent) + Sync),
+) -> Result<TcpStream, SshError> {
+	let resolved: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+		.await
+		.map_err(|e| SshError::Dns(format!("{host}: {e}")))?
+		.collect();
+	let addrs = order_addresses(resolved, preference);
+	if addrs.is_empty() {
+		return Err(SshError::Dns(format!("{host}: no usable address")));
+	}
+	progress(SshConnectionProgressEvent::DnsResolved {
+		addresses: addrs.iter().map(|a| a.ip().to_string()).collect(),
+	});
+	race(addrs, progress).await
+}
+
+/// The Happy Eyeballs race proper. Returns the last failure if none connect.
+pub(crate) async fn race(
+	addrs: Vec<SocketAddr>,
+	progress: &(dyn Fn(SshConnectionProgressEvent) + Sync),
+) -> Result<TcpStream, SshError> {
+	let mut pending = addrs.into_iter();
+	let mut attempts = FuturesUnordered::new();
+	let mut last_error = None;
+	let start = |addr: SocketAddr, attempts: &mut FuturesUnordered<_>| {
+		progress(SshConnectionProgressEvent::TcpAttempt {
+			address: addr.ip().to_string(),
+		});
+		attempts.push(async move { (addr, TcpStream::connect(addr).await) });
+	};
+	if let Some(addr) = pending.next() {
+		start(addr, &mut attempts);
+	}
+	while !attempts.is_empty() {
+		let stagger = tokio::time::sleep(ATTEMPT_DELAY);
+		tokio::select! {
+			Some((addr, result)) = attempts.next() => match result {
+				Ok(socket) => return Ok(socket),
+				Err(e) => {
+					progress(SshConnectionProgressEvent::TcpAttemptFailed {
+						address: addr.ip().to_string(),
+						error: e.to_string(),
+					});
+					last_error = Some(e);
+					if let Some(next) = pending.next() {
+						start(next, &mut attempts);
+					}
+				}
+			},
+			() = stagger, if pending.len() > 0 => {
+				if let Some(next) = pending.next() {
+					start(next, &mut attempts);
+				}
+			}
+		}
+	}
+	Err(last_error.map_or(SshError::Disconnected, SshError::from))
+}
+
+#[cfg(test)]
+mod tests {
+	use std::sync::Mutex;
+
+	use super::*;
+
+	fn addr(s: &str) -> SocketAddr {
+		s.parse().unwrap()
+	}
+
+	#[test]
+	fn interleaves_and_filters_families() {
+		let resolved = vec![
+			addr("10.0.0.1:22"),
+			addr("10.0.0.2:22"),
+			addr("10.0.0.3:22"),
+			addr("[fd00::1]:22"),
+		];
+		assert_eq!(
+			order_addresses(resolved.clone(), IpPreference::Ipv6First),
+			[
+				addr("[fd00::1]:22"),
+				addr("10.0.0.1:22"),
+				addr("10.0.0.2:22"),
+				addr("10.0.0.3:22"),
+			]
+		);
+		assert_eq!(
+			order_addresses(resolved.clone(), IpPreference::Ipv4First),
+			[
+				addr("10.0.0.1:22"),
+				addr("[fd00::1]:22"),
+				addr("10.0.0.2:22"),
+				addr("10.0.0.3:22"),
+			]
+		);
+		assert_eq!(
+			order_addresses(resolved.clone(), IpPreference::Ipv6Only),
+			[addr("[fd00::1]:22")]
+		);
+		assert_eq!(order_addresses(resolved, IpPreference::Ipv4Only).len(), 3);
+	}
+
+	#[tokio::test]
+	async fn race_moves_past_a_refused_address() {
+		let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let good = listener.local_addr().unwrap();
+		// Bind then drop: nothing listens there, so the connect is refused.
+		let refused = std::net::TcpListener::bind("127.0.0.1:0")
+			.unwrap()
+			.local_addr()
+			.unwrap();
+
+		let events = Mutex::new(Vec::new());
+		let socket = race(vec![refused, good], &|e| events.lock().unwrap().push(e))
+			.await
+			.unwrap();
+		assert_eq!(socket.peer_addr().unwrap(), good);
+		let events = events.into_inner().unwrap();
+		assert!(matches!(
+			&events[..],
+			[
+				SshConnectionProgressEvent::TcpAttempt { .. },
+				SshConnectionProgressEvent::TcpAttemptFailed { .. },
+				SshConnectionProgressEvent::TcpAttempt { .. },
+			]
+		));
+	}
+
+	#[tokio::test]
+	async fn deadline_names_the_phase() {
+		let slow = async {
+			tokio::time::sleep(Duration::from_secs(5)).await;
+			Ok(())
+		};
+		let err = deadline(Some(10), "handshake", slow).await.unwrap_err();
+		assert!(matches!(err, SshError::Timeout(m) if m == "handshake after 10 ms"));
+	}
+
+	#[tokio::test]
+	async fn prompts_do_not_count_against_the_deadline() {
+		let clock = PromptClock::default();
+		let with_prompt = async {
+			tokio::time::sleep(Duration::from_millis(20)).await;
+			clock
+				.exclude(tokio::time::sleep(Duration::from_millis(150)))
+				.await;
+			tokio::time::sleep(Duration::from_millis(20)).await;
+			Ok(())
+		};
+		deadline_excluding(Some(100), "auth", &clock, with_prompt)
+			.await
+			.unwrap();
+
+		// Outside a prompt, the budget still applies.
+		let slow = tokio::time::sleep(Duration::from_secs(5));
+		let err = deadline_excluding(Some(10), "auth", &clock, async {
+			slow.await;
+			Ok(())
+		})
+		.await
+		.unwrap_err();
+		assert!(matches!(err, SshError::Timeout(m) if m == "auth after 10 ms"));
+	}
+}
@@ -0,0 +1,164 @@
+//! A streaming `exec` channel: the command's output as it arrives, stdin, and
+//! signals — where [`Connection::exec_command`] buffers everything until the
+//! channel closes. Split like a shell into an [`ExecReader`] and a cloneable
+//! [`ExecWriter`].
+//!
+//! [`Connection::exec_command`]: crate::Connection::exec_command
+
+use std::sync::Arc;
+
+use bytes::Bytes;
+use russh::{client, ChannelMsg, ChannelWriteHalf, Sig};
+use tokio::sync::Mutex as AsyncMutex;
+
+use crate::shell::{ShellChunk, StreamKind};
+use crate::utils::SshError;
+
+/// The signals an exec channel can deliver (an SSH `signal` request). Servers
+/// may ignore them (older OpenSSH releases do); closing the channel is the
+/// fallback.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExecSignal {
+	Int,
+	Term,
+	Kill,
+}
+
+impl ExecSignal {
+	fn as_sig(self) -> Sig {
+		match self {
+			ExecSignal::Int => Sig::INT,
+			ExecSignal::Term => Sig::TERM,
+			ExecSignal::Kill => Sig::KILL,
+		}
+	}
+}
+
+/// What an exec channel reports, in arrival order.
+#[derive(Debug, Clone)]
+pub enum ExecEvent {
+	Output(ShellChunk),
+	/// The command exited normally (`exit-status`).
+	ExitStatus(u32),
+	/// The command was killed by a signal (`exit-signal`). `signal` is the name
+	/// without the `SIG` prefix.
+	ExitSignal {
+		signal: String,
+		core_dumped: bool,
+		message: String,
+	},
+}
+
+/// The read half of an exec channel. Call [`recv`](ExecReader::recv) until it
+/// returns `None` (channel closed).
+pub struct ExecReader {
+	inner: russh::ChannelReadHalf,
+}
+
+impl ExecReader {
+	pub async fn recv(&mut self) -> Option<ExecEvent> {
+		loop {
+			let event = match self.inner.wait().await? {
+				ChannelMsg::Data { data } => ExecEvent::Output(ShellChunk {
+					stream: StreamKind::Stdout,
+					bytes: Bytes::copy_from_slice(&data),
+				}),
+				// ext 1 is stderr; anything else is folded into stdout, as in
+				// `exec_command`.
+				ChannelMsg::ExtendedData { data, ext } => ExecEvent::Output(ShellChunk {
+					stream: if ext == 1 {
+						StreamKind::Stderr
+					} else {
+						StreamKind::Stdout
+					},
+					bytes: Bytes::copy_from_slice(&data),
+				}),
+				ChannelMsg::ExitStatus { exit_status } => ExecEvent::ExitStatus(exit_status),
+				ChannelMsg::ExitSignal {
+					signal_name,
+					core_dumped,
+					error_message,
+					..
+				} => ExecEvent::ExitSignal {
+					signal: signal_name_of(&signal_name),
+					core_dumped,
+					message: error_message,
+				},
+				ChannelMsg::Close => return None,
+				_ => continue,
+			};
+			return Some(event);
+		}
+	}
+}
+
+pub(crate) fn signal_name_of(sig: &Sig) -> String {
+	match sig {
+		Sig::Custom(name) => name.clone(),
+		// The `Debug` form of the named variants is the wire name.
+		other => format!("{other:?}"),
+	}
+}
+
+/// The write half of an exec channel: stdin, EOF, signals and close.
+#[derive(Clone)]
+pub struct ExecWriter {
+	inner: Arc<AsyncMutex<ChannelWriteHalf<client::Msg>>>,
+}
+
+impl ExecWriter {
+	pub async fn send_data(&self, data: &[u8]) -> Result<(), SshError> {
+		self.inner.lock().await.data(data).await?;
+		Ok(())
+	}
+
+	/// Close the command's stdin. Output keeps flowing.
+	pub async fn eof(&self) -> Result<(), SshError> {
+		self.inner.lock().await.eof().await?;
+		Ok(())
+	}
+
+	pub async fn signal(&self, signal: ExecSignal) -> Result<(), SshError> {
+		self.inner.lock().await.signal(signal.as_sig()).await?;
+		Ok(())
+	}
+
+	/// Close the channel (best effort); the reader then ends.
+	pub async fn close(&self) -> Result<(), SshError> {
+		self.inner.lock().await.close().await.ok();
+		Ok(())
+	}
+}
+
+/// A freshly started command.
+pub struct Exec {
+	pub channel_id: u32,
+	pub reader: ExecReader,
+	pub writer: ExecWriter,
+}
+
+impl Exec {
+	pub(crate) fn new(channel: russh::Channel<client::Msg>) -> Self {
+		let channel_id = channel.id().into();
+		let (reader, writer) = channel.split();
+		Exec {
+			channel_id,
+			reader: ExecReader { inner: reader },
+			writer: ExecWriter {
+				inner: Arc::new(AsyncMutex::new(writer)),
+			},
+		}
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn signal_names_drop_the_prefix() {
+		assert_eq!(signal_name_of(&Sig::TERM), "TERM");
+		assert_eq!(signal_name_of(&Sig::KILL), "KILL");
+		assert_eq!(signal_name_of(&Sig::Custom("WINCH".into())), "WINCH");
+	}
+}
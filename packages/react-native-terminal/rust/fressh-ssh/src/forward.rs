@@ -0,0 +1,157 @@
+//! Remote port forwarding (`-R`) plumbing: routing the server's incoming
+//! `forwarded-tcpip` channels back to whoever asked for the forward.
+//!
+//! The russh `Handler` is moved into the session task at connect time, so it
+//! can't be told about forwards requested later. Instead it shares a
+//! [`RemoteForwardRoutes`] table with the [`Connection`](crate::Connection):
+//! `request_remote_forward` registers a route, and the handler looks each
+//! incoming channel up by the address/port the server accepted it on and hands
+//! it over as a [`ForwardedConnection`]. What happens next (dialing the local
+//! target, pumping bytes, counting them) is the caller's business — `fressh-core`
+//! does it exactly like its `-L` streams.
+
+use std::collections::hash_map::Entry;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::mpsc;
+
+use crate::connection::TunnelStream;
+
+/// One connection the server accepted on a `-R` listener, as a byte stream plus
+/// where it arrived and where it came from (as reported by the server).
+pub struct ForwardedConnection {
+	pub stream: TunnelStream,
+	pub connected_address: String,
+	pub connected_port: u16,
+	pub originator_address: String,
+	pub originator_port: u16,
+}
+
+/// A granted `tcpip-forward`: the port the server actually bound (it picks one
+/// when asked for 0) and the queue its connections arrive on. The queue closes
+/// when the forward is cancelled or the connection drops.
+pub struct RemoteForward {
+	pub bound_port: u16,
+	pub incoming: mpsc::UnboundedReceiver<ForwardedConnection>,
+}
+
+/// `(bind address, bound port)` → the queue of the forward listening there.
+type RouteTable = HashMap<(String, u16), mpsc::UnboundedSender<ForwardedConnection>>;
+
+#[derive(Default)]
+struct Routes {
+	table: RouteTable,
+	/// Port-0 requests still waiting for the server to say which port it bound.
+	/// The server may open channels on that port before its reply reaches us.
+	awaiting_port: usize,
+	/// Channels no route wanted while a port was awaited; retried when it lands.
+	held: Vec<ForwardedConnection>,
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct RemoteForwardRoutes {
+	routes: Arc<Mutex<Routes>>,
+}
+
+impl RemoteForwardRoutes {
+	/// Route `address:port` to `tx`. `false` (and nothing changes) if a forward
+	/// already listens there.
+	pub(crate) fn insert(
+		&self,
+		address: &str,
+		port: u16,
+		tx: mpsc::UnboundedSender<ForwardedConnection>,
+	) -> bool {
+		match self.lock().table.entry((address.to_string(), port)) {
+			Entry::Occupied(_) => false,
+			Entry::Vacant(slot) => {
+				slot.insert(tx);
+				true
+			}
+		}
+	}
+
+	pub(crate) fn remove(&self, address: &str, port: u16) {
+		self.lock().table.remove(&(address.to_string(), port));
+	}
+
+	/// Mark a port-0 request in flight: until the returned guard is dropped,
+	/// channels that match no route are held rather than closed.
+	pub(crate) fn await_port(&self) -> AwaitingPort {
+		self.lock().awaiting_port += 1;
+		AwaitingPort {
+			routes: self.clone(),
+		}
+	}
+
+	/// Route an incoming channel. Servers echo the bind address back as it was
+	/// requested, but some normalize it (`""` vs `0.0.0.0` vs `localhost`), so an
+	/// exact miss falls back to the only route on that port, if unambiguous.
+	/// Returns `false` if nothing wanted it; the stream is dropped, closing the
+	/// channel.
+	pub(crate) fn dispatch(&self, conn: ForwardedConnection) -> bool {
+		let mut routes = self.lock();
+		if let Some(tx) = route(&routes.table, &conn) {
+			return tx.send(conn).is_ok();
+		}
+		if routes.awaiting_port == 0 {
+			return false;
+		}
+		routes.held.push(conn);
+		true
+	}
+
+	fn lock(&self) -> std::sync::MutexGuard<'_, Routes> {
+		self.routes.lock().unwrap_or_else(|p| p.into_inner())
+	}
+}
+
+fn route<'a>(
+	table: &'a RouteTable,
+	conn: &ForwardedConnection,
+) -> Option<&'a mpsc::UnboundedSender<ForwardedConnection>> {
+	let key = (conn.connected_address.clone(), conn.connected_port);
+	table.get(&key).or_else(|| {
+		let mut on_port = table
+			.iter()
+			.filter(|((_, port), _)| *port == conn.connected_port);
+		match (on_port.next(), on_port.next()) {
+			(Some((_, tx)), None) => Some(tx),
+			_ => None,
+		}
+	})
+}
+
+/// A port-0 `tcpip-forward` awaiting its reply (see
+/// [`RemoteForwardRoutes::await_port`]). Dropping it — the request failed or
+/// was abandoned — re-routes what was held and closes what still has no home.
+pub(crate) struct AwaitingPort {
+	routes: RemoteForwardRoutes,
+}
+
+impl AwaitingPort {
+	/// The server bound `port`: route it to `tx`, then release what was held.
+	pub(crate) fn bound(
+		self,
+		address: &str,
+		port: u16,
+		tx: mpsc::UnboundedSender<ForwardedConnection>,
+	) -> bool {
+		self.routes.insert(address, port, tx)
+	}
+}
+
+impl Drop for AwaitingPort {
+	fn drop(&mut self) {
+		let mut routes = self.routes.lock();
+		routes.awaiting_port -= 1;
+		for conn in std::mem::take(&mut routes.held) {
+			if let Some(tx) = route(&routes.table, &conn) {
+				let _ = tx.send(conn);
+			} else if routes.awaiting_port > 0 {
+				routes.held.push(conn);
+			}
+		}
+	}
+}
@@ -6,20 +6,43 @@
 //! `fressh-core`; this crate only speaks SSH and hands `fressh-core` a clean
 //! byte stream + writer. See docs/projects/native-rendering-refactor.md §7, §9, §10.
 
+pub mod agent;
+pub mod algorithms;
 pub mod connection;
+pub mod dial;
+pub mod exec;
+pub mod forward;
 pub mod private_key;
+pub mod proxy;
+pub mod scp;
+pub mod sftp;
 pub mod shell;
 pub mod shell_integration;
+pub mod ssh_config;
 pub mod utils;
 
+pub use agent::{AgentConfirmer, AgentKeyInfo, AgentSignRequest, KeyAgent};
+pub use algorithms::{AlgorithmPreferences, NegotiatedAlgorithms};
 pub use connection::{
-	connect, CommandOutput, ConnectOptions, Connection, ConnectionDetails, HostKeyVerifier,
-	ProgressCallback, Security, ServerPublicKeyInfo, SshConnectionInfo,
-	SshConnectionInfoProgressTimings, SshConnectionProgressEvent,
+	connect, AuthPrompt, AuthPromptRequest, AuthPrompter, CommandOutput, ConnectConfig,
+	ConnectOptions, Connection, ConnectionDetails, HostKeyVerifier, JumpHost, KeepaliveConfig,
+	ProgressCallback, ReconnectConfig, Security, ServerPublicKeyInfo, SshConnectionInfo,
+	SshConnectionInfoProgressTimings, SshConnectionProgressEvent, TunnelStream,
 };
-pub use private_key::{generate_key_pair, validate_private_key, KeyType};
+pub use dial::{ConnectTimeouts, IpPreference};
+pub use exec::{Exec, ExecEvent, ExecReader, ExecSignal, ExecWriter};
+pub use forward::{ForwardedConnection, RemoteForward};
+pub use private_key::{
+	generate_key_pair, inspect_certificate, validate_private_key, CertificateInfo, CertificateType,
+	KeyType,
+};
+pub use proxy::{ProxyKind, UpstreamProxy};
+pub use scp::ScpOptions;
+pub use sftp::{Sftp, SftpEntry, SftpFileType, SftpStat};
 pub use shell::{
-	Shell, ShellChunk, ShellReader, ShellWriter, StartShellOptions, StreamKind, TerminalMode,
-	TerminalPixelSize, TerminalSize, TerminalType,
+	Shell, ShellChunk, ShellExit, ShellReader, ShellWriter, StartShellOptions, StreamKind,
+	TerminalMode, TerminalPixelSize, TerminalSize, TerminalType,
 };
+pub use shell_integration::EnvReport;
+pub use ssh_config::{SshConfig, SshConfigHop, SshConfigHost};
 pub use utils::{now_ms, SshError};
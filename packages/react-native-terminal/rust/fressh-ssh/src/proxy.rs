@@ -0,0 +1,336 @@
+//! The client side of an upstream proxy, for networks where SSH can't leave
+//! directly: HTTP `CONNECT` (RFC 9110 §9.3.6, optional Basic auth) and SOCKS5
+//! (RFC 1928, optional username/password per RFC 1929).
+//!
+//! [`connect`] runs the proxy's handshake over an already-dialed socket; once it
+//! returns, the stream is a plain byte pipe to the target and goes to
+//! `client::connect_stream` like any other. The target's name is handed to the
+//! proxy unresolved, so DNS happens on the proxy's side.
+
+use std::io;
+use std::net::IpAddr;
+
+use base64::Engine as _;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::utils::SshError;
+
+const SOCKS5: u8 = 0x05;
+const NO_AUTH: u8 = 0x00;
+const USER_PASS: u8 = 0x02;
+const USER_PASS_VERSION: u8 = 0x01;
+const CMD_CONNECT: u8 = 0x01;
+const ATYP_IPV4: u8 = 0x01;
+const ATYP_DOMAIN: u8 = 0x03;
+const ATYP_IPV6: u8 = 0x04;
+
+/// Longest HTTP response head we read before giving up on the proxy.
+const MAX_HTTP_HEAD: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProxyKind {
+	HttpConnect,
+	Socks5,
+}
+
+/// Where the first TCP hop goes instead of the target (or first jump host).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpstreamProxy {
+	pub kind: ProxyKind,
+	pub host: String,
+	pub port: u16,
+	/// Basic auth for HTTP, RFC 1929 for SOCKS5. Sent only if `username` is set.
+	pub username: Option<String>,
+	pub password: Option<String>,
+}
+
+fn invalid(msg: impl std::fmt::Display) -> io::Error {
+	io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+/// Ask `proxy` (already connected as `stream`) for a tunnel to `host:port`.
+pub(crate) async fn connect<S>(
+	proxy: &UpstreamProxy,
+	stream: &mut S,
+	host: &str,
+	port: u16,
+) -> Result<(), SshError>
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	let result = match proxy.kind {
+		ProxyKind::HttpConnect => http_connect(proxy, stream, host, port).await,
+		ProxyKind::Socks5 => socks5_connect(proxy, stream, host, port).await,
+	};
+	result.map_err(|e| SshError::Proxy(format!("{}:{}: {e}", proxy.host, proxy.port)))
+}
+
+async fn http_connect<S>(
+	proxy: &UpstreamProxy,
+	stream: &mut S,
+	host: &str,
+	port: u16,
+) -> io::Result<()>
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	// IPv6 literals need brackets in an authority.
+	let authority = match host.parse::<IpAddr>() {
+		Ok(IpAddr::V6(_)) => format!("[{host}]:{port}"),
+		_ => format!("{host}:{port}"),
+	};
+	let mut request = format!("CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\n");
+	if let Some(username) = &proxy.username {
+		let credentials = format!("{username}:{}", proxy.password.as_deref().unwrap_or(""));
+		let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
+		request.push_str(&format!("Proxy-Authorization: Basic {encoded}\r\n"));
+	}
+	request.push_str("\r\n");
+	stream.write_all(request.as_bytes()).await?;
+	stream.flush().await?;
+
+	// Byte at a time: anything past the blank line is already the SSH banner
+	// and must stay in the stream.
+	let mut head = Vec::new();
+	while !head.ends_with(b"\r\n\r\n") {
+		if head.len() >= MAX_HTTP_HEAD {
+			return Err(invalid("response head too long"));
+		}
+		head.push(stream.read_u8().await?);
+	}
+	let status_line = head
+		.split(|&b| b == b'\n')
+		.next()
+		.map(|line| String::from_utf8_lossy(line).trim_end().to_string())
+		.unwrap_or_default();
+	let mut parts = status_line.splitn(3, ' ');
+	let (version, code) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+	if !version.starts_with("HTTP/1.") {
+		return Err(invalid(format!("not an HTTP proxy: {status_line:?}")));
+	}
+	match code.parse::<u16>() {
+		Ok(200..=299) => Ok(()),
+		Ok(407) => Err(io::Error::new(
+			io::ErrorKind::PermissionDenied,
+			format!("proxy authentication required ({status_line})"),
+		)),
+		_ => Err(io::Error::new(
+			io::ErrorKind::ConnectionRefused,
+			format!("CONNECT refused: {status_line}"),
+		)),
+	}
+}
+
+async fn socks5_connect<S>(
+	proxy: &UpstreamProxy,
+	stream: &mut S,
+	host: &str,
+	port: u16,
+) -> io::Result<()>
+where
+	S: AsyncRead + AsyncWrite + Unpin,
+{
+	let greeting: &[u8] = if proxy.username.is_some() {
+		&[SOCKS5, 2, NO_AUTH, USER_PASS]
+	} else {
+		&[SOCKS5, 1, NO_AUTH]
+	};
+	stream.write_all(greeting).await?;
+	let mut choice = [0u8; 2];
+	stream.read_exact(&mut choice).await?;
+	if choice[0] != SOCKS5 {
+		return Err(invalid(format!(
+			"not a SOCKS5 proxy (version {:#04x})",
+			choice[0]
+		)));
+	}
+	match choice[1] {
+		NO_AUTH => {}
+		USER_PASS if proxy.username.is_some() => {
+			let username = proxy.username.as_deref().unwrap_or("").as_bytes();
+			let password = proxy.password.as_deref().unwrap_or("").as_bytes();
+			let (Ok(ulen), Ok(plen)) = (u8::try_from(username.len()), u8::try_from(password.len()))
+			else {
+				return Err(invalid("username/password longer than 255 bytes"));
+			};
+			let mut auth = vec![USER_PASS_VERSION, ulen];
+			auth.extend_from_slice(username);
+			auth.push(plen);
+			auth.extend_from_slice(password);
+			stream.write_all(&auth).await?;
+			let mut status = [0u8; 2];
+			stream.read_exact(&mut status).await?;
+			if status[1] != 0 {
+				return Err(io::Error::new(
+					io::ErrorKind::PermissionDenied,
+					"proxy rejected the username/password",
+				));
+			}
+		}
+		_ => {
+			return Err(io::Error::new(
+				io::ErrorKind::PermissionDenied,
+				"proxy accepts none of our authentication methods",
+			));
+		}
+	}
+
+	let mut request = vec![SOCKS5, CMD_CONNECT, 0];
+	match host.parse::<IpAddr>() {
+		Ok(IpAddr::V4(ip)) => {
+			request.push(ATYP_IPV4);
+			request.extend_from_slice(&ip.octets());
+		}
+		Ok(IpAddr::V6(ip)) => {
+			request.push(ATYP_IPV6);
+			request.extend_from_slice(&ip.octets());
+		}
+		Err(_) => {
+			let name = u8::try_from(host.len()).map_err(|_| invalid("hostname too long"))?;
+			request.extend_from_slice(&[ATYP_DOMAIN, name]);
+			request.extend_from_slice(host.as_bytes());
+		}
+	}
+	request.extend_from_slice(&port.to_be_bytes());
+	stream.write_all(&request).await?;
+
+	let mut head = [0u8; 4];
+	stream.read_exact(&mut head).await?;
+	if head[1] != 0 {
+		return Err(io::Error::new(
+			io::ErrorKind::ConnectionRefused,
+			format!("CONNECT refused: {}", socks5_reply_text(head[1])),
+		));
+	}
+	// Skip the bound address; we have no use for it.
+	let skip = match head[3] {
+		ATYP_IPV4 => 4,
+		ATYP_IPV6 => 16,
+		ATYP_DOMAIN => stream.read_u8().await? as usize,
+		other => return Err(invalid(format!("unknown address type {other:#04x}"))),
+	};
+	let mut bound = vec![0u8; skip + 2];
+	stream.read_exact(&mut bound).await?;
+	Ok(())
+}
+
+/// RFC 1928 §6 reply codes.
+fn socks5_reply_text(rep: u8) -> &'static str {
+	match rep {
+		0x01 => "general failure",
+		0x02 => "not allowed by ruleset",
+		0x03 => "network unreachable",
+		0x04 => "host unreachable",
+		0x05 => "connection refused",
+		0x06 => "TTL expired",
+		0x07 => "command not supported",
+		0x08 => "address type not supported",
+		_ => "unknown error",
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use tokio::net::{TcpListener, TcpStream};
+
+	use super::*;
+
+	fn proxy(kind: ProxyKind, port: u16, user: Option<(&str, &str)>) -> UpstreamProxy {
+		UpstreamProxy {
+			kind,
+			host: "127.0.0.1".into(),
+			port,
+			username: user.map(|(u, _)| u.into()),
+			password: user.map(|(_, p)| p.into()),
+		}
+	}
+
+	/// A one-shot proxy stand-in: for each `(expect, reply)` exchange, reads
+	/// exactly `expect.len()` bytes, checks them and answers with `reply`; then
+	/// sends `b"SSH-"` as the "target".
+	async fn stand_in(exchanges: Vec<(Vec<u8>, Vec<u8>)>) -> u16 {
+		let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+		let port = listener.local_addr().unwrap().port();
+		tokio::spawn(async move {
+			let (mut socket, _) = listener.accept().await.unwrap();
+			for (expect, reply) in exchanges {
+				let mut got = vec![0u8; expect.len()];
+				socket.read_exact(&mut got).await.unwrap();
+				assert_eq!(
+					String::from_utf8_lossy(&got),
+					String::from_utf8_lossy(&expect)
+				);
+				socket.write_all(&reply).await.unwrap();
+			}
+			socket.write_all(b"SSH-").await.unwrap();
+		});
+		port
+	}
+
+	async fn through(proxy: &UpstreamProxy, host: &str) -> Result<[u8; 4], SshError> {
+		let mut socket = TcpStream::connect(("127.0.0.1", proxy.port)).await.unwrap();
+		connect(proxy, &mut socket, host, 22).await?;
+		let mut banner = [0u8; 4];
+		socket.read_exact(&mut banner).await.unwrap();
+		Ok(banner)
+	}
+
+	#[tokio::test]
+	async fn http_connect_with_basic_auth() {
+		let port = stand_in(vec![(
+			b"CONNECT example.com:22 HTTP/1.1\r\nHost: example.com:22\r\n\
+			  Proxy-Authorization: Basic dTpw\r\n\r\n"
+				.to_vec(),
+			b"HTTP/1.1 200 Connection established\r\nVia: test\r\n\r\n".to_vec(),
+		)])
+		.await;
+		let p = proxy(ProxyKind::HttpConnect, port, Some(("u", "p")));
+		// The banner right after the head must not be swallowed.
+		assert_eq!(&through(&p, "example.com").await.unwrap(), b"SSH-");
+	}
+
+	#[tokio::test]
+	async fn http_connect_refusal_is_a_proxy_error() {
+		let port = stand_in(vec![(
+			b"CONNECT [::1]:22 HTTP/1.1\r\nHost: [::1]:22\r\n\r\n".to_vec(),
+			b"HTTP/1.1 407 Proxy Authentication Required\r\n\r\n".to_vec(),
+		)])
+		.await;
+		let p = proxy(ProxyKind::HttpConnect, port, None);
+		let err = through(&p, "::1").await.unwrap_err();
+		assert!(
+			matches!(&err, SshError::Proxy(m) if m.contains("authentication required")),
+			"{err}"
+		);
+	}
+
+	#[tokio::test]
+	async fn socks5_with_username_password() {
+		let mut request = vec![5, 1, 0, 3, 11];
+		request.extend_from_slice(b"example.com");
+		request.extend_from_slice(&22u16.to_be_bytes());
+		let port = stand_in(vec![
+			(vec![5, 2, 0, 2], vec![5, 2]),
+			(vec![1, 1, b'u', 2, b'p', b'w'], vec![1, 0]),
+			(request, vec![5, 0, 0, 1, 10, 0, 0, 1, 0, 22]),
+		])
+		.await;
+		let p = proxy(ProxyKind::Socks5, port, Some(("u", "pw")));
+		assert_eq!(&through(&p, "example.com").await.unwrap(), b"SSH-");
+	}
+
+	#[tokio::test]
+	async fn socks5_refusal_names_the_reply() {
+		let port = stand_in(vec![
+			(vec![5, 1, 0], vec![5, 0]),
+			(vec![5, 1, 0, 1, 10, 0, 0, 7, 0, 22], vec![5, 5, 0, 1]),
+		])
+		.await;
+		let p = proxy(ProxyKind::Socks5, port, None);
+		let err = through(&p, "10.0.0.7").await.unwrap_err();
+		assert!(
+			matches!(&err, SshError::Proxy(m) if m.contains("connection refused")),
+			"{err}"
+		);
+	}
+}
@@ -0,0 +1,531 @@
+//! The SCP protocol (the `rcp` one `scp` speaks over an `exec` channel), for
+//! hosts that allow `scp` but have no `sftp` subsystem.
+//!
+//! Uploading runs `scp -t <target>` remotely and plays the *source*; downloading
+//! runs `scp -f <path>` and plays the *sink*. Each side sends control lines —
+//! `C<mode> <size> <name>` for a file (its bytes follow), `D<mode> 0 <name>` /
+//! `E` around a directory's contents, `T<mtime> 0 <atime> 0` before either with
+//! `-p` — and answers each with an ack byte: 0, or 1/2 and a message line for a
+//! warning/fatal error.
+//!
+//! There are no offsets: an interrupted copy can only start over. Names from
+//! the server are refused if they could escape the target (`/`, `.`, `..`).
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use futures::future::BoxFuture;
+use tokio::fs::{self, File};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+use crate::utils::SshError;
+
+/// Chunk size for file bytes.
+const CHUNK_SIZE: usize = 32 * 1024;
+
+/// Longest control line accepted from the peer.
+const MAX_LINE: u64 = 8 * 1024;
+
+/// `scp` flags.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScpOptions {
+	/// `-r`: copy directories and their contents.
+	pub recursive: bool,
+	/// `-p`: carry modification/access times and modes across.
+	pub preserve: bool,
+}
+
+impl ScpOptions {
+	/// The remote command for `mode` (`t` to receive, `f` to send) on `path`.
+	pub(crate) fn command(self, mode: char, path: &str) -> String {
+		let mut command = String::from("scp");
+		if self.recursive {
+			command.push_str(" -r");
+		}
+		if self.preserve {
+			command.push_str(" -p");
+		}
+		// Not every `scp` takes `--`; keep a leading `-` from reading as a flag.
+		let path = if path.starts_with('-') {
+			format!("./{path}")
+		} else {
+			path.to_string()
+		};
+		format!(
+			"{command} -{mode} {}",
+			crate::shell_integration::quote(&path)
+		)
+	}
+}
+
+/// Play the source: send `local` (a file, or with `recursive` a directory) to a
+/// remote `scp -t`. Returns the file bytes sent; `progress` gets the running
+/// total after each chunk.
+pub(crate) async fn send<S>(
+	stream: &mut S,
+	local: &Path,
+	options: ScpOptions,
+	progress: &(dyn Fn(u64) + Sync),
+) -> Result<u64, SshError>
+where
+	S: AsyncBufRead + AsyncWrite + Unpin + Send,
+{
+	read_ack(stream).await?;
+	let mut sent = 0;
+	send_entry(stream, local, options, &mut sent, progress).await?;
+	stream.shutdown().await?;
+	Ok(sent)
+}
+
+fn send_entry<'a, S>(
+	stream: &'a mut S,
+	path: &'a Path,
+	options: ScpOptions,
+	sent: &'a mut u64,
+	progress: &'a (dyn Fn(u64) + Sync),
+) -> BoxFuture<'a, Result<(), SshError>>
+where
+	S: AsyncBufRead + AsyncWrite + Unpin + Send,
+{
+	Box::pin(async move {
+		let meta = fs::metadata(path).await?;
+		let name = path
+			.file_name()
+			.and_then(|n| n.to_str())
+			.ok_or_else(|| SshError::Scp(format!("no file name in {}", path.display())))?;
+		if options.preserve {
+			let line = format!("T{} 0 {} 0\n", secs(meta.modified()), secs(meta.accessed()));
+			send_line(stream, &line).await?;
+		}
+		if meta.is_dir() {
+			if !options.recursive {
+				return Err(SshError::Scp(format!("{} is a directory", path.display())));
+			}
+			send_line(stream, &format!("D{:04o} 0 {name}\n", mode_of(&meta))).await?;
+			let mut children = Vec::new();
+			let mut dir = fs::read_dir(path).await?;
+			while let Some(entry) = dir.next_entry().await? {
+				children.push(entry.path());
+			}
+			children.sort();
+			for child in &children {
+				send_entry(stream, child, options, sent, progress).await?;
+			}
+			send_line(stream, "E\n").await
+		} else {
+			let size = meta.len();
+			let line = format!("C{:04o} {size} {name}\n", mode_of(&meta));
+			send_line(stream, &line).await?;
+			let mut file = File::open(path).await?;
+			let mut buf = vec![0u8; CHUNK_SIZE];
+			let mut left = size;
+			while left > 0 {
+				let want = buf.len().min(usize::try_from(left).unwrap_or(usize::MAX));
+				let n = file.read(&mut buf[..want]).await?;
+				if n == 0 {
+					return Err(SshError::Scp(format!(
+						"{} shrank while sending",
+						path.display()
+					)));
+				}
+				stream.write_all(&buf[..n]).await?;
+				left -= n as u64;
+				*sent += n as u64;
+				progress(*sent);
+			}
+			stream.write_all(&[0]).await?;
+			stream.flush().await?;
+			read_ack(stream).await
+		}
+	})
+}
+
+/// Play the sink: receive what a remote `scp -f` sends into `local`. As with
+/// `scp`, an existing directory at `local` receives the top entry by name;
+/// otherwise the entry is written at `local` itself. Returns the file bytes
+/// received; `progress` as for [`send`].
+pub(crate) async fn receive<S>(
+	stream: &mut S,
+	local: &Path,
+	options: ScpOptions,
+	progress: &(dyn Fn(u64) + Sync),
+) -> Result<u64, SshError>
+where
+	S: AsyncBufRead + AsyncWrite + Unpin + Send,
+{
+	let into_dir = fs::metadata(local).await.is_ok_and(|m| m.is_dir());
+	let target = |dirs: &[(PathBuf, Option<(u64, u64)>)], name: &str| match dirs.last() {
+		Some((dir, _)) => dir.join(name),
+		None if into_dir => local.join(name),
+		None => local.to_path_buf(),
+	};
+	// Open directories, with the times to give each once its `E` arrives.
+	let mut dirs = Vec::new();
+	let mut times = None;
+	let mut warning = None;
+	let mut received = 0;
+	ack(stream).await?;
+	while let Some(line) = read_line(stream).await? {
+		// Every kind byte is ASCII, so `rest` starts on a char boundary.
+		let kind = line.as_bytes().first().copied();
+		let rest = line.get(1..).unwrap_or_default();
+		match kind {
+			Some(b'\x01') => warning = Some(rest.to_string()),
+			Some(b'\x02') => return Err(SshError::Scp(rest.to_string())),
+			Some(b'T') => times = Some(parse_times(rest)?),
+			Some(b'D') => {
+				let (mode, _, name) = parse_entry(rest)?;
+				let dir = target(&dirs, name);
+				match fs::create_dir(&dir).await {
+					Err(e) if e.kind() != std::io::ErrorKind::AlreadyExists => return Err(e.into()),
+					_ => {}
+				}
+				if options.preserve {
+					set_mode(&dir, mode).await?;
+				}
+				dirs.push((dir, times.take()));
+			}
+			Some(b'E') => {
+				let (dir, dir_times) = dirs
+					.pop()
+					.ok_or_else(|| SshError::Scp("unbalanced E".into()))?;
+				if let Some((mtime, atime)) = dir_times {
+					set_times(&dir, mtime, atime).await?;
+				}
+			}
+			Some(b'C') => {
+				let (mode, size, name) = parse_entry(rest)?;
+				let path = target(&dirs, name);
+				ack(stream).await?;
+				let mut file = File::create(&path).await?;
+				let mut buf = vec![0u8; CHUNK_SIZE];
+				let mut left = size;
+				while left > 0 {
+					let want = buf.len().min(usize::try_from(left).unwrap_or(usize::MAX));
+					let n = stream.read(&mut buf[..want]).await?;
+					if n == 0 {
+						return Err(SshError::Scp(format!("{name}: stream ended early")));
+					}
+					file.write_all(&buf[..n]).await?;
+					left -= n as u64;
+					received += n as u64;
+					progress(received);
+				}
+				file.sync_all().await?;
+				drop(file);
+				read_ack(stream).await?;
+				if options.preserve {
+					set_mode(&path, mode).await?;
+				}
+				if let Some((mtime, atime)) = times.take() {
+					set_times(&path, mtime, atime).await?;
+				}
+			}
+			_ => return Err(SshError::Scp(format!("unexpected line {line:?}"))),
+		}
+		// A warning line is the peer's ack for nothing: don't answer it.
+		if kind != Some(b'\x01') {
+			ack(stream).await?;
+		}
+	}
+	match warning {
+		Some(message) => Err(SshError::Scp(message)),
+		None => Ok(received),
+	}
+}
+
+async fn send_line<S>(stream: &mut S, line: &str) -> Result<(), SshError>
+where
+	S: AsyncBufRead + AsyncWrite + Unpin,
+{
+	stream.write_all(line.as_bytes()).await?;
+	stream.flush().await?;
+	read_ack(stream).await
+}
+
+async fn ack<S: AsyncWrite + Unpin>(stream: &mut S) -> Result<(), SshError> {
+	stream.write_all(&[0]).await?;
+	stream.flush().await?;
+	Ok(())
+}
+
+/// Read the peer's answer to what we just sent.
+async fn read_ack<S: AsyncBufRead + Unpin>(stream: &mut S) -> Result<(), SshError> {
+	match stream.read_u8().await? {
+		0 => Ok(()),
+		1 | 2 => {
+			let message = read_line(stream).await?.unwrap_or_default();
+			Err(SshError::Scp(message))
+		}
+		other => Err(SshError::Scp(format!("unexpected ack byte {other}"))),
+	}
+}
+
+/// One control line without its `\n`; `None` at a clean end of stream.
+async fn read_line<S: AsyncBufRead + Unpin>(stream: &mut S) -> Result<Option<String>, SshError> {
+	let mut line = Vec::new();
+	(&mut *stream)
+		.take(MAX_LINE)
+		.read_until(b'\n', &mut line)
+		.await?;
+	if line.is_empty() {
+		return Ok(None);
+	}
+	if line.pop() != Some(b'\n') {
+		return Err(SshError::Scp("control line too long or cut off".into()));
+	}
+	String::from_utf8(line)
+		.map(Some)
+		.map_err(|_| SshError::Scp("control line is not UTF-8".into()))
+}
+
+/// `<mode> <size> <name>` of a `C`/`D` line.
+fn parse_entry(rest: &str) -> Result<(u32, u64, &str), SshError> {
+	let bad = || SshError::Scp(format!("malformed entry {rest:?}"));
+	let mut parts = rest.splitn(3, ' ');
+	let mode = u32::from_str_radix(parts.next().ok_or_else(bad)?, 8).map_err(|_| bad())?;
+	let size = parts.next().ok_or_else(bad)?.parse().map_err(|_| bad())?;
+	let name = parts.next().ok_or_else(bad)?;
+	if name.is_empty() || name == "." || name == ".." || name.contains('/') {
+		return Err(SshError::Scp(format!("refusing entry name {name:?}")));
+	}
+	Ok((mode & 0o7777, size, name))
+}
+
+/// `<mtime> 0 <atime> 0` of a `T` line.
+fn parse_times(rest: &str) -> Result<(u64, u64), SshError> {
+	let fields: Vec<&str> = rest.split(' ').collect();
+	match fields[..] {
+		[mtime, _, atime, _] => match (mtime.parse(), atime.parse()) {
+			(Ok(mtime), Ok(atime)) => Ok((mtime, atime)),
+			_ => Err(SshError::Scp(format!("malformed times {rest:?}"))),
+		},
+		_ => Err(SshError::Scp(format!("malformed times {rest:?}"))),
+	}
+}
+
+fn secs(time: std::io::Result<SystemTime>) -> u64 {
+	time.ok()
+		.and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+		.map_or(0, |d| d.as_secs())
+}
+
+#[cfg(unix)]
+fn mode_of(meta: &std::fs::Metadata) -> u32 {
+	use std::os::unix::fs::PermissionsExt;
+	meta.permissions().mode() & 0o7777
+}
+
+#[cfg(not(unix))]
+fn mode_of(meta: &std::fs::Metadata) -> u32 {
+	if meta.is_dir() {
+		0o755
+	} else {
+		0o644
+	}
+}
+
+#[cfg(unix)]
+async fn set_mode(path: &Path, mode: u32) -> Result<(), SshError> {
+	use std::os::unix::fs::PermissionsExt;
+	fs::set_permissions(path, std::fs::Permissions::from_mode(mode)).await?;
+	Ok(())
+}
+
+#[cfg(not(unix))]
+async fn set_mode(_path: &Path, _mode: u32) -> Result<(), SshError> {
+	Ok(())
+}
+
+async fn set_times(path: &Path, mtime: u64, atime: u64) -> Result<(), SshError> {
+	let at = |secs| UNIX_EPOCH + Duration::from_secs(secs);
+	let times = std::fs::FileTimes::new()
+		.set_modified(at(mtime))
+		.set_accessed(at(atime));
+	let file = File::open(path).await?.into_std().await;
+	tokio::task::spawn_blocking(move || file.set_times(times))
+		.await
+		.map_err(|e| SshError::Scp(e.to_string()))??;
+	Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+	use tokio::io::{duplex, AsyncRead, BufReader, DuplexStream};
+
+	use super::*;
+
+	/// A scratch directory under the system temp dir, removed on drop.
+	struct Scratch(PathBuf);
+
+	impl Scratch {
+		fn new(tag: &str) -> Self {
+			let dir = std::env::temp_dir().join(format!("fressh-scp-{tag}-{}", std::process::id()));
+			let _ = std::fs::remove_dir_all(&dir);
+			std::fs::create_dir_all(&dir).unwrap();
+			Scratch(dir)
+		}
+	}
+
+	impl Drop for Scratch {
+		fn drop(&mut self) {
+			let _ = std::fs::remove_dir_all(&self.0);
+		}
+	}
+
+	async fn expect(peer: &mut (impl AsyncRead + Unpin), want: &[u8]) {
+		let mut got = vec![0; want.len()];
+		peer.read_exact(&mut got).await.unwrap();
+		assert_eq!(String::from_utf8_lossy(&got), String::from_utf8_lossy(want));
+	}
+
+	fn pair() -> (BufReader<DuplexStream>, DuplexStream) {
+		let (ours, peer) = duplex(64 * 1024);
+		(BufReader::new(ours), peer)
+	}
+
+	#[test]
+	fn builds_the_remote_command() {
+		let options = ScpOptions {
+			recursive: true,
+			preserve: true,
+		};
+		assert_eq!(options.command('t', "/srv/a b"), "scp -r -p -t '/srv/a b'");
+		assert_eq!(
+			ScpOptions::default().command('f', "-odd"),
+			"scp -f './-odd'"
+		);
+	}
+
+	#[tokio::test]
+	async fn sends_a_directory_tree() {
+		let scratch = Scratch::new("send");
+		let root = scratch.0.join("tree");
+		std::fs::create_dir_all(root.join("sub")).unwrap();
+		std::fs::write(root.join("a.txt"), b"hello").unwrap();
+		std::fs::write(root.join("sub/b.bin"), b"xy").unwrap();
+
+		let (mut ours, mut peer) = pair();
+		let options = ScpOptions {
+			recursive: true,
+			preserve: false,
+		};
+		let sender = tokio::spawn(async move { send(&mut ours, &root, options, &|_| {}).await });
+		peer.write_all(&[0]).await.unwrap();
+		for (expected, body) in [
+			(&b"D0"[..], None),
+			(b"C0", Some(&b"hello"[..])),
+			(b"D0", None),
+			(b"C0", Some(b"xy")),
+		] {
+			let mut line = Vec::new();
+			loop {
+				let byte = peer.read_u8().await.unwrap();
+				line.push(byte);
+				if byte == b'\n' {
+					break;
+				}
+			}
+			assert!(line.starts_with(expected), "{line:?}");
+			peer.write_all(&[0]).await.unwrap();
+			if let Some(body) = body {
+				expect(&mut peer, body).await;
+				expect(&mut peer, &[0]).await;
+				peer.write_all(&[0]).await.unwrap();
+			}
+		}
+		expect(&mut peer, b"E\n").await;
+		peer.write_all(&[0]).await.unwrap();
+		expect(&mut peer, b"E\n").await;
+		peer.write_all(&[0]).await.unwrap();
+		assert_eq!(sender.await.unwrap().unwrap(), 7);
+	}
+
+	#[tokio::test]
+	async fn receives_files_modes_and_times() {
+		let scratch = Scratch::new("receive");
+		let local = scratch.0.clone();
+		let (mut ours, mut peer) = pair();
+		let options = ScpOptions {
+			recursive: true,
+			preserve: true,
+		};
+		let receiver = tokio::spawn(async move {
+			let seen = std::sync::Mutex::new(Vec::new());
+			let total = receive(&mut ours, &local, options, &|n| {
+				seen.lock().unwrap().push(n)
+			})
+			.await;
+			(total, seen.into_inner().unwrap())
+		});
+		expect(&mut peer, &[0]).await;
+		for line in [&b"D0750 0 proj\n"[..], b"T1700000000 0 1700000001 0\n"] {
+			peer.write_all(line).await.unwrap();
+			expect(&mut peer, &[0]).await;
+		}
+		peer.write_all(b"C0640 3 run.sh\n").await.unwrap();
+		expect(&mut peer, &[0]).await;
+		peer.write_all(b"abc\0").await.unwrap();
+		expect(&mut peer, &[0]).await;
+		peer.write_all(b"E\n").await.unwrap();
+		expect(&mut peer, &[0]).await;
+		drop(peer);
+
+		let (total, seen) = receiver.await.unwrap();
+		assert_eq!(total.unwrap(), 3);
+		assert_eq!(seen, [3]);
+		let file = scratch.0.join("proj/run.sh");
+		assert_eq!(std::fs::read(&file).unwrap(), b"abc");
+		let meta = std::fs::metadata(&file).unwrap();
+		assert_eq!(secs(meta.modified()), 1_700_000_000);
+		#[cfg(unix)]
+		assert_eq!(mode_of(&meta), 0o640);
+	}
+
+	#[tokio::test]
+	async fn refuses_names_that_escape_the_target() {
+		let scratch = Scratch::new("escape");
+		let local = scratch.0.clone();
+		let (mut ours, mut peer) = pair();
+		let receiver = tokio::spawn(async move {
+			receive(&mut ours, &local, ScpOptions::default(), &|_| {}).await
+		});
+		expect(&mut peer, &[0]).await;
+		peer.write_all(b"C0644 1 ../evil\n").await.unwrap();
+		let err = receiver.await.unwrap().unwrap_err();
+		assert!(matches!(err, SshError::Scp(m) if m.contains("../evil")));
+	}
+
+	#[tokio::test]
+	async fn rejects_empty_and_non_ascii_lines() {
+		for line in [&b"\n"[..], "é0644 1 x\n".as_bytes()] {
+			let scratch = Scratch::new("garbage");
+			let local = scratch.0.clone();
+			let (mut ours, mut peer) = pair();
+			let receiver = tokio::spawn(async move {
+				receive(&mut ours, &local, ScpOptions::default(), &|_| {}).await
+			});
+			expect(&mut peer, &[0]).await;
+			peer.write_all(line).await.unwrap();
+			let err = receiver.await.unwrap().unwrap_err();
+			assert!(matches!(err, SshError::Scp(m) if m.starts_with("unexpected line")));
+		}
+	}
+
+	#[tokio::test]
+	async fn surfaces_remote_errors() {
+		let scratch = Scratch::new("error");
+		let local = scratch.0.join("missing.txt");
+		let (mut ours, mut peer) = pair();
+		let receiver = tokio::spawn(async move {
+			receive(&mut ours, &local, ScpOptions::default(), &|_| {}).await
+		});
+		expect(&mut peer, &[0]).await;
+		peer.write_all(b"\x01scp: /nope: No such file or directory\n")
+			.await
+			.unwrap();
+		drop(peer);
+		let err = receiver.await.unwrap().unwrap_err();
+		assert!(matches!(err, SshError::Scp(m) if m.ends_with("No such file or directory")));
+	}
+}
@@ -13,6 +13,8 @@ use bytes::Bytes;
 use russh::{client, ChannelMsg, ChannelWriteHalf};
 use tokio::sync::Mutex as AsyncMutex;
 
+use crate::exec::signal_name_of;
+use crate::shell_integration::EnvReport;
 use crate::utils::SshError;
 
 // Note: russh accepts an untyped string for the terminal type.
@@ -83,6 +85,18 @@ pub struct StartShellOptions {
 	/// falls back to a plain login shell for shells it can't inject. See
 	/// [`crate::shell_integration`]. Off ⇒ behave like a plain SSH client.
 	pub shell_integration: bool,
+	/// Variables the injected integration reports back as they change (e.g.
+	/// `VIRTUAL_ENV`), so commands run in the shell's context see them too.
+	/// Ignored without `shell_integration`.
+	pub report_env: Vec<String>,
+	/// Variables to set in the shell (`LANG`, `LC_*`, …), sent as `env`
+	/// requests. sshd drops any its `AcceptEnv` doesn't list; with
+	/// `shell_integration` those are exported by the bootstrap instead.
+	pub env: Vec<(String, String)>,
+	/// A program to run on the PTY instead of an interactive shell (`tmux
+	/// attach`, `htop`), through the user's shell as `ssh host cmd` would. The
+	/// channel closes when it exits.
+	pub command: Option<String>,
 }
 
 impl Default for StartShellOptions {
@@ -93,6 +107,9 @@ impl Default for StartShellOptions {
 			terminal_size: None,
 			terminal_pixel_size: None,
 			shell_integration: true,
+			report_env: Vec::new(),
+			env: Vec::new(),
+			command: None,
 		}
 	}
 }
@@ -114,15 +131,45 @@ pub static DEFAULT_TERM_COL_WIDTH: u32 = 80;
 pub static DEFAULT_TERM_PIXEL_WIDTH: u32 = 0;
 pub static DEFAULT_TERM_PIXEL_HEIGHT: u32 = 0;
 
+/// How the server said a shell (or its [`StartShellOptions::command`]) ended:
+/// `exit-status` for a normal exit, `exit-signal` when it was killed. All empty
+/// when the channel closed without either, e.g. a dropped connection.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ShellExit {
+	pub status: Option<u32>,
+	/// The signal's name without the `SIG` prefix.
+	pub signal: Option<String>,
+	pub core_dumped: bool,
+	/// The message sent with the signal, if not empty.
+	pub error_message: Option<String>,
+}
+
+impl ShellExit {
+	/// Whether the server reported an exit at all.
+	pub fn is_reported(&self) -> bool {
+		self.status.is_some() || self.signal.is_some()
+	}
+}
+
 /// The read half of a shell channel. Call [`recv`](ShellReader::recv) in a loop
 /// until it returns `None` (channel closed).
 pub struct ShellReader {
 	inner: russh::ChannelReadHalf,
+	exit: ShellExit,
 }
 
 impl ShellReader {
 	pub(crate) fn new(inner: russh::ChannelReadHalf) -> Self {
-		Self { inner }
+		Self {
+			inner,
+			exit: ShellExit::default(),
+		}
+	}
+
+	/// What the server has said about the shell's exit so far; complete once
+	/// [`recv`](Self::recv) returned `None`.
+	pub fn exit(&self) -> &ShellExit {
+		&self.exit
 	}
 
 	/// Await the next chunk of shell output. Returns `None` once the channel is
@@ -142,6 +189,19 @@ impl ShellReader {
 						bytes: Bytes::copy_from_slice(&data),
 					});
 				}
+				Some(ChannelMsg::ExitStatus { exit_status }) => {
+					self.exit.status = Some(exit_status);
+				}
+				Some(ChannelMsg::ExitSignal {
+					signal_name,
+					core_dumped,
+					error_message,
+					..
+				}) => {
+					self.exit.signal = Some(signal_name_of(&signal_name));
+					self.exit.core_dumped = core_dumped;
+					self.exit.error_message = Some(error_message).filter(|m| !m.is_empty());
+				}
 				Some(ChannelMsg::Close) | None => return None,
 				Some(_) => continue,
 			}
@@ -197,6 +257,12 @@ pub struct Shell {
 	pub channel_id: u32,
 	pub term: TerminalType,
 	pub created_at_ms: f64,
+	/// Names from [`StartShellOptions::env`] the server refused. Exported by
+	/// the shell-integration bootstrap instead if that is on; unset otherwise.
+	pub rejected_env: Vec<String>,
+	/// The nonce and names the injected integration reports env with; `None`
+	/// when integration is off (no env report is genuine then).
+	pub env_report: Option<EnvReport>,
 	pub reader: ShellReader,
 	pub writer: ShellWriter,
 }
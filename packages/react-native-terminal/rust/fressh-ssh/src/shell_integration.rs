@@ -46,7 +46,7 @@ fn b64(s: &str) -> String {
 }
 
 /// A fresh per-session nonce (hex). VS Code's scripts embed it in `633;E`/env
-/// entries; a future scanner check can use it to reject spoofed sequences.
+/// entries; the scanner drops env reports that don't carry it (see [`EnvReport`]).
 pub fn generate_nonce() -> String {
 	let mut rng = rand::thread_rng();
 	(0..16)
@@ -55,18 +55,56 @@ pub fn generate_nonce() -> String {
 }
 
 /// Build the SSH `exec` command that launches an interactive shell with OSC 633
-/// shell integration injected. Pass the result to `Channel::exec`.
+/// shell integration injected. Pass the result to `Channel::exec`. The scripts
+/// report the variables named in `report_env` back (`633;EnvSingle*`); names
+/// that aren't plain identifiers are left out. `env` is exported first — the
+/// variables the server wouldn't take as `env` requests. With a `command`, the
+/// shell runs just that (`-c`) instead of prompting, and exits with it.
 ///
 /// INVARIANT: the inner bootstrap must contain no `'` (single quote) so it nests
-/// inside `sh -c '…'`. The base64 payloads and the POSIX body below honor that.
-pub fn build_exec_command(nonce: &str) -> String {
+/// inside `sh -c '…'`. The base64 payloads and the POSIX body below honor that;
+/// `env`'s values and `command`, which may hold anything, go in base64-encoded
+/// too.
+pub fn build_exec_command(
+	nonce: &str,
+	report_env: &[String],
+	env: &[(String, String)],
+	command: Option<&str>,
+) -> String {
+	// `c` holds the command; `run` hands it to the integrated shells, `plain`
+	// to the fallback (`$SHELL -c`, as sshd runs an exec).
+	let (command, run, plain) = match command {
+		Some(command) => (
+			format!("c=$(printf %s {} | base64 -d); ", b64(command)),
+			" -c \"$c\"",
+			"-c \"$c\"",
+		),
+		None => (String::new(), "", "-l"),
+	};
+	let exports = export_script(env);
+	let exports = if exports.is_empty() {
+		String::new()
+	} else {
+		format!("eval \"$(printf %s {} | base64 -d)\"; ", b64(&exports))
+	};
+	let report: Vec<&str> = report_env
+		.iter()
+		.map(String::as_str)
+		.filter(|name| is_env_name(name))
+		.collect();
+	let report = if report.is_empty() {
+		String::new()
+	} else {
+		format!("export VSCODE_SHELL_ENV_REPORTING={}; ", report.join(","))
+	};
 	// One `printf … | base64 -d > file` per script. zsh needs the four-file
 	// ZDOTDIR dance; bash and fish need one file each.
 	let bootstrap = format!(
 		concat!(
+			"{exports}{command}",
 			// A temp dir to hold the materialized scripts; bail to a plain login
 			// shell if we can't make one (read-only /tmp, no mktemp, …).
-			"d=$(mktemp -d \"${{TMPDIR:-/tmp}}/.fressh.XXXXXX\" 2>/dev/null) || exec \"${{SHELL:-/bin/sh}}\" -l; ",
+			"d=$(mktemp -d \"${{TMPDIR:-/tmp}}/.fressh.XXXXXX\" 2>/dev/null) || exec \"${{SHELL:-/bin/sh}}\" {plain}; ",
 			"printf %s {bash} | base64 -d > \"$d/bash\" 2>/dev/null; ",
 			"printf %s {zrc} | base64 -d > \"$d/.zshrc\" 2>/dev/null; ",
 			"printf %s {zenv} | base64 -d > \"$d/.zshenv\" 2>/dev/null; ",
@@ -74,18 +112,19 @@ pub fn build_exec_command(nonce: &str) -> String {
 			"printf %s {zlogin} | base64 -d > \"$d/.zlogin\" 2>/dev/null; ",
 			"printf %s {fish} | base64 -d > \"$d/fish\" 2>/dev/null; ",
 			"export VSCODE_INJECTION=1 VSCODE_NONCE={nonce} VSCODE_STABLE=1; ",
+			"{report}",
 			"case \"${{SHELL##*/}}\" in ",
 			// bash: --init-file sources our script; VSCODE_SHELL_LOGIN makes it
 			// replay the login chain (/etc/profile → bash_profile/…).
-			"bash) export VSCODE_SHELL_LOGIN=1; exec bash --init-file \"$d/bash\" -i ;; ",
+			"bash) export VSCODE_SHELL_LOGIN=1; exec bash --init-file \"$d/bash\" -i{run} ;; ",
 			// zsh: point ZDOTDIR at our dir (the four files restore USER_ZDOTDIR
 			// and source the user's real rc). -i -l = interactive login.
-			"zsh) export USER_ZDOTDIR=\"${{ZDOTDIR:-$HOME}}\" ZDOTDIR=\"$d\"; exec zsh -i -l ;; ",
+			"zsh) export USER_ZDOTDIR=\"${{ZDOTDIR:-$HOME}}\" ZDOTDIR=\"$d\"; exec zsh -i -l{run} ;; ",
 			// fish: --init-command sources our script after fish's own config.
-			"fish) exec fish -l -C \"source $d/fish\" ;; ",
+			"fish) exec fish -l -C \"source $d/fish\"{run} ;; ",
 			// unknown shell: clean up and launch it plainly. Integration just stays
 			// inactive; the connection is unaffected.
-			"*) rm -rf \"$d\" 2>/dev/null; exec \"${{SHELL:-/bin/sh}}\" -l ;; ",
+			"*) rm -rf \"$d\" 2>/dev/null; exec \"${{SHELL:-/bin/sh}}\" {plain} ;; ",
 			"esac"
 		),
 		bash = b64(BASH),
@@ -95,10 +134,83 @@ pub fn build_exec_command(nonce: &str) -> String {
 		zlogin = b64(ZSH_LOGIN),
 		fish = b64(FISH),
 		nonce = nonce,
+		report = report,
+		exports = exports,
+		command = command,
+		run = run,
+		plain = plain,
 	);
 	format!("sh -c '{bootstrap}'")
 }
 
+/// Build an `exec` command that runs `command` in `cwd` with `env` exported —
+/// the context a live shell reported — through the user's `$SHELL`, like a
+/// plain `exec` would. Without a cwd or env, `command` is returned as is.
+///
+/// The `cd`/`export` script is POSIX-quoted, then shipped base64-encoded to an
+/// `sh -c 'eval …'` — like the bootstrap, so the login shell (maybe fish or
+/// csh, with their own quoting rules) only ever sees a quote-free string.
+pub fn build_context_command(cwd: Option<&str>, env: &[(String, String)], command: &str) -> String {
+	if cwd.is_none() && env.is_empty() {
+		return command.to_string();
+	}
+	let mut script = String::new();
+	if let Some(cwd) = cwd {
+		script.push_str(&format!("cd {} || exit 1; ", quote(cwd)));
+	}
+	script.push_str(&export_script(env));
+	script.push_str(&format!(
+		"exec \"${{SHELL:-/bin/sh}}\" -c {}",
+		quote(command)
+	));
+	format!("sh -c 'eval \"$(printf %s {} | base64 -d)\"'", b64(&script))
+}
+
+/// Single-quote `s` for a POSIX shell: each `'` becomes `'\''`.
+pub(crate) fn quote(s: &str) -> String {
+	format!("'{}'", s.replace('\'', r"'\''"))
+}
+
+/// `export NAME='value'; ` per variable, skipping names that aren't plain
+/// identifiers.
+fn export_script(env: &[(String, String)]) -> String {
+	env.iter()
+		.filter(|(name, _)| is_env_name(name))
+		.map(|(name, value)| format!("export {name}={}; ", quote(value)))
+		.collect()
+}
+
+/// What an injected shell was asked to report back. Anything printed to the
+/// terminal (a `cat`-ed file, a remote program) can forge a `633;EnvSingleEntry`,
+/// so the scanner keeps only entries carrying `nonce` for one of `names`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EnvReport {
+	pub nonce: String,
+	/// The requested names the bootstrap actually asks for (valid identifiers).
+	pub names: Vec<String>,
+}
+
+impl EnvReport {
+	pub fn new(nonce: String, report_env: &[String]) -> Self {
+		Self {
+			nonce,
+			names: report_env
+				.iter()
+				.filter(|name| is_env_name(name))
+				.cloned()
+				.collect(),
+		}
+	}
+}
+
+fn is_env_name(name: &str) -> bool {
+	let mut chars = name.chars();
+	chars
+		.next()
+		.is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+		&& chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
 #[cfg(test)]
 mod tests {
 	use super::*;
@@ -106,7 +218,12 @@ mod tests {
 	#[test]
 	fn bootstrap_has_no_single_quote_inside_wrapper() {
 		// The whole point of the sh -c '…' wrapping: the body must be quote-free.
-		let cmd = build_exec_command("abc123");
+		let cmd = build_exec_command(
+			"abc123",
+			&["LANG".into()],
+			&[("TEAM_ENV".into(), "it's".into())],
+			Some("tmux attach -t 'main'"),
+		);
 		let inner = cmd
 			.strip_prefix("sh -c '")
 			.and_then(|s| s.strip_suffix('\''))
@@ -119,7 +236,7 @@ mod tests {
 
 	#[test]
 	fn bootstrap_dispatches_each_known_shell() {
-		let cmd = build_exec_command("n");
+		let cmd = build_exec_command("n", &[], &[], None);
 		for needle in [
 			"bash --init-file",
 			"ZDOTDIR=",
@@ -132,6 +249,80 @@ mod tests {
 		}
 	}
 
+	#[test]
+	fn bootstrap_reports_only_identifier_env_names() {
+		let cmd = build_exec_command(
+			"n",
+			&["VIRTUAL_ENV".into(), "A;rm".into(), "PATH".into()],
+			&[],
+			None,
+		);
+		assert!(cmd.contains("export VSCODE_SHELL_ENV_REPORTING=VIRTUAL_ENV,PATH; "));
+		assert!(!build_exec_command("n", &[], &[], None).contains("VSCODE_SHELL_ENV_REPORTING"));
+	}
+
+	#[test]
+	fn bootstrap_runs_a_command_in_every_branch() {
+		let cmd = build_exec_command("n", &[], &[], Some("htop"));
+		let set = format!("c=$(printf %s {} | base64 -d); ", b64("htop"));
+		assert!(cmd.starts_with(&format!("sh -c '{set}")), "{cmd}");
+		for needle in [
+			"-i -c \"$c\" ;; ",
+			"-i -l -c \"$c\" ;; ",
+			"fish\" -c \"$c\" ;; ",
+			"|| exec \"${SHELL:-/bin/sh}\" -c \"$c\"; ",
+			"exec \"${SHELL:-/bin/sh}\" -c \"$c\" ;; ",
+		] {
+			assert!(cmd.contains(needle), "missing {needle:?} in exec command");
+		}
+		assert!(!cmd.contains(" -l;") && !cmd.contains(" -l ;;"));
+	}
+
+	#[test]
+	fn bootstrap_exports_env_before_anything_else() {
+		let env = [
+			("LANG".into(), "de_DE.UTF-8".into()),
+			("bad name".into(), "x".into()),
+		];
+		let cmd = build_exec_command("n", &[], &env, None);
+		let encoded = cmd
+			.strip_prefix("sh -c 'eval \"$(printf %s ")
+			.and_then(|s| s.split_once(" | base64 -d)\"; "))
+			.expect("exports must come first")
+			.0;
+		let decoded = base64::engine::general_purpose::STANDARD
+			.decode(encoded)
+			.unwrap();
+		assert_eq!(decoded, b"export LANG='de_DE.UTF-8'; ");
+		assert!(!build_exec_command("n", &[], &[], None).contains("eval"));
+	}
+
+	#[test]
+	fn context_command_quotes_everything() {
+		let cmd = build_context_command(
+			Some("/srv/it's here"),
+			&[
+				("NODE_ENV".into(), "a b".into()),
+				("bad name".into(), "x".into()),
+			],
+			"echo '$HOME'",
+		);
+		let script = concat!(
+			r#"cd '/srv/it'\''s here' || exit 1; "#,
+			r#"export NODE_ENV='a b'; "#,
+			r#"exec "${SHELL:-/bin/sh}" -c 'echo '\''$HOME'\'''"#,
+		);
+		let encoded = cmd
+			.strip_prefix("sh -c 'eval \"$(printf %s ")
+			.and_then(|s| s.strip_suffix(" | base64 -d)\"'"))
+			.expect("must be an eval of a base64 payload");
+		let decoded = base64::engine::general_purpose::STANDARD
+			.decode(encoded)
+			.unwrap();
+		assert_eq!(String::from_utf8(decoded).unwrap(), script);
+		assert_eq!(build_context_command(None, &[], "ls"), "ls");
+	}
+
 	#[test]
 	fn nonce_is_sixteen_hex_chars() {
 		let n = generate_nonce();
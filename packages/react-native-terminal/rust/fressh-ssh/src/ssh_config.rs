@@ -0,0 +1,617 @@
+//! `ssh_config(5)` import: resolve a host alias the way `ssh` would.
+//!
+//! Users paste their desktop `~/.ssh/config`; [`SshConfig::resolve`] turns an
+//! alias into [`ConnectionDetails`] plus the options we act on — `HostName`
+//! (with `%h`), `Port`, `User`, `IdentityFile` (kept as a path: the app maps it
+//! to a stored key), `ProxyJump` (resolved hop by hop), `ServerAliveInterval` /
+//! `ServerAliveCountMax`. Everything else is parsed and ignored.
+//!
+//! Semantics follow OpenSSH: blocks apply in file order and the first value
+//! obtained for an option wins (`IdentityFile` accumulates). `Host` patterns
+//! take `*`/`?` and `!`; `Match` understands `all`, `host`, `originalhost`,
+//! `user` and `final` — criteria that need the local machine (`exec`,
+//! `localuser`, `localnetwork`, …) never match. There is no filesystem to read
+//! on a phone, so `Include` resolves against the files the app passes in, keyed
+//! by path (relative paths are under `~/.ssh/`, as in `ssh`).
+
+use std::collections::HashMap;
+
+use crate::connection::ConnectionDetails;
+use crate::utils::SshError;
+
+/// OpenSSH's limit on nested `Include`s.
+const MAX_INCLUDE_DEPTH: usize = 16;
+/// How deep `ProxyJump` chains may recurse through each other's configs.
+const MAX_JUMP_DEPTH: usize = 8;
+
+/// A hop of a resolved `ProxyJump` chain.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshConfigHop {
+	pub details: ConnectionDetails,
+	pub identity_files: Vec<String>,
+}
+
+/// A resolved host. `details.credentials` is always empty (the config holds no
+/// secrets) and `details.username` is empty when no `User` applies.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SshConfigHost {
+	pub alias: String,
+	pub details: ConnectionDetails,
+	/// `IdentityFile` paths, in order, as written (e.g. `~/.ssh/id_ed25519`).
+	pub identity_files: Vec<String>,
+	/// `ProxyJump`, outermost first — what `connect`'s `jump_hosts` expects.
+	pub jump_hosts: Vec<SshConfigHop>,
+	pub server_alive_interval: Option<u32>,
+	pub server_alive_count_max: Option<u32>,
+}
+
+#[derive(Debug, Clone)]
+enum Criterion {
+	All,
+	Final,
+	Host(String),
+	OriginalHost(String),
+	User(String),
+	/// Needs the local machine; never matches.
+	Unsupported,
+}
+
+#[derive(Debug, Clone)]
+enum Condition {
+	/// Top of the file, before any `Host`/`Match`.
+	Always,
+	Host(Vec<String>),
+	Match(Vec<(bool, Criterion)>),
+}
+
+#[derive(Debug, Clone)]
+struct Block {
+	condition: Condition,
+	/// `(lowercase keyword, args, line)`
+	options: Vec<(String, Vec<String>, usize)>,
+}
+
+/// A parsed `ssh_config`, `Include`s expanded.
+#[derive(Debug, Clone)]
+pub struct SshConfig {
+	blocks: Vec<Block>,
+}
+
+/// OpenSSH `match_pattern`: `*` matches any run, `?` any one character.
+fn wildcard_match(pattern: &[u8], name: &[u8]) -> bool {
+	match (pattern.split_first(), name.split_first()) {
+		(None, None) => true,
+		(Some((b'*', rest)), _) => {
+			wildcard_match(rest, name) || (!name.is_empty() && wildcard_match(pattern, &name[1..]))
+		}
+		(Some((b'?', rest)), Some((_, name_rest))) => wildcard_match(rest, name_rest),
+		(Some((p, rest)), Some((n, name_rest))) if p == n => wildcard_match(rest, name_rest),
+		_ => false,
+	}
+}
+
+/// A comma/space-separated pattern list with `!` negation: some positive
+/// pattern must match and no negated one may. Case-insensitive, like hostnames.
+fn match_pattern_list<'a>(patterns: impl IntoIterator<Item = &'a str>, name: &str) -> bool {
+	let name = name.to_ascii_lowercase();
+	let mut matched = false;
+	for p in patterns
+		.into_iter()
+		.flat_map(|p| p.split(','))
+		.filter(|p| !p.is_empty())
+	{
+		let (negated, p) = match p.strip_prefix('!') {
+			Some(p) => (true, p),
+			None => (false, p),
+		};
+		if wildcard_match(p.to_ascii_lowercase().as_bytes(), name.as_bytes()) {
+			if negated {
+				return false;
+			}
+			matched = true;
+		}
+	}
+	matched
+}
+
+fn config_err(line: usize, msg: impl std::fmt::Display) -> SshError {
+	SshError::Config(format!("line {line}: {msg}"))
+}
+
+/// Split a line into its lowercase keyword and arguments. Accepts `Key value`
+/// and `Key=value`; arguments may be quoted.
+fn split_line(line: &str, line_no: usize) -> Result<Option<(String, Vec<String>)>, SshError> {
+	let line = line.trim();
+	if line.is_empty() || line.starts_with('#') {
+		return Ok(None);
+	}
+	let end = line
+		.find(|c: char| c.is_whitespace() || c == '=')
+		.unwrap_or(line.len());
+	let keyword = line[..end].to_ascii_lowercase();
+	let rest = line[end..].trim_start();
+	let rest = rest.strip_prefix('=').unwrap_or(rest);
+
+	let mut args = Vec::new();
+	let mut chars = rest.chars().peekable();
+	loop {
+		while chars.next_if(|c| c.is_whitespace()).is_some() {}
+		let Some(&c) = chars.peek() else { break };
+		if c == '#' {
+			break;
+		}
+		let mut arg = String::new();
+		if c == '"' || c == '\'' {
+			chars.next();
+			loop {
+				match chars.next() {
+					Some(q) if q == c => break,
+					Some(ch) => arg.push(ch),
+					None => return Err(config_err(line_no, "unterminated quote")),
+				}
+			}
+		} else {
+			while let Some(ch) = chars.next_if(|c| !c.is_whitespace()) {
+				arg.push(ch);
+			}
+		}
+		args.push(arg);
+	}
+	Ok(Some((keyword, args)))
+}
+
+/// `Include` paths are relative to `~/.ssh/` unless absolute or `~`-rooted.
+fn include_path(path: &str) -> String {
+	if path.starts_with('/') || path.starts_with('~') {
+		path.to_string()
+	} else {
+		format!("~/.ssh/{path}")
+	}
+}
+
+fn parse_match(args: &[String], line: usize) -> Result<Vec<(bool, Criterion)>, SshError> {
+	let mut criteria = Vec::new();
+	let mut args = args.iter();
+	while let Some(arg) = args.next() {
+		let lower = arg.to_ascii_lowercase();
+		let (negated, name) = match lower.strip_prefix('!') {
+			Some(name) => (true, name),
+			None => (false, lower.as_str()),
+		};
+		let criterion = match name {
+			"all" => Criterion::All,
+			"final" => Criterion::Final,
+			"canonical" => Criterion::Unsupported,
+			_ => {
+				let value = args
+					.next()
+					.ok_or_else(|| config_err(line, format!("Match {name} needs an argument")))?
+					.clone();
+				match name {
+					"host" => Criterion::Host(value),
+					"originalhost" => Criterion::OriginalHost(value),
+					"user" => Criterion::User(value),
+					_ => Criterion::Unsupported,
+				}
+			}
+		};
+		criteria.push((negated, criterion));
+	}
+	Ok(criteria)
+}
+
+impl SshConfig {
+	/// Parse `text`. `includes` maps paths (`~/.ssh/conf.d/work`, or relative to
+	/// `~/.ssh/`) to contents for `Include`; missing files are skipped, as `ssh`
+	/// skips them.
+	pub fn parse(text: &str, includes: &HashMap<String, String>) -> Result<Self, SshError> {
+		let includes: Vec<(String, &str)> = {
+			let mut v: Vec<_> = includes
+				.iter()
+				.map(|(path, text)| (include_path(path), text.as_str()))
+				.collect();
+			v.sort_by(|a, b| a.0.cmp(&b.0));
+			v
+		};
+		let mut blocks = Vec::new();
+		Self::parse_into(text, Condition::Always, &includes, 0, &mut blocks)?;
+		Ok(SshConfig { blocks })
+	}
+
+	fn parse_into(
+		text: &str,
+		condition: Condition,
+		includes: &[(String, &str)],
+		depth: usize,
+		blocks: &mut Vec<Block>,
+	) -> Result<(), SshError> {
+		if depth > MAX_INCLUDE_DEPTH {
+			return Err(SshError::Config("Include nested too deeply".into()));
+		}
+		blocks.push(Block {
+			condition,
+			options: Vec::new(),
+		});
+		for (i, line) in text.lines().enumerate() {
+			let line_no = i + 1;
+			let Some((keyword, args)) = split_line(line, line_no)? else {
+				continue;
+			};
+			let condition = match keyword.as_str() {
+				"host" => Condition::Host(args),
+				"match" => Condition::Match(parse_match(&args, line_no)?),
+				"include" => {
+					// An included file starts under the including block's
+					// condition; its own Host/Match lines don't leak back out.
+					let current = blocks.last().map(|b| b.condition.clone());
+					let current = current.unwrap_or(Condition::Always);
+					for pattern in &args {
+						let pattern = include_path(pattern);
+						for (_, text) in includes
+							.iter()
+							.filter(|(path, _)| wildcard_match(pattern.as_bytes(), path.as_bytes()))
+						{
+							Self::parse_into(text, current.clone(), includes, depth + 1, blocks)?;
+						}
+					}
+					current
+				}
+				_ => {
+					if let Some(block) = blocks.last_mut() {
+						block.options.push((keyword, args, line_no));
+					}
+					continue;
+				}
+			};
+			blocks.push(Block {
+				condition,
+				options: Vec::new(),
+			});
+		}
+		Ok(())
+	}
+
+	/// The concrete aliases named by `Host` lines (no wildcards or negation),
+	/// in file order — what a bulk import offers.
+	pub fn aliases(&self) -> Vec<String> {
+		let mut out: Vec<String> = Vec::new();
+		for block in &self.blocks {
+			let Condition::Host(patterns) = &block.condition else {
+				continue;
+			};
+			for p in patterns.iter().flat_map(|p| p.split(',')) {
+				if !p.is_empty()
+					&& !p.contains(['*', '?', '!'])
+					&& !out.iter().any(|a| a.eq_ignore_ascii_case(p))
+				{
+					out.push(p.to_string());
+				}
+			}
+		}
+		out
+	}
+
+	/// Resolve every alias from [`aliases`](Self::aliases).
+	pub fn resolve_all(&self) -> Result<Vec<SshConfigHost>, SshError> {
+		self.aliases().iter().map(|a| self.resolve(a)).collect()
+	}
+
+	/// Resolve `alias` (which needn't appear in a `Host` line — `Host *`
+	/// defaults still apply to a bare hostname).
+	pub fn resolve(&self, alias: &str) -> Result<SshConfigHost, SshError> {
+		self.resolve_at_depth(alias, 0)
+	}
+
+	fn resolve_at_depth(&self, alias: &str, depth: usize) -> Result<SshConfigHost, SshError> {
+		if depth > MAX_JUMP_DEPTH {
+			return Err(SshError::Config(format!(
+				"ProxyJump loop through {alias:?}"
+			)));
+		}
+		let mut host_name: Option<String> = None;
+		let mut port: Option<u16> = None;
+		let mut user: Option<String> = None;
+		let mut identity_files: Vec<String> = Vec::new();
+		let mut proxy_jump: Option<String> = None;
+		let mut alive_interval: Option<u32> = None;
+		let mut alive_count_max: Option<u32> = None;
+
+		for block in &self.blocks {
+			let applies = match &block.condition {
+				Condition::Always => true,
+				Condition::Host(patterns) => {
+					match_pattern_list(patterns.iter().map(String::as_str), alias)
+				}
+				Condition::Match(criteria) => criteria.iter().all(|(negated, c)| {
+					let hit = match c {
+						Criterion::All | Criterion::Final => true,
+						Criterion::Host(p) => {
+							match_pattern_list([p.as_str()], host_name.as_deref().unwrap_or(alias))
+						}
+						Criterion::OriginalHost(p) => match_pattern_list([p.as_str()], alias),
+						Criterion::User(p) => user
+							.as_deref()
+							.is_some_and(|u| match_pattern_list([p.as_str()], u)),
+						Criterion::Unsupported => return false,
+					};
+					hit != *negated
+				}),
+			};
+			if !applies {
+				continue;
+			}
+			for (keyword, args, line) in &block.options {
+				let Some(value) = args.first() else {
+					return Err(config_err(*line, format!("{keyword} needs an argument")));
+				};
+				let number = || {
+					value
+						.parse::<u32>()
+						.map_err(|_| config_err(*line, format!("bad {keyword} {value:?}")))
+				};
+				match keyword.as_str() {
+					"hostname" if host_name.is_none() => {
+						host_name = Some(value.replace("%h", alias).replace("%%", "%"));
+					}
+					"port" if port.is_none() => {
+						port = Some(
+							value
+								.parse()
+								.map_err(|_| config_err(*line, format!("bad port {value:?}")))?,
+						);
+					}
+					"user" if user.is_none() => user = Some(value.clone()),
+					"identityfile" => {
+						if !identity_files.contains(value) {
+							identity_files.push(value.clone());
+						}
+					}
+					"proxyjump" if proxy_jump.is_none() => proxy_jump = Some(value.clone()),
+					"serveraliveinterval" if alive_interval.is_none() => {
+						alive_interval = Some(number()?);
+					}
+					"serveralivecountmax" if alive_count_max.is_none() => {
+						alive_count_max = Some(number()?);
+					}
+					_ => {}
+				}
+			}
+		}
+
+		let jump_hosts = match proxy_jump.as_deref() {
+			None | Some("none") => Vec::new(),
+			Some(spec) => self.resolve_jumps(spec, depth)?,
+		};
+		Ok(SshConfigHost {
+			alias: alias.to_string(),
+			details: ConnectionDetails {
+				host: host_name.unwrap_or_else(|| alias.to_string()),
+				port: port.unwrap_or(22),
+				username: user.unwrap_or_default(),
+				credentials: Vec::new(),
+			},
+			identity_files,
+			jump_hosts,
+			server_alive_interval: alive_interval,
+			server_alive_count_max: alive_count_max,
+		})
+	}
+
+	/// `ProxyJump a,b` ≡ `ssh -J a,b`: `b` is reached through `a`, so only the
+	/// first hop's own `ProxyJump` applies (it is prepended); later hops' are
+	/// overridden by the chain, exactly as `ssh` does it.
+	fn resolve_jumps(&self, spec: &str, depth: usize) -> Result<Vec<SshConfigHop>, SshError> {
+		let mut chain = Vec::new();
+		for (i, hop) in spec.split(',').filter(|h| !h.is_empty()).enumerate() {
+			let (user, host, port) = parse_jump(hop)
+				.ok_or_else(|| SshError::Config(format!("bad ProxyJump hop {hop:?}")))?;
+			let mut resolved = self.resolve_at_depth(host, depth + 1)?;
+			if i == 0 {
+				chain.append(&mut resolved.jump_hosts);
+			}
+			if let Some(user) = user {
+				resolved.details.username = user.to_string();
+			}
+			if let Some(port) = port {
+				resolved.details.port = port;
+			}
+			chain.push(SshConfigHop {
+				details: resolved.details,
+				identity_files: resolved.identity_files,
+			});
+		}
+		Ok(chain)
+	}
+}
+
+/// `[ssh://][user@]host[:port]`, with `[v6]:port` brackets.
+fn parse_jump(hop: &str) -> Option<(Option<&str>, &str, Option<u16>)> {
+	let hop = hop.strip_prefix("ssh://").unwrap_or(hop);
+	let (user, rest) = match hop.rsplit_once('@') {
+		Some((user, rest)) => (Some(user), rest),
+		None => (None, hop),
+	};
+	let (host, port) = if let Some(rest) = rest.strip_prefix('[') {
+		let (host, after) = rest.split_once(']')?;
+		match after.strip_prefix(':') {
+			Some(port) => (host, Some(port)),
+			None if after.is_empty() => (host, None),
+			None => return None,
+		}
+	} else {
+		match rest.split_once(':') {
+			Some((host, port)) => (host, Some(port)),
+			None => (rest, None),
+		}
+	};
+	let port = match port {
+		Some(p) => Some(p.parse().ok()?),
+		None => None,
+	};
+	(!host.is_empty()).then_some((user, host, port))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	const CONFIG: &str = r#"
+# Desktop config
+Host bastion
+	HostName bastion.example.com
+	User jump
+	IdentityFile ~/.ssh/jump_ed25519
+
+Host db-* !db-legacy
+	HostName %h.internal
+	ProxyJump bastion
+	Port=2200
+
+Host web web.example.com
+	HostName "web.example.com"
+	User deploy
+	ServerAliveInterval 30
+
+Match host *.internal user admin
+	IdentityFile ~/.ssh/admin
+
+Host *
+	User me
+	IdentityFile ~/.ssh/id_ed25519
+	ServerAliveInterval 60
+	ServerAliveCountMax 3
+"#;
+
+	fn resolve(text: &str, alias: &str) -> SshConfigHost {
+		SshConfig::parse(text, &HashMap::new())
+			.unwrap()
+			.resolve(alias)
+			.unwrap()
+	}
+
+	#[test]
+	fn first_value_wins_and_identity_files_accumulate() {
+		let web = resolve(CONFIG, "web");
+		assert_eq!(web.details.host, "web.example.com");
+		assert_eq!(web.details.port, 22);
+		assert_eq!(web.details.username, "deploy");
+		assert_eq!(web.identity_files, ["~/.ssh/id_ed25519"]);
+		assert_eq!(web.server_alive_interval, Some(30));
+		assert_eq!(web.server_alive_count_max, Some(3));
+		assert!(web.jump_hosts.is_empty());
+
+		let bare = resolve(CONFIG, "elsewhere.org");
+		assert_eq!(bare.details.host, "elsewhere.org");
+		assert_eq!(bare.details.username, "me");
+	}
+
+	#[test]
+	fn host_globbing_negation_and_proxy_jump() {
+		let db = resolve(CONFIG, "db-main");
+		assert_eq!(db.details.host, "db-main.internal");
+		assert_eq!(db.details.port, 2200);
+		assert_eq!(db.jump_hosts.len(), 1);
+		let hop = &db.jump_hosts[0];
+		assert_eq!(hop.details.host, "bastion.example.com");
+		assert_eq!(hop.details.username, "jump");
+		assert_eq!(
+			hop.identity_files,
+			["~/.ssh/jump_ed25519", "~/.ssh/id_ed25519"]
+		);
+
+		let legacy = resolve(CONFIG, "db-legacy");
+		assert_eq!(legacy.details.host, "db-legacy");
+		assert!(legacy.jump_hosts.is_empty());
+	}
+
+	#[test]
+	fn match_uses_resolved_host_and_user() {
+		let cfg = format!("Host db-admin\n\tUser admin\n{CONFIG}");
+		let admin = resolve(&cfg, "db-admin");
+		assert_eq!(admin.identity_files, ["~/.ssh/admin", "~/.ssh/id_ed25519"]);
+		// Same host, different user: the Match doesn't apply.
+		assert_eq!(
+			resolve(CONFIG, "db-main").identity_files,
+			["~/.ssh/id_ed25519"]
+		);
+		// Criteria needing the local machine never match.
+		let exec = resolve("Match exec \"true\"\n\tUser nope\n", "x");
+		assert_eq!(exec.details.username, "");
+	}
+
+	#[test]
+	fn proxy_jump_specs_chain_and_override() {
+		let cfg = "Host inner\n\tProxyJump outer\nHost outer\n\tHostName outer.example.com\n\
+		           Host target\n\tProxyJump inner,ops@[fd00::1]:2222\n";
+		let target = resolve(cfg, "target");
+		let hosts: Vec<_> = target
+			.jump_hosts
+			.iter()
+			.map(|h| {
+				(
+					h.details.host.as_str(),
+					h.details.port,
+					h.details.username.as_str(),
+				)
+			})
+			.collect();
+		assert_eq!(
+			hosts,
+			[
+				("outer.example.com", 22, ""),
+				("inner", 22, ""),
+				("fd00::1", 2222, "ops"),
+			]
+		);
+
+		let looped = SshConfig::parse(
+			"Host a\n\tProxyJump b\nHost b\n\tProxyJump a\n",
+			&HashMap::new(),
+		)
+		.unwrap()
+		.resolve("a");
+		assert!(matches!(looped, Err(SshError::Config(_))));
+	}
+
+	#[test]
+	fn includes_resolve_against_supplied_files() {
+		let includes = HashMap::from([
+			(
+				"conf.d/work".to_string(),
+				"Host work\n\tHostName work.example.com\n".to_string(),
+			),
+			(
+				"~/.ssh/conf.d/home".to_string(),
+				"Host nas\n\tPort 2222\n".to_string(),
+			),
+			("~/.ssh/extra".to_string(), "User scoped\n".to_string()),
+		]);
+		let cfg = "Include conf.d/*\nHost scoped\n\tInclude extra\n\tPort 23\nHost *\n\tUser me\n";
+		let parsed = SshConfig::parse(cfg, &includes).unwrap();
+		assert_eq!(parsed.aliases(), ["nas", "work", "scoped"]);
+		assert_eq!(
+			parsed.resolve("work").unwrap().details.host,
+			"work.example.com"
+		);
+		assert_eq!(parsed.resolve("nas").unwrap().details.port, 2222);
+		// The included file's lines stay under the including `Host`, and the
+		// lines after the Include are still part of that block.
+		let scoped = parsed.resolve("scoped").unwrap();
+		assert_eq!(
+			(scoped.details.username.as_str(), scoped.details.port),
+			("scoped", 23)
+		);
+		assert_eq!(parsed.resolve("nas").unwrap().details.username, "me");
+	}
+
+	#[test]
+	fn syntax_errors_name_the_line() {
+		let err = SshConfig::parse("Host a\n\tHostName \"oops\n", &HashMap::new()).unwrap_err();
+		assert!(matches!(err, SshError::Config(m) if m.starts_with("line 2")));
+		let err = SshConfig::parse("Host a\n\tPort http\n", &HashMap::new())
+			.unwrap()
+			.resolve("a")
+			.unwrap_err();
+		assert!(matches!(err, SshError::Config(_)));
+	}
+}
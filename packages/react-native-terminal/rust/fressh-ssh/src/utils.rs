@@ -25,6 +25,49 @@ pub enum SshError {
 	UnsupportedKeyType,
 	#[error("Auth failed: {0}")]
 	Auth(String),
+	/// Every credential was tried (or skipped as no longer offered) without
+	/// getting in. `remaining_methods` is what the server still accepts (e.g.
+	/// `["password"]` after a key was accepted as a partial success).
+	#[error("Auth failed; server still accepts: {}", remaining_methods.join(", "))]
+	AuthRejected {
+		remaining_methods: Vec<String>,
+		partial_success: bool,
+	},
+	/// The private key is encrypted and no passphrase was supplied.
+	#[error("Passphrase required")]
+	PassphraseRequired,
+	/// The supplied passphrase does not decrypt the private key.
+	#[error("Wrong passphrase")]
+	WrongPassphrase,
+	/// An OpenSSH certificate is malformed or can't be used for this login.
+	#[error("Certificate error: {0}")]
+	Certificate(String),
+	/// An `ssh_config` can't be parsed or resolved.
+	#[error("ssh_config error: {0}")]
+	Config(String),
+	/// A mosh session couldn't be bootstrapped or reached.
+	#[error("mosh error: {0}")]
+	Mosh(String),
+	/// An algorithm preference names something russh doesn't implement.
+	#[error("Unsupported algorithm: {0}")]
+	UnsupportedAlgorithm(String),
+	/// A connect phase (TCP, handshake or auth) outlived its deadline.
+	#[error("Timed out: {0}")]
+	Timeout(String),
+	/// The host name didn't resolve to a usable address.
+	#[error("DNS resolution failed: {0}")]
+	Dns(String),
+	/// The upstream proxy couldn't be used or refused the tunnel.
+	#[error("Proxy error: {0}")]
+	Proxy(String),
+	/// An SFTP request failed (the server's status message), or the server
+	/// has no `sftp` subsystem.
+	#[error("SFTP error: {0}")]
+	Sftp(String),
+	/// The remote `scp` reported an error, or spoke something other than the
+	/// SCP protocol.
+	#[error("SCP error: {0}")]
+	Scp(String),
 	#[error("Shell already running")]
 	ShellAlreadyRunning,
 	#[error("Host key rejected")]
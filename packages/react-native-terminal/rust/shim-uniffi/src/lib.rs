@@ -10,6 +10,7 @@
 //! types (no uniffi derives), so we define uniffi-shaped mirrors here and convert.
 //! Swapping to craby later replaces THIS file's wrappers, not the core.
 
+use std::collections::{BTreeMap, HashMap};
 use std::sync::Arc;
 
 // The render-plane C-ABI (the Nitro view's native entry points). Shared by both
@@ -30,6 +31,34 @@ pub enum SshError {
 	UnsupportedKeyType,
 	#[error("Auth failed: {0}")]
 	Auth(String),
+	/// No credential got in; `remaining_methods` is what the server still accepts.
+	#[error("Auth failed; server still accepts: {}", remaining_methods.join(", "))]
+	AuthRejected {
+		remaining_methods: Vec<String>,
+		partial_success: bool,
+	},
+	#[error("Passphrase required")]
+	PassphraseRequired,
+	#[error("Wrong passphrase")]
+	WrongPassphrase,
+	#[error("Certificate error: {0}")]
+	Certificate(String),
+	#[error("ssh_config error: {0}")]
+	Config(String),
+	#[error("mosh error: {0}")]
+	Mosh(String),
+	#[error("Unsupported algorithm: {0}")]
+	UnsupportedAlgorithm(String),
+	#[error("Timed out: {0}")]
+	Timeout(String),
+	#[error("DNS resolution failed: {0}")]
+	Dns(String),
+	#[error("Proxy error: {0}")]
+	Proxy(String),
+	#[error("SFTP error: {0}")]
+	Sftp(String),
+	#[error("SCP error: {0}")]
+	Scp(String),
 	#[error("Shell already running")]
 	ShellAlreadyRunning,
 	#[error("Host key rejected")]
@@ -49,6 +78,24 @@ impl From<fressh_core::SshError> for SshError {
 			E::Disconnected => SshError::Disconnected,
 			E::UnsupportedKeyType => SshError::UnsupportedKeyType,
 			E::Auth(m) => SshError::Auth(m),
+			E::AuthRejected {
+				remaining_methods,
+				partial_success,
+			} => SshError::AuthRejected {
+				remaining_methods,
+				partial_success,
+			},
+			E::PassphraseRequired => SshError::PassphraseRequired,
+			E::WrongPassphrase => SshError::WrongPassphrase,
+			E::Certificate(m) => SshError::Certificate(m),
+			E::Config(m) => SshError::Config(m),
+			E::Mosh(m) => SshError::Mosh(m),
+			E::UnsupportedAlgorithm(m) => SshError::UnsupportedAlgorithm(m),
+			E::Timeout(m) => SshError::Timeout(m),
+			E::Dns(m) => SshError::Dns(m),
+			E::Proxy(m) => SshError::Proxy(m),
+			E::Sftp(m) => SshError::Sftp(m),
+			E::Scp(m) => SshError::Scp(m),
 			E::ShellAlreadyRunning => SshError::ShellAlreadyRunning,
 			E::HostKeyRejected => SshError::HostKeyRejected,
 			E::NotFound(m) => SshError::NotFound(m),
@@ -62,8 +109,20 @@ impl From<fressh_core::SshError> for SshError {
 
 #[derive(uniffi::Enum)]
 pub enum Security {
-	Password { password: String },
-	Key { private_key_content: String },
+	Password {
+		password: String,
+	},
+	Key {
+		private_key_content: String,
+		/// Required if the key is encrypted (`SshError::PassphraseRequired`).
+		#[uniffi(default)]
+		passphrase: Option<String>,
+		/// OpenSSH user certificate (`id_*-cert.pub`) to present with the key.
+		#[uniffi(default)]
+		certificate: Option<String>,
+	},
+	/// Prompts arrive as `AuthPromptPending`; answer with `respond_to_auth_prompt`.
+	KeyboardInteractive,
 }
 impl From<Security> for fressh_core::Security {
 	fn from(s: Security) -> Self {
@@ -71,9 +130,14 @@ impl From<Security> for fressh_core::Security {
 			Security::Password { password } => fressh_core::Security::Password { password },
 			Security::Key {
 				private_key_content,
+				passphrase,
+				certificate,
 			} => fressh_core::Security::Key {
 				private_key_content,
+				passphrase,
+				certificate,
 			},
+			Security::KeyboardInteractive => fressh_core::Security::KeyboardInteractive,
 		}
 	}
 }
@@ -83,7 +147,8 @@ pub struct ConnectionDetails {
 	pub host: String,
 	pub port: u16,
 	pub username: String,
-	pub security: Security,
+	/// Tried in order until one gets in; see `fressh_ssh::ConnectionDetails`.
+	pub credentials: Vec<Security>,
 }
 impl From<ConnectionDetails> for fressh_core::ConnectionDetails {
 	fn from(d: ConnectionDetails) -> Self {
@@ -91,7 +156,208 @@ impl From<ConnectionDetails> for fressh_core::ConnectionDetails {
 			host: d.host,
 			port: d.port,
 			username: d.username,
-			security: d.security.into(),
+			credentials: d.credentials.into_iter().map(Into::into).collect(),
+		}
+	}
+}
+
+/// Mirrors `fressh_core::KeepaliveConfig`. `max_missed` 0 = never give up.
+#[derive(uniffi::Record)]
+pub struct KeepaliveConfig {
+	pub interval_secs: u32,
+	pub max_missed: u32,
+}
+
+/// Mirrors `fressh_core::ReconnectConfig`. `max_attempts` 0 = retry forever.
+#[derive(uniffi::Record)]
+pub struct ReconnectConfig {
+	pub max_attempts: u32,
+	pub initial_delay_ms: u32,
+	pub max_delay_ms: u32,
+}
+
+/// Mirrors `fressh_core::AlgorithmPreferences`: names to offer, most preferred
+/// first. An empty list keeps the default for that slot.
+#[derive(uniffi::Record)]
+pub struct AlgorithmPreferences {
+	#[uniffi(default)]
+	pub kex: Vec<String>,
+	#[uniffi(default)]
+	pub host_key: Vec<String>,
+	#[uniffi(default)]
+	pub cipher: Vec<String>,
+	#[uniffi(default)]
+	pub mac: Vec<String>,
+	#[uniffi(default)]
+	pub compression: Vec<String>,
+}
+impl From<AlgorithmPreferences> for fressh_core::AlgorithmPreferences {
+	fn from(a: AlgorithmPreferences) -> Self {
+		fressh_core::AlgorithmPreferences {
+			kex: a.kex,
+			host_key: a.host_key,
+			cipher: a.cipher,
+			mac: a.mac,
+			compression: a.compression,
+		}
+	}
+}
+
+/// Mirrors `fressh_core::ConnectTimeouts`, in ms. Unset = wait indefinitely.
+#[derive(uniffi::Record)]
+pub struct ConnectTimeouts {
+	#[uniffi(default)]
+	pub connect_ms: Option<u32>,
+	#[uniffi(default)]
+	pub handshake_ms: Option<u32>,
+	#[uniffi(default)]
+	pub auth_ms: Option<u32>,
+}
+
+/// Mirrors `fressh_core::IpPreference`.
+#[derive(uniffi::Enum)]
+pub enum IpPreference {
+	Any,
+	Ipv4First,
+	Ipv6First,
+	Ipv4Only,
+	Ipv6Only,
+}
+impl From<IpPreference> for fressh_core::IpPreference {
+	fn from(p: IpPreference) -> Self {
+		match p {
+			IpPreference::Any => fressh_core::IpPreference::Any,
+			IpPreference::Ipv4First => fressh_core::IpPreference::Ipv4First,
+			IpPreference::Ipv6First => fressh_core::IpPreference::Ipv6First,
+			IpPreference::Ipv4Only => fressh_core::IpPreference::Ipv4Only,
+			IpPreference::Ipv6Only => fressh_core::IpPreference::Ipv6Only,
+		}
+	}
+}
+
+/// Mirrors `fressh_core::UpstreamProxy`.
+#[derive(uniffi::Enum)]
+pub enum ProxyKind {
+	HttpConnect,
+	Socks5,
+}
+
+/// Mirrors `fressh_core::UpstreamProxy`: what the first TCP hop dials instead.
+#[derive(uniffi::Record)]
+pub struct UpstreamProxy {
+	pub kind: ProxyKind,
+	pub host: String,
+	pub port: u16,
+	#[uniffi(default)]
+	pub username: Option<String>,
+	#[uniffi(default)]
+	pub password: Option<String>,
+}
+impl From<UpstreamProxy> for fressh_core::UpstreamProxy {
+	fn from(p: UpstreamProxy) -> Self {
+		fressh_core::UpstreamProxy {
+			kind: match p.kind {
+				ProxyKind::HttpConnect => fressh_core::ProxyKind::HttpConnect,
+				ProxyKind::Socks5 => fressh_core::ProxyKind::Socks5,
+			},
+			host: p.host,
+			port: p.port,
+			username: p.username,
+			password: p.password,
+		}
+	}
+}
+
+/// Mirrors `fressh_core::ConnectConfig`: connection tunables.
+#[derive(uniffi::Record)]
+pub struct ConnectConfig {
+	#[uniffi(default)]
+	pub keepalive: Option<KeepaliveConfig>,
+	#[uniffi(default)]
+	pub reconnect: Option<ReconnectConfig>,
+	#[uniffi(default)]
+	pub algorithms: Option<AlgorithmPreferences>,
+	#[uniffi(default)]
+	pub timeouts: Option<ConnectTimeouts>,
+	#[uniffi(default)]
+	pub ip_preference: Option<IpPreference>,
+	#[uniffi(default)]
+	pub proxy: Option<UpstreamProxy>,
+	/// Forward the app's SSH agent (`agent_add_key`) to the target's shells.
+	#[uniffi(default)]
+	pub forward_agent: bool,
+}
+impl From<ConnectConfig> for fressh_core::ConnectConfig {
+	fn from(c: ConnectConfig) -> Self {
+		fressh_core::ConnectConfig {
+			keepalive: c.keepalive.map(|k| fressh_core::KeepaliveConfig {
+				interval_secs: k.interval_secs,
+				max_missed: k.max_missed,
+			}),
+			reconnect: c.reconnect.map(|r| fressh_core::ReconnectConfig {
+				max_attempts: r.max_attempts,
+				initial_delay_ms: r.initial_delay_ms,
+				max_delay_ms: r.max_delay_ms,
+			}),
+			algorithms: c.algorithms.map(Into::into).unwrap_or_default(),
+			timeouts: c
+				.timeouts
+				.map(|t| fressh_core::ConnectTimeouts {
+					connect_ms: t.connect_ms,
+					handshake_ms: t.handshake_ms,
+					auth_ms: t.auth_ms,
+				})
+				.unwrap_or_default(),
+			ip_preference: c.ip_preference.map(Into::into).unwrap_or_default(),
+			proxy: c.proxy.map(Into::into),
+			forward_agent: c.forward_agent,
+		}
+	}
+}
+
+/// A hop of an `ssh_config` `ProxyJump` chain.
+#[derive(uniffi::Record)]
+pub struct SshConfigHop {
+	pub details: ConnectionDetails,
+	pub identity_files: Vec<String>,
+}
+
+/// Mirrors `fressh_core::SshConfigHost`. `details.credentials` is empty (the
+/// app fills it from `identity_files`); `username` is empty if no `User` applies.
+#[derive(uniffi::Record)]
+pub struct SshConfigHost {
+	pub alias: String,
+	pub details: ConnectionDetails,
+	pub identity_files: Vec<String>,
+	pub jump_hosts: Vec<SshConfigHop>,
+	pub server_alive_interval: Option<u32>,
+	pub server_alive_count_max: Option<u32>,
+}
+/// Config-derived details never carry credentials, so none are mapped back.
+fn config_details(d: fressh_core::ConnectionDetails) -> ConnectionDetails {
+	ConnectionDetails {
+		host: d.host,
+		port: d.port,
+		username: d.username,
+		credentials: Vec::new(),
+	}
+}
+impl From<fressh_core::SshConfigHost> for SshConfigHost {
+	fn from(h: fressh_core::SshConfigHost) -> Self {
+		SshConfigHost {
+			alias: h.alias,
+			details: config_details(h.details),
+			identity_files: h.identity_files,
+			jump_hosts: h
+				.jump_hosts
+				.into_iter()
+				.map(|hop| SshConfigHop {
+					details: config_details(hop.details),
+					identity_files: hop.identity_files,
+				})
+				.collect(),
+			server_alive_interval: h.server_alive_interval,
+			server_alive_count_max: h.server_alive_count_max,
 		}
 	}
 }
@@ -114,6 +380,52 @@ impl From<KeyType> for fressh_core::KeyType {
 	}
 }
 
+#[derive(uniffi::Enum)]
+pub enum CertificateType {
+	User,
+	Host,
+}
+
+/// Mirrors `fressh_core::CertificateInfo`. Times are unix seconds.
+#[derive(uniffi::Record)]
+pub struct CertificateInfo {
+	pub cert_type: CertificateType,
+	pub key_id: String,
+	pub serial: u64,
+	pub principals: Vec<String>,
+	pub valid_after: u64,
+	pub valid_before: u64,
+	pub ca_fingerprint_sha256: String,
+	pub key_fingerprint_sha256: String,
+	pub public_key: String,
+	pub ca_public_key: String,
+	pub signature_valid: bool,
+	pub critical_options: Vec<String>,
+	pub extensions: Vec<String>,
+}
+impl From<fressh_core::CertificateInfo> for CertificateInfo {
+	fn from(c: fressh_core::CertificateInfo) -> Self {
+		CertificateInfo {
+			cert_type: match c.cert_type {
+				fressh_core::CertificateType::User => CertificateType::User,
+				fressh_core::CertificateType::Host => CertificateType::Host,
+			},
+			key_id: c.key_id,
+			serial: c.serial,
+			principals: c.principals,
+			valid_after: c.valid_after,
+			valid_before: c.valid_before,
+			ca_fingerprint_sha256: c.ca_fingerprint_sha256,
+			key_fingerprint_sha256: c.key_fingerprint_sha256,
+			public_key: c.public_key,
+			ca_public_key: c.ca_public_key,
+			signature_valid: c.signature_valid,
+			critical_options: c.critical_options,
+			extensions: c.extensions,
+		}
+	}
+}
+
 #[derive(uniffi::Enum)]
 pub enum TerminalType {
 	Vanilla,
@@ -148,6 +460,34 @@ pub struct ShellOptions {
 	/// exit code / command text). `None` ⇒ default on. Set `false` from the app's
 	/// global kill-switch / per-host toggle to behave like a plain SSH client.
 	pub shell_integration: Option<bool>,
+	/// Variables the integration reports back as they change, so
+	/// `run_command_in_shell_context` can export them (e.g. `VIRTUAL_ENV`).
+	#[uniffi(default)]
+	pub report_env: Vec<String>,
+	/// Variables to set in the shell (`LANG`, `LC_*`, …). Ones the server's
+	/// `AcceptEnv` refuses are exported by the shell integration instead.
+	#[uniffi(default)]
+	pub env: HashMap<String, String>,
+	/// Run this on the PTY instead of an interactive shell (`tmux attach`,
+	/// `htop`); the shell closes when it exits, its status in `ShellClosed`.
+	#[uniffi(default)]
+	pub command: Option<String>,
+}
+
+/// Mirrors `fressh_core::StartedShell`.
+#[derive(uniffi::Record)]
+pub struct StartedShell {
+	pub shell_id: String,
+	/// `env` names the server refused (exported by the integration if on).
+	pub rejected_env: Vec<String>,
+}
+impl From<fressh_core::StartedShell> for StartedShell {
+	fn from(s: fressh_core::StartedShell) -> Self {
+		StartedShell {
+			shell_id: s.shell_id,
+			rejected_env: s.rejected_env,
+		}
+	}
 }
 
 #[derive(uniffi::Record)]
@@ -172,20 +512,128 @@ impl From<fressh_core::ServerPublicKeyInfo> for ServerPublicKeyInfo {
 	}
 }
 
+/// Mirrors `fressh_core::HostKeyStatus`: what `known_hosts` says about a key.
+#[derive(uniffi::Enum)]
+pub enum HostKeyStatus {
+	Trusted,
+	Revoked,
+	Changed,
+	Unknown,
+}
+impl From<fressh_core::HostKeyStatus> for HostKeyStatus {
+	fn from(s: fressh_core::HostKeyStatus) -> Self {
+		use fressh_core::HostKeyStatus as S;
+		match s {
+			S::Trusted => HostKeyStatus::Trusted,
+			S::Revoked => HostKeyStatus::Revoked,
+			S::Changed => HostKeyStatus::Changed,
+			S::Unknown => HostKeyStatus::Unknown,
+		}
+	}
+}
+
+#[derive(uniffi::Record)]
+pub struct AuthPrompt {
+	pub prompt: String,
+	pub echo: bool,
+}
+
+/// One keyboard-interactive round. Mirrors `fressh_core::AuthPromptRequest`.
+#[derive(uniffi::Record)]
+pub struct AuthPromptRequest {
+	pub round: u32,
+	pub name: String,
+	pub instruction: String,
+	pub prompts: Vec<AuthPrompt>,
+}
+impl From<fressh_core::AuthPromptRequest> for AuthPromptRequest {
+	fn from(r: fressh_core::AuthPromptRequest) -> Self {
+		AuthPromptRequest {
+			round: r.round,
+			name: r.name,
+			instruction: r.instruction,
+			prompts: r
+				.prompts
+				.into_iter()
+				.map(|p| AuthPrompt {
+					prompt: p.prompt,
+					echo: p.echo,
+				})
+				.collect(),
+		}
+	}
+}
+
+/// A signature a forwarded agent wants from a `confirm` key. Mirrors
+/// `fressh_core::AgentSignRequest`.
+#[derive(uniffi::Record)]
+pub struct AgentSignRequest {
+	pub fingerprint: String,
+	pub comment: String,
+	pub host: String,
+	pub port: u16,
+}
+impl From<fressh_core::AgentSignRequest> for AgentSignRequest {
+	fn from(r: fressh_core::AgentSignRequest) -> Self {
+		AgentSignRequest {
+			fingerprint: r.fingerprint,
+			comment: r.comment,
+			host: r.host,
+			port: r.port,
+		}
+	}
+}
+
+/// A key held by the app's SSH agent. Mirrors `fressh_core::AgentKeyInfo`.
+#[derive(uniffi::Record)]
+pub struct AgentKeyInfo {
+	pub fingerprint: String,
+	pub algorithm: String,
+	pub comment: String,
+	pub confirm: bool,
+}
+impl From<fressh_core::AgentKeyInfo> for AgentKeyInfo {
+	fn from(k: fressh_core::AgentKeyInfo) -> Self {
+		AgentKeyInfo {
+			fingerprint: k.fingerprint,
+			algorithm: k.algorithm,
+			comment: k.comment,
+			confirm: k.confirm,
+		}
+	}
+}
+
 #[derive(uniffi::Enum)]
 pub enum SshConnectionProgressEvent {
+	DnsResolved { addresses: Vec<String> },
+	TcpAttempt { address: String },
+	TcpAttemptFailed { address: String, error: String },
+	JumpHostTcpConnected { hop: u32 },
+	JumpHostSshHandshake { hop: u32 },
+	JumpHostAuthenticated { hop: u32 },
 	TcpConnected,
 	SshHandshake,
 }
 impl From<fressh_core::SshConnectionProgressEvent> for SshConnectionProgressEvent {
 	fn from(e: fressh_core::SshConnectionProgressEvent) -> Self {
+		use fressh_core::SshConnectionProgressEvent as E;
 		match e {
-			fressh_core::SshConnectionProgressEvent::TcpConnected => {
-				SshConnectionProgressEvent::TcpConnected
+			E::DnsResolved { addresses } => SshConnectionProgressEvent::DnsResolved { addresses },
+			E::TcpAttempt { address } => SshConnectionProgressEvent::TcpAttempt { address },
+			E::TcpAttemptFailed { address, error } => {
+				SshConnectionProgressEvent::TcpAttemptFailed { address, error }
+			}
+			E::JumpHostTcpConnected { hop } => {
+				SshConnectionProgressEvent::JumpHostTcpConnected { hop }
+			}
+			E::JumpHostSshHandshake { hop } => {
+				SshConnectionProgressEvent::JumpHostSshHandshake { hop }
 			}
-			fressh_core::SshConnectionProgressEvent::SshHandshake => {
-				SshConnectionProgressEvent::SshHandshake
+			E::JumpHostAuthenticated { hop } => {
+				SshConnectionProgressEvent::JumpHostAuthenticated { hop }
 			}
+			E::TcpConnected => SshConnectionProgressEvent::TcpConnected,
+			E::SshHandshake => SshConnectionProgressEvent::SshHandshake,
 		}
 	}
 }
@@ -198,15 +646,103 @@ pub enum FresshEvent {
 		connection_id: String,
 		event: SshConnectionProgressEvent,
 	},
+	/// `hop` is the jump-host index being verified, `None` for the final target.
+	/// `status` is `Unknown` or `Changed` (warn loudly on the latter).
 	HostKeyPending {
 		connection_id: String,
+		hop: Option<u32>,
 		info: ServerPublicKeyInfo,
+		status: HostKeyStatus,
+	},
+	AuthPromptPending {
+		connection_id: String,
+		hop: Option<u32>,
+		request: AuthPromptRequest,
+	},
+	/// Answer with `respond_to_agent_sign(request_id, …)`.
+	AgentSignPending {
+		connection_id: String,
+		request_id: String,
+		request: AgentSignRequest,
 	},
 	ConnectionClosed {
 		connection_id: String,
 	},
+	/// Keepalives unanswered (`missed` of `max_missed`); `rtt_ms` = last good.
+	ConnectionDegraded {
+		connection_id: String,
+		missed: u32,
+		max_missed: u32,
+		rtt_ms: Option<u32>,
+	},
+	ConnectionRecovered {
+		connection_id: String,
+		rtt_ms: u32,
+	},
+	/// Emitted before the lost connection's shells/forwards are torn down.
+	ConnectionLost {
+		connection_id: String,
+		reason: String,
+	},
+	/// Shells stay alive (detached) while these run; see `ConnectConfig.reconnect`.
+	Reconnecting {
+		connection_id: String,
+		attempt: u32,
+		delay_ms: u32,
+		reason: String,
+	},
+	Reconnected {
+		connection_id: String,
+		attempt: u32,
+	},
 	ShellClosed {
 		shell_id: String,
+		end: ShellEnd,
+	},
+	// Port-forward lifecycle; counters are pulled via `list_forwards`.
+	ForwardOpened {
+		forward_id: String,
+		connection_id: String,
+		bound_port: u16,
+	},
+	ForwardClosed {
+		forward_id: String,
+	},
+	ForwardError {
+		forward_id: String,
+		message: String,
+	},
+	/// An SFTP session ended (explicitly or with its connection).
+	SftpClosed {
+		sftp_id: String,
+	},
+	// File transfers; progress is throttled, `list_transfers` has exact counts.
+	TransferProgress {
+		transfer_id: String,
+		bytes: u64,
+		total: Option<u64>,
+	},
+	TransferFinished {
+		transfer_id: String,
+		bytes: u64,
+	},
+	TransferFailed {
+		transfer_id: String,
+		message: String,
+	},
+	// Streaming commands (`start_exec`); output is coalesced per stream.
+	ExecOutput {
+		exec_id: String,
+		stream: StreamKind,
+		data: Vec<u8>,
+	},
+	/// The command ended; `truncated` / `timed_out` name a limit it hit.
+	ExecExited {
+		exec_id: String,
+		exit_code: Option<i32>,
+		exit_signal: Option<String>,
+		truncated: bool,
+		timed_out: bool,
 	},
 	// Shell-integration semantic events (OSC 7 + OSC 133); see fressh-core::osc.
 	WorkingDirectoryChanged {
@@ -242,15 +778,142 @@ impl From<fressh_core::CoreEvent> for FresshEvent {
 			},
 			E::HostKeyPending {
 				connection_id,
+				hop,
 				info,
+				status,
 			} => FresshEvent::HostKeyPending {
 				connection_id,
+				hop,
 				info: info.into(),
+				status: status.into(),
+			},
+			E::AuthPromptPending {
+				connection_id,
+				hop,
+				request,
+			} => FresshEvent::AuthPromptPending {
+				connection_id,
+				hop,
+				request: request.into(),
+			},
+			E::AgentSignPending {
+				connection_id,
+				request_id,
+				request,
+			} => FresshEvent::AgentSignPending {
+				connection_id,
+				request_id,
+				request: request.into(),
 			},
 			E::ConnectionClosed { connection_id } => {
 				FresshEvent::ConnectionClosed { connection_id }
 			}
-			E::ShellClosed { shell_id } => FresshEvent::ShellClosed { shell_id },
+			E::ConnectionDegraded {
+				connection_id,
+				missed,
+				max_missed,
+				rtt_ms,
+			} => FresshEvent::ConnectionDegraded {
+				connection_id,
+				missed,
+				max_missed,
+				rtt_ms,
+			},
+			E::ConnectionRecovered {
+				connection_id,
+				rtt_ms,
+			} => FresshEvent::ConnectionRecovered {
+				connection_id,
+				rtt_ms,
+			},
+			E::ConnectionLost {
+				connection_id,
+				reason,
+			} => FresshEvent::ConnectionLost {
+				connection_id,
+				reason,
+			},
+			E::Reconnecting {
+				connection_id,
+				attempt,
+				delay_ms,
+				reason,
+			} => FresshEvent::Reconnecting {
+				connection_id,
+				attempt,
+				delay_ms,
+				reason,
+			},
+			E::Reconnected {
+				connection_id,
+				attempt,
+			} => FresshEvent::Reconnected {
+				connection_id,
+				attempt,
+			},
+			E::ShellClosed { shell_id, end } => FresshEvent::ShellClosed {
+				shell_id,
+				end: end.into(),
+			},
+			E::ForwardOpened {
+				forward_id,
+				connection_id,
+				bound_port,
+			} => FresshEvent::ForwardOpened {
+				forward_id,
+				connection_id,
+				bound_port,
+			},
+			E::ForwardClosed { forward_id } => FresshEvent::ForwardClosed { forward_id },
+			E::SftpClosed { sftp_id } => FresshEvent::SftpClosed { sftp_id },
+			E::TransferProgress {
+				transfer_id,
+				bytes,
+				total,
+			} => FresshEvent::TransferProgress {
+				transfer_id,
+				bytes,
+				total,
+			},
+			E::TransferFinished { transfer_id, bytes } => {
+				FresshEvent::TransferFinished { transfer_id, bytes }
+			}
+			E::TransferFailed {
+				transfer_id,
+				message,
+			} => FresshEvent::TransferFailed {
+				transfer_id,
+				message,
+			},
+			E::ExecOutput {
+				exec_id,
+				stream,
+				data,
+			} => FresshEvent::ExecOutput {
+				exec_id,
+				stream: stream.into(),
+				data,
+			},
+			E::ExecExited {
+				exec_id,
+				exit_code,
+				exit_signal,
+				truncated,
+				timed_out,
+			} => FresshEvent::ExecExited {
+				exec_id,
+				exit_code,
+				exit_signal,
+				truncated,
+				timed_out,
+			},
+			E::ForwardError {
+				forward_id,
+				message,
+			} => FresshEvent::ForwardError {
+				forward_id,
+				message,
+			},
 			E::WorkingDirectoryChanged { shell_id, path } => {
 				FresshEvent::WorkingDirectoryChanged { shell_id, path }
 			}
@@ -295,13 +958,26 @@ pub fn set_event_listener(listener: Arc<dyn FresshEventListener>) {
 
 // ─────────────────────────── control plane ───────────────────────────
 
-/// Connect + authenticate. Returns the connection id. A `HostKeyPending` event is
-/// emitted mid-handshake; answer it with [`respond_to_host_key`].
-#[uniffi::export(async_runtime = "tokio")]
-pub async fn connect(details: ConnectionDetails) -> Result<String, SshError> {
-	fressh_core::connect(details.into())
-		.await
-		.map_err(Into::into)
+/// Connect + authenticate, tunnelled through `jump_hosts` in order (empty ⇒
+/// direct). Returns the connection id. A `HostKeyPending` event is emitted
+/// mid-handshake for every hop; answer each with [`respond_to_host_key`]. Hops
+/// using keyboard-interactive emit `AuthPromptPending` per round; answer with
+/// [`respond_to_auth_prompt`]. `config` carries tunables such as keepalives
+/// (which enable the `ConnectionDegraded`/`ConnectionLost` health events) and
+/// a reconnect policy (`Reconnecting`/`Reconnected`).
+#[uniffi::export(async_runtime = "tokio", default(config = None))]
+pub async fn connect(
+	details: ConnectionDetails,
+	jump_hosts: Vec<ConnectionDetails>,
+	config: Option<ConnectConfig>,
+) -> Result<String, SshError> {
+	fressh_core::connect(
+		details.into(),
+		jump_hosts.into_iter().map(Into::into).collect(),
+		config.map(Into::into).unwrap_or_default(),
+	)
+	.await
+	.map_err(Into::into)
 }
 
 /// Resume a parked host-key decision (accept/reject the server key).
@@ -310,9 +986,71 @@ pub fn respond_to_host_key(connection_id: String, accept: bool) {
 	fressh_core::respond_to_host_key(&connection_id, accept);
 }
 
-/// Open a PTY + shell, returning the new shell id. Render with `<Terminal shellId=…/>`.
+/// Install the app's `known_hosts` text. Trusted keys then connect without a
+/// `HostKeyPending`, revoked ones are refused. Returns the entries understood.
+#[uniffi::export]
+pub fn set_known_hosts(text: String) -> u32 {
+	fressh_core::set_known_hosts(&text) as u32
+}
+
+/// Resume a parked keyboard-interactive round: one answer per prompt, in order,
+/// or `None` to cancel.
+#[uniffi::export]
+pub fn respond_to_auth_prompt(connection_id: String, answers: Option<Vec<String>>) {
+	fressh_core::respond_to_auth_prompt(&connection_id, answers);
+}
+
+/// Hold a key in the app's SSH agent, served to connections with
+/// `forward_agent`. With `confirm`, every signature raises `AgentSignPending`.
+#[uniffi::export(default(passphrase = None, comment = None))]
+pub fn agent_add_key(
+	private_key_content: String,
+	passphrase: Option<String>,
+	comment: Option<String>,
+	confirm: bool,
+) -> Result<AgentKeyInfo, SshError> {
+	fressh_core::agent_add_key(
+		&private_key_content,
+		passphrase.as_deref(),
+		comment,
+		confirm,
+	)
+	.map(Into::into)
+	.map_err(Into::into)
+}
+
+/// Drop a key from the agent by fingerprint; `false` if it wasn't held.
+#[uniffi::export]
+pub fn agent_remove_key(fingerprint: String) -> bool {
+	fressh_core::agent_remove_key(&fingerprint)
+}
+
+#[uniffi::export]
+pub fn agent_remove_all() {
+	fressh_core::agent_remove_all();
+}
+
+#[uniffi::export]
+pub fn agent_list_keys() -> Vec<AgentKeyInfo> {
+	fressh_core::agent_list_keys()
+		.into_iter()
+		.map(Into::into)
+		.collect()
+}
+
+/// Resume a parked agent signature: `allow` lets the key sign this once.
+#[uniffi::export]
+pub fn respond_to_agent_sign(request_id: String, allow: bool) {
+	fressh_core::respond_to_agent_sign(&request_id, allow);
+}
+
+/// Open a PTY + shell, returning the new shell id (render with
+/// `<Terminal shellId=…/>`) and any `env` the server refused.
 #[uniffi::export(async_runtime = "tokio")]
-pub async fn start_shell(connection_id: String, options: ShellOptions) -> Result<String, SshError> {
+pub async fn start_shell(
+	connection_id: String,
+	options: ShellOptions,
+) -> Result<StartedShell, SshError> {
 	let cols = options.cols as usize;
 	let rows = options.rows as usize;
 	let scrollback = options.scrollback_lines as usize;
@@ -325,12 +1063,43 @@ pub async fn start_shell(connection_id: String, options: ShellOptions) -> Result
 		}),
 		terminal_pixel_size: None,
 		shell_integration: options.shell_integration.unwrap_or(true),
+		report_env: options.report_env,
+		// Sorted, so the requests (and a reconnect's) go out in a stable order.
+		env: options
+			.env
+			.into_iter()
+			.collect::<BTreeMap<_, _>>()
+			.into_iter()
+			.collect(),
+		command: options.command,
 	};
 	fressh_core::start_shell(connection_id, core_opts, cols, rows, scrollback)
 		.await
+		.map(Into::into)
 		.map_err(Into::into)
 }
 
+/// Start a mosh shell through a connection (which runs `server`, default
+/// `mosh-server`), returning the new shell id. Only `cols`/`rows`/
+/// `scrollback_lines` of `options` apply: the remote side picks the rest.
+/// Fails on a connection through a jump host or proxy.
+#[uniffi::export(async_runtime = "tokio", default(server = None))]
+pub async fn start_mosh_shell(
+	connection_id: String,
+	options: ShellOptions,
+	server: Option<String>,
+) -> Result<String, SshError> {
+	fressh_core::start_mosh_shell(
+		connection_id,
+		server,
+		options.cols as usize,
+		options.rows as usize,
+		options.scrollback_lines as usize,
+	)
+	.await
+	.map_err(Into::into)
+}
+
 /// Output of a one-off [`run_command`]: stdout/stderr decoded UTF-8-lossy (for
 /// display) + the exit code (absent if killed by a signal).
 #[derive(uniffi::Record)]
@@ -351,7 +1120,8 @@ impl From<fressh_core::CommandOutput> for CommandResult {
 
 /// Run a one-off command on an existing connection without a PTY/shell. The
 /// interactive shell (if any) is untouched. Note: runs in the login/home dir — an
-/// `exec` channel does NOT inherit a live shell's cwd (use `cd … && …` if needed).
+/// `exec` channel does NOT inherit a live shell's cwd (see
+/// [`run_command_in_shell_context`]).
 #[uniffi::export(async_runtime = "tokio")]
 pub async fn run_command(
 	connection_id: String,
@@ -363,6 +1133,683 @@ pub async fn run_command(
 		.map_err(Into::into)
 }
 
+/// [`run_command`] in a shell's latest cwd, with the variables it reported
+/// (`ShellOptions.report_env`) exported. Needs shell integration (or the
+/// user's own OSC 7); a shell that reported nothing runs it in the login dir.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn run_command_in_shell_context(
+	shell_id: String,
+	command: String,
+) -> Result<CommandResult, SshError> {
+	fressh_core::run_command_in_shell_context(shell_id, command)
+		.await
+		.map(Into::into)
+		.map_err(Into::into)
+}
+
+/// Mirrors `fressh_core::StreamKind`.
+#[derive(uniffi::Enum)]
+pub enum StreamKind {
+	Stdout,
+	Stderr,
+}
+impl From<fressh_core::StreamKind> for StreamKind {
+	fn from(s: fressh_core::StreamKind) -> Self {
+		match s {
+			fressh_core::StreamKind::Stdout => StreamKind::Stdout,
+			fressh_core::StreamKind::Stderr => StreamKind::Stderr,
+		}
+	}
+}
+
+/// Mirrors `fressh_core::ExecSignal`.
+#[derive(uniffi::Enum)]
+pub enum ExecSignal {
+	Int,
+	Term,
+	Kill,
+}
+impl From<ExecSignal> for fressh_core::ExecSignal {
+	fn from(s: ExecSignal) -> Self {
+		match s {
+			ExecSignal::Int => fressh_core::ExecSignal::Int,
+			ExecSignal::Term => fressh_core::ExecSignal::Term,
+			ExecSignal::Kill => fressh_core::ExecSignal::Kill,
+		}
+	}
+}
+
+/// Mirrors `fressh_core::ExecOptions`. Unset = no limit.
+#[derive(uniffi::Record)]
+pub struct ExecOptions {
+	#[uniffi(default)]
+	pub max_output_bytes: Option<u64>,
+	#[uniffi(default)]
+	pub timeout_ms: Option<u32>,
+}
+impl From<ExecOptions> for fressh_core::ExecOptions {
+	fn from(o: ExecOptions) -> Self {
+		fressh_core::ExecOptions {
+			max_output_bytes: o.max_output_bytes,
+			timeout_ms: o.timeout_ms,
+		}
+	}
+}
+
+/// Start a command whose output streams back as `ExecOutput` events, returning
+/// its `exec_id`; it ends with `ExecExited`. Same cwd caveat as [`run_command`].
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn start_exec(
+	connection_id: String,
+	command: String,
+	options: ExecOptions,
+) -> Result<String, SshError> {
+	fressh_core::start_exec(connection_id, command, options.into())
+		.await
+		.map_err(Into::into)
+}
+
+/// Write to a streaming command's stdin.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn exec_write(exec_id: String, data: Vec<u8>) -> Result<(), SshError> {
+	fressh_core::exec_write(exec_id, data)
+		.await
+		.map_err(Into::into)
+}
+
+/// Close a streaming command's stdin.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn exec_eof(exec_id: String) -> Result<(), SshError> {
+	fressh_core::exec_eof(exec_id).await.map_err(Into::into)
+}
+
+/// Signal a streaming command (the server may ignore it; `exec_close` won't be).
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn exec_signal(exec_id: String, signal: ExecSignal) -> Result<(), SshError> {
+	fressh_core::exec_signal(exec_id, signal.into())
+		.await
+		.map_err(Into::into)
+}
+
+/// Close a streaming command's channel; `ExecExited` follows.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn exec_close(exec_id: String) -> Result<(), SshError> {
+	fressh_core::exec_close(exec_id).await.map_err(Into::into)
+}
+
+// ─────────────────────────── port forwarding ───────────────────────────
+
+/// What a forward does. Mirrors `fressh_core::ForwardSpec`.
+#[derive(uniffi::Enum)]
+pub enum ForwardSpec {
+	Local {
+		bind_host: String,
+		bind_port: u16,
+		remote_host: String,
+		remote_port: u16,
+	},
+	Remote {
+		bind_host: String,
+		bind_port: u16,
+		local_host: String,
+		local_port: u16,
+	},
+	Dynamic {
+		bind_host: String,
+		bind_port: u16,
+	},
+}
+impl From<fressh_core::ForwardSpec> for ForwardSpec {
+	fn from(s: fressh_core::ForwardSpec) -> Self {
+		match s {
+			fressh_core::ForwardSpec::Local {
+				bind_host,
+				bind_port,
+				remote_host,
+				remote_port,
+			} => ForwardSpec::Local {
+				bind_host,
+				bind_port,
+				remote_host,
+				remote_port,
+			},
+			fressh_core::ForwardSpec::Remote {
+				bind_host,
+				bind_port,
+				local_host,
+				local_port,
+			} => ForwardSpec::Remote {
+				bind_host,
+				bind_port,
+				local_host,
+				local_port,
+			},
+			fressh_core::ForwardSpec::Dynamic {
+				bind_host,
+				bind_port,
+			} => ForwardSpec::Dynamic {
+				bind_host,
+				bind_port,
+			},
+		}
+	}
+}
+
+/// A forward plus its live traffic counters (`out` = device → server).
+#[derive(uniffi::Record)]
+pub struct ForwardInfo {
+	pub forward_id: String,
+	pub connection_id: String,
+	pub spec: ForwardSpec,
+	pub bound_port: u16,
+	pub bytes_out: u64,
+	pub bytes_in: u64,
+	pub active_streams: u64,
+	pub total_streams: u64,
+}
+impl From<fressh_core::ForwardInfo> for ForwardInfo {
+	fn from(i: fressh_core::ForwardInfo) -> Self {
+		ForwardInfo {
+			forward_id: i.forward_id,
+			connection_id: i.connection_id,
+			spec: i.spec.into(),
+			bound_port: i.bound_port,
+			bytes_out: i.bytes_out,
+			bytes_in: i.bytes_in,
+			active_streams: i.active_streams,
+			total_streams: i.total_streams,
+		}
+	}
+}
+
+/// Start a local port forward (`-L`): listen on `bind_host:bind_port` on the
+/// device, forward each socket to `remote_host:remote_port` via the server.
+/// `bind_port` 0 picks a free port (returned as `bound_port`).
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn start_local_forward(
+	connection_id: String,
+	bind_host: String,
+	bind_port: u16,
+	remote_host: String,
+	remote_port: u16,
+) -> Result<ForwardInfo, SshError> {
+	fressh_core::start_local_forward(
+		connection_id,
+		bind_host,
+		bind_port,
+		remote_host,
+		remote_port,
+	)
+	.await
+	.map(Into::into)
+	.map_err(Into::into)
+}
+
+/// Start a remote port forward (`-R`): the server listens on
+/// `bind_host:bind_port` and each connection is forwarded to
+/// `local_host:local_port` as seen from the device. `bind_port` 0 lets the server
+/// pick (returned as `bound_port`). `stop_forward` cancels it server-side.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn request_remote_forward(
+	connection_id: String,
+	bind_host: String,
+	bind_port: u16,
+	local_host: String,
+	local_port: u16,
+) -> Result<ForwardInfo, SshError> {
+	fressh_core::request_remote_forward(connection_id, bind_host, bind_port, local_host, local_port)
+		.await
+		.map(Into::into)
+		.map_err(Into::into)
+}
+
+/// Start a dynamic forward (`-D`): a SOCKS5/SOCKS4a proxy on
+/// `bind_host:bind_port` whose connections go out via the server. `bind_port` 0
+/// picks a free port (returned as `bound_port`).
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn start_dynamic_forward(
+	connection_id: String,
+	bind_host: String,
+	bind_port: u16,
+) -> Result<ForwardInfo, SshError> {
+	fressh_core::start_dynamic_forward(connection_id, bind_host, bind_port)
+		.await
+		.map(Into::into)
+		.map_err(Into::into)
+}
+
+/// Mirrors `fressh_core::NegotiatedAlgorithms`. MACs are absent under an AEAD
+/// cipher.
+#[derive(uniffi::Record)]
+pub struct NegotiatedAlgorithms {
+	pub kex: String,
+	pub host_key: String,
+	pub cipher: String,
+	pub mac_client_to_server: Option<String>,
+	pub mac_server_to_client: Option<String>,
+	pub compression_client_to_server: String,
+	pub compression_server_to_client: String,
+}
+impl From<fressh_core::NegotiatedAlgorithms> for NegotiatedAlgorithms {
+	fn from(a: fressh_core::NegotiatedAlgorithms) -> Self {
+		Self {
+			kex: a.kex,
+			host_key: a.host_key,
+			cipher: a.cipher,
+			mac_client_to_server: a.mac_client_to_server,
+			mac_server_to_client: a.mac_server_to_client,
+			compression_client_to_server: a.compression_client_to_server,
+			compression_server_to_client: a.compression_server_to_client,
+		}
+	}
+}
+
+/// An open connection, for a connection-details view.
+#[derive(uniffi::Record)]
+pub struct ConnectionInfo {
+	pub host: String,
+	pub port: u16,
+	pub username: String,
+	pub local_port: u16,
+	pub created_at_ms: f64,
+	pub connected_at_ms: f64,
+	/// Absent if the handshake couldn't be read back.
+	pub algorithms: Option<NegotiatedAlgorithms>,
+}
+
+/// Details of an open connection, including its negotiated algorithms.
+/// `None` for an unknown id.
+#[uniffi::export]
+pub fn connection_info(connection_id: String) -> Option<ConnectionInfo> {
+	fressh_core::connection_info(&connection_id).map(|info| ConnectionInfo {
+		host: info.details.host,
+		port: info.details.port,
+		username: info.details.username,
+		local_port: info.local_port,
+		created_at_ms: info.created_at_ms,
+		connected_at_ms: info.connected_at_ms,
+		algorithms: info.algorithms.map(Into::into),
+	})
+}
+
+/// The forwards open on a connection, with their live traffic counters.
+#[uniffi::export]
+pub fn list_forwards(connection_id: String) -> Vec<ForwardInfo> {
+	fressh_core::list_forwards(&connection_id)
+		.into_iter()
+		.map(Into::into)
+		.collect()
+}
+
+/// Stop a forward (also happens automatically on `disconnect`). For `-R` this
+/// sends `cancel-tcpip-forward`.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn stop_forward(forward_id: String) -> Result<(), SshError> {
+	fressh_core::stop_forward(forward_id)
+		.await
+		.map_err(Into::into)
+}
+
+// ─────────────────────────── SFTP ───────────────────────────
+
+/// Mirrors `fressh_core::SftpFileType`.
+#[derive(uniffi::Enum)]
+pub enum SftpFileType {
+	File,
+	Directory,
+	Symlink,
+	Other,
+}
+impl From<fressh_core::SftpFileType> for SftpFileType {
+	fn from(t: fressh_core::SftpFileType) -> Self {
+		match t {
+			fressh_core::SftpFileType::File => SftpFileType::File,
+			fressh_core::SftpFileType::Directory => SftpFileType::Directory,
+			fressh_core::SftpFileType::Symlink => SftpFileType::Symlink,
+			fressh_core::SftpFileType::Other => SftpFileType::Other,
+		}
+	}
+}
+
+/// File attributes as far as the server reported them. `permissions` holds
+/// the mode bits only; times are unix seconds.
+#[derive(uniffi::Record)]
+pub struct SftpStat {
+	pub file_type: SftpFileType,
+	pub size: Option<u64>,
+	pub permissions: Option<u32>,
+	pub uid: Option<u32>,
+	pub gid: Option<u32>,
+	pub atime: Option<u32>,
+	pub mtime: Option<u32>,
+}
+impl From<fressh_core::SftpStat> for SftpStat {
+	fn from(s: fressh_core::SftpStat) -> Self {
+		SftpStat {
+			file_type: s.file_type.into(),
+			size: s.size,
+			permissions: s.permissions,
+			uid: s.uid,
+			gid: s.gid,
+			atime: s.atime,
+			mtime: s.mtime,
+		}
+	}
+}
+
+/// A directory entry; `stat` describes a symlink itself, not its target.
+#[derive(uniffi::Record)]
+pub struct SftpEntry {
+	pub name: String,
+	pub path: String,
+	pub stat: SftpStat,
+}
+impl From<fressh_core::SftpEntry> for SftpEntry {
+	fn from(e: fressh_core::SftpEntry) -> Self {
+		SftpEntry {
+			name: e.name,
+			path: e.path,
+			stat: e.stat.into(),
+		}
+	}
+}
+
+/// Start an SFTP session on a connection, returning its `sftp_id`. Fails with
+/// `SshError::Sftp` if the server has no `sftp` subsystem.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn open_sftp(connection_id: String) -> Result<String, SshError> {
+	fressh_core::open_sftp(connection_id)
+		.await
+		.map_err(Into::into)
+}
+
+/// End an SFTP session (also happens automatically on `disconnect`).
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn close_sftp(sftp_id: String) -> Result<(), SshError> {
+	fressh_core::close_sftp(sftp_id).await.map_err(Into::into)
+}
+
+/// The absolute form of `path`; `"."` is the login directory.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn sftp_realpath(sftp_id: String, path: String) -> Result<String, SshError> {
+	fressh_core::sftp_realpath(sftp_id, path)
+		.await
+		.map_err(Into::into)
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn sftp_read_dir(sftp_id: String, path: String) -> Result<Vec<SftpEntry>, SshError> {
+	fressh_core::sftp_read_dir(sftp_id, path)
+		.await
+		.map(|entries| entries.into_iter().map(Into::into).collect())
+		.map_err(Into::into)
+}
+
+/// Attributes of `path`, following symlinks.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn sftp_stat(sftp_id: String, path: String) -> Result<SftpStat, SshError> {
+	fressh_core::sftp_stat(sftp_id, path)
+		.await
+		.map(Into::into)
+		.map_err(Into::into)
+}
+
+/// Attributes of `path` itself, even if it is a symlink.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn sftp_lstat(sftp_id: String, path: String) -> Result<SftpStat, SshError> {
+	fressh_core::sftp_lstat(sftp_id, path)
+		.await
+		.map(Into::into)
+		.map_err(Into::into)
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn sftp_mkdir(sftp_id: String, path: String) -> Result<(), SshError> {
+	fressh_core::sftp_mkdir(sftp_id, path)
+		.await
+		.map_err(Into::into)
+}
+
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn sftp_rename(sftp_id: String, from: String, to: String) -> Result<(), SshError> {
+	fressh_core::sftp_rename(sftp_id, from, to)
+		.await
+		.map_err(Into::into)
+}
+
+/// Remove a file, symlink or empty directory.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn sftp_remove(sftp_id: String, path: String) -> Result<(), SshError> {
+	fressh_core::sftp_remove(sftp_id, path)
+		.await
+		.map_err(Into::into)
+}
+
+/// Create a symlink at `link_path` pointing to `target`.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn sftp_symlink(
+	sftp_id: String,
+	target: String,
+	link_path: String,
+) -> Result<(), SshError> {
+	fressh_core::sftp_symlink(sftp_id, target, link_path)
+		.await
+		.map_err(Into::into)
+}
+
+/// Read up to `len` bytes of `path` from `offset`; shorter only at EOF.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn sftp_read(
+	sftp_id: String,
+	path: String,
+	offset: u64,
+	len: u32,
+) -> Result<Vec<u8>, SshError> {
+	fressh_core::sftp_read(sftp_id, path, offset, len)
+		.await
+		.map_err(Into::into)
+}
+
+/// Write `data` into `path` at `offset`, creating it if needed. `truncate`
+/// empties the file first.
+#[uniffi::export(async_runtime = "tokio")]
+pub async fn sftp_write(
+	sftp_id: String,
+	path: String,
+	offset: u64,
+	data: Vec<u8>,
+	truncate: bool,
+) -> Result<(), SshError> {
+	fressh_core::sftp_write(sftp_id, path, offset, data, truncate)
+		.await
+		.map_err(Into::into)
+}
+
+// ─────────────────────────── file transfers ───────────────────────────
+
+/// Mirrors `fressh_core::TransferDirection`.
+#[derive(uniffi::Enum)]
+pub enum TransferDirection {
+	Download,
+	Upload,
+}
+impl From<fressh_core::TransferDirection> for TransferDirection {
+	fn from(d: fressh_core::TransferDirection) -> Self {
+		match d {
+			fressh_core::TransferDirection::Download => TransferDirection::Download,
+			fressh_core::TransferDirection::Upload => TransferDirection::Upload,
+		}
+	}
+}
+
+/// Mirrors `fressh_core::TransferState`.
+#[derive(uniffi::Enum)]
+pub enum TransferState {
+	Queued,
+	Running,
+	Paused,
+}
+impl From<fressh_core::TransferState> for TransferState {
+	fn from(s: fressh_core::TransferState) -> Self {
+		match s {
+			fressh_core::TransferState::Queued => TransferState::Queued,
+			fressh_core::TransferState::Running => TransferState::Running,
+			fressh_core::TransferState::Paused => TransferState::Paused,
+		}
+	}
+}
+
+/// Mirrors `fressh_core::ScpOptions` (`scp -r` / `-p`).
+#[derive(uniffi::Record)]
+pub struct ScpOptions {
+	#[uniffi(default)]
+	pub recursive: bool,
+	#[uniffi(default)]
+	pub preserve: bool,
+}
+impl From<ScpOptions> for fressh_core::ScpOptions {
+	fn from(o: ScpOptions) -> Self {
+		fressh_core::ScpOptions {
+			recursive: o.recursive,
+			preserve: o.preserve,
+		}
+	}
+}
+impl From<fressh_core::ScpOptions> for ScpOptions {
+	fn from(o: fressh_core::ScpOptions) -> Self {
+		ScpOptions {
+			recursive: o.recursive,
+			preserve: o.preserve,
+		}
+	}
+}
+
+/// Mirrors `fressh_core::TransferVia`.
+#[derive(uniffi::Enum)]
+pub enum TransferVia {
+	Sftp {
+		sftp_id: String,
+	},
+	Scp {
+		connection_id: String,
+		options: ScpOptions,
+	},
+}
+impl From<fressh_core::TransferVia> for TransferVia {
+	fn from(v: fressh_core::TransferVia) -> Self {
+		match v {
+			fressh_core::TransferVia::Sftp { sftp_id } => TransferVia::Sftp { sftp_id },
+			fressh_core::TransferVia::Scp {
+				connection_id,
+				options,
+			} => TransferVia::Scp {
+				connection_id,
+				options: options.into(),
+			},
+		}
+	}
+}
+
+/// A transfer still in the queue (queued, running or paused).
+#[derive(uniffi::Record)]
+pub struct TransferInfo {
+	pub transfer_id: String,
+	pub via: TransferVia,
+	pub direction: TransferDirection,
+	pub remote_path: String,
+	pub local_path: String,
+	pub state: TransferState,
+	pub bytes: u64,
+	pub total: Option<u64>,
+}
+impl From<fressh_core::TransferInfo> for TransferInfo {
+	fn from(t: fressh_core::TransferInfo) -> Self {
+		TransferInfo {
+			transfer_id: t.transfer_id,
+			via: t.via.into(),
+			direction: t.direction.into(),
+			remote_path: t.remote_path,
+			local_path: t.local_path,
+			state: t.state.into(),
+			bytes: t.bytes,
+			total: t.total,
+		}
+	}
+}
+
+/// Queue a download of `remote_path` to the device file `local_path` (a path,
+/// not a `file://` URI), returning its `transfer_id`. The outcome arrives as
+/// `TransferFinished` / `TransferFailed`.
+#[uniffi::export]
+pub fn start_download(
+	sftp_id: String,
+	remote_path: String,
+	local_path: String,
+) -> Result<String, SshError> {
+	fressh_core::start_download(sftp_id, remote_path, local_path).map_err(Into::into)
+}
+
+/// Queue an upload of the device file `local_path` to `remote_path`.
+#[uniffi::export]
+pub fn start_upload(
+	sftp_id: String,
+	local_path: String,
+	remote_path: String,
+) -> Result<String, SshError> {
+	fressh_core::start_upload(sftp_id, local_path, remote_path).map_err(Into::into)
+}
+
+/// Queue a download over `scp` on the connection, for hosts without SFTP. An
+/// existing directory at `local_path` receives it under its remote name.
+/// Resuming an SCP transfer starts it over.
+#[uniffi::export]
+pub fn start_scp_download(
+	connection_id: String,
+	remote_path: String,
+	local_path: String,
+	options: ScpOptions,
+) -> Result<String, SshError> {
+	fressh_core::start_scp_download(connection_id, remote_path, local_path, options.into())
+		.map_err(Into::into)
+}
+
+/// Queue an upload over `scp`; see [`start_scp_download`].
+#[uniffi::export]
+pub fn start_scp_upload(
+	connection_id: String,
+	local_path: String,
+	remote_path: String,
+	options: ScpOptions,
+) -> Result<String, SshError> {
+	fressh_core::start_scp_upload(connection_id, local_path, remote_path, options.into())
+		.map_err(Into::into)
+}
+
+#[uniffi::export]
+pub fn list_transfers() -> Vec<TransferInfo> {
+	fressh_core::list_transfers()
+		.into_iter()
+		.map(Into::into)
+		.collect()
+}
+
+#[uniffi::export]
+pub fn pause_transfer(transfer_id: String) -> Result<(), SshError> {
+	fressh_core::pause_transfer(&transfer_id).map_err(Into::into)
+}
+
+/// Continue a paused transfer from what the destination already holds.
+#[uniffi::export]
+pub fn resume_transfer(transfer_id: String) -> Result<(), SshError> {
+	fressh_core::resume_transfer(&transfer_id).map_err(Into::into)
+}
+
+/// Stop and forget a transfer (no event follows).
+#[uniffi::export]
+pub fn cancel_transfer(transfer_id: String) {
+	fressh_core::cancel_transfer(&transfer_id);
+}
+
 /// Create a non-SSH preview shell fed a canned snippet, bound by `previewId`.
 /// Render it with `<Terminal shellId={previewId} />` — the live config still flows
 /// through, so it reflows as Terminal settings change. Tear down with
@@ -454,6 +1901,55 @@ pub async fn close_shell(shell_id: String) -> Result<(), SshError> {
 	fressh_core::close_shell(shell_id).await.map_err(Into::into)
 }
 
+/// Mirrors `fressh_core::ShellCloseReason`.
+#[derive(uniffi::Enum)]
+pub enum ShellCloseReason {
+	RemoteExit,
+	Eof,
+	LocalClose,
+	ConnectionLost,
+}
+impl From<fressh_core::ShellCloseReason> for ShellCloseReason {
+	fn from(r: fressh_core::ShellCloseReason) -> Self {
+		match r {
+			fressh_core::ShellCloseReason::RemoteExit => ShellCloseReason::RemoteExit,
+			fressh_core::ShellCloseReason::Eof => ShellCloseReason::Eof,
+			fressh_core::ShellCloseReason::LocalClose => ShellCloseReason::LocalClose,
+			fressh_core::ShellCloseReason::ConnectionLost => ShellCloseReason::ConnectionLost,
+		}
+	}
+}
+
+/// Mirrors `fressh_core::ShellEnd`.
+#[derive(uniffi::Record)]
+pub struct ShellEnd {
+	pub reason: ShellCloseReason,
+	pub exit_code: Option<i32>,
+	pub exit_signal: Option<String>,
+	pub core_dumped: bool,
+	pub error_message: Option<String>,
+	pub ended_at_ms: f64,
+}
+impl From<fressh_core::ShellEnd> for ShellEnd {
+	fn from(e: fressh_core::ShellEnd) -> Self {
+		ShellEnd {
+			reason: e.reason.into(),
+			exit_code: e.exit_code,
+			exit_signal: e.exit_signal,
+			core_dumped: e.core_dumped,
+			error_message: e.error_message,
+			ended_at_ms: e.ended_at_ms,
+		}
+	}
+}
+
+/// How a closed shell ended, as its `ShellClosed` said. Kept for the most
+/// recent few dozen closed shells.
+#[uniffi::export]
+pub fn shell_end(shell_id: String) -> Option<ShellEnd> {
+	fressh_core::shell_end(&shell_id).map(Into::into)
+}
+
 /// Disconnect a connection (closes its shells first).
 #[uniffi::export(async_runtime = "tokio")]
 pub async fn disconnect(connection_id: String) -> Result<(), SshError> {
@@ -462,14 +1958,53 @@ pub async fn disconnect(connection_id: String) -> Result<(), SshError> {
 		.map_err(Into::into)
 }
 
-/// Generate a new key pair (OpenSSH private-key string).
+/// Generate a new key pair (OpenSSH private-key string), encrypted under
+/// `passphrase` if given.
+#[uniffi::export(default(passphrase = None))]
+pub fn generate_key_pair(
+	key_type: KeyType,
+	passphrase: Option<String>,
+) -> Result<String, SshError> {
+	fressh_core::generate_key_pair(key_type.into(), passphrase.as_deref()).map_err(Into::into)
+}
+
+/// Import an `ssh_config` in bulk: every concrete `Host` alias, resolved.
+/// `includes` maps paths (relative ones are under `~/.ssh/`) to the files
+/// `Include` may pull in.
 #[uniffi::export]
-pub fn generate_key_pair(key_type: KeyType) -> Result<String, SshError> {
-	fressh_core::generate_key_pair(key_type.into()).map_err(Into::into)
+pub fn parse_ssh_config(
+	config: String,
+	includes: HashMap<String, String>,
+) -> Result<Vec<SshConfigHost>, SshError> {
+	fressh_core::parse_ssh_config(&config, &includes)
+		.map(|hosts| hosts.into_iter().map(Into::into).collect())
+		.map_err(Into::into)
 }
 
-/// Validate a private key; returns its canonical OpenSSH form.
+/// Resolve one alias against an `ssh_config`, to connect by alias.
 #[uniffi::export]
-pub fn validate_private_key(pem: String) -> Result<String, SshError> {
-	fressh_core::validate_private_key(&pem).map_err(Into::into)
+pub fn resolve_ssh_config_host(
+	config: String,
+	includes: HashMap<String, String>,
+	alias: String,
+) -> Result<SshConfigHost, SshError> {
+	fressh_core::resolve_ssh_config_host(&config, &includes, &alias)
+		.map(Into::into)
+		.map_err(Into::into)
+}
+
+/// Describe an OpenSSH certificate (`*-cert.pub`) — e.g. to warn before
+/// `valid_before` (unix seconds; `u64::MAX` = forever).
+#[uniffi::export]
+pub fn inspect_certificate(certificate: String) -> Result<CertificateInfo, SshError> {
+	fressh_core::inspect_certificate(&certificate)
+		.map(Into::into)
+		.map_err(Into::into)
+}
+
+/// Validate a private key (an encrypted one needs its `passphrase`); returns
+/// its canonical OpenSSH form, still encrypted if the input was.
+#[uniffi::export(default(passphrase = None))]
+pub fn validate_private_key(pem: String, passphrase: Option<String>) -> Result<String, SshError> {
+	fressh_core::validate_private_key(&pem, passphrase.as_deref()).map_err(Into::into)
 }
@@ -14,17 +14,46 @@ export type { TerminalMethods, TerminalProps } from '../nitro/Terminal.nitro';
 // Control plane (§10): connect/shell lifecycle + key helpers + the event stream.
 export {
 	addFresshEventListener,
+	agentAddKey,
+	agentListKeys,
+	agentRemoveAll,
+	agentRemoveKey,
+	cancelTransfer,
+	CertificateType,
 	closePreviewTerm,
+	closeSftp,
 	closeShell,
 	connect,
+	connectionInfo,
 	createPreviewTerm,
 	disconnect,
+	execClose,
+	execEof,
+	execSignal,
+	ExecSignal,
+	execWrite,
+	ForwardSpec,
 	FresshEvent_Tags,
 	generateKeyPair,
+	HostKeyStatus,
+	inspectCertificate,
+	IpPreference,
 	KeyType,
+	listForwards,
+	listTransfers,
+	openSftp,
+	pauseTransfer,
+	parseSshConfig,
+	ProxyKind,
+	requestRemoteForward,
 	resize,
+	resolveSshConfigHost,
+	resumeTransfer,
+	respondToAgentSign,
+	respondToAuthPrompt,
 	respondToHostKey,
 	runCommand,
+	runCommandInShellContext,
 	scroll,
 	Security,
 	selectionClear,
@@ -33,20 +62,75 @@ export {
 	selectionText,
 	selectionUpdate,
 	sendData,
+	setKnownHosts,
+	SftpFileType,
+	sftpLstat,
+	sftpMkdir,
+	sftpRead,
+	sftpReadDir,
+	sftpRealpath,
+	sftpRemove,
+	sftpRename,
+	sftpStat,
+	sftpSymlink,
+	sftpWrite,
+	ShellCloseReason,
+	shellEnd,
 	SshConnectionProgressEvent,
+	SshConnectionProgressEvent_Tags,
 	SshError_Tags,
+	startDownload,
+	startDynamicForward,
+	startExec,
+	startScpDownload,
+	startScpUpload,
+	startLocalForward,
+	startMoshShell,
 	startShell,
+	startUpload,
+	stopForward,
+	StreamKind,
 	TerminalType,
+	TransferDirection,
+	TransferState,
+	TransferVia_Tags,
 	validatePrivateKey,
 } from './ssh';
 export type {
+	AgentKeyInfo,
+	AgentSignRequest,
+	AlgorithmPreferences,
+	AuthPrompt,
+	AuthPromptRequest,
+	CertificateInfo,
 	CommandResult,
+	ConnectConfig,
+	ConnectTimeouts,
 	ConnectionDetails,
 	ConnectionId,
+	ConnectionInfo,
+	ExecId,
 	FresshEvent,
 	FresshEventCallback,
 	FresshEventListener,
+	ForwardId,
+	ForwardInfo,
+	KeepaliveConfig,
+	NegotiatedAlgorithms,
+	ReconnectConfig,
+	ScpOptions,
 	ServerPublicKeyInfo,
+	SftpEntry,
+	SftpId,
+	SftpStat,
+	ShellEnd,
 	ShellId,
 	ShellOptions,
+	SshConfigHop,
+	SshConfigHost,
+	StartedShell,
+	TransferId,
+	TransferInfo,
+	TransferVia,
+	UpstreamProxy,
 } from './ssh';
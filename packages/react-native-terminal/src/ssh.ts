@@ -11,62 +11,180 @@
 
 import { NativeModules, TurboModuleRegistry } from 'react-native';
 import generatedModule, {
+	cancelTransfer as _cancelTransfer,
+	agentAddKey as _agentAddKey,
+	agentListKeys as _agentListKeys,
+	agentRemoveAll as _agentRemoveAll,
+	agentRemoveKey as _agentRemoveKey,
 	closePreview as _closePreview,
+	closeSftp as _closeSftp,
 	closeShell as _closeShell,
 	connect as _connect,
+	connectionInfo as _connectionInfo,
 	createPreview as _createPreview,
 	disconnect as _disconnect,
+	execClose as _execClose,
+	execEof as _execEof,
+	execSignal as _execSignal,
+	execWrite as _execWrite,
 	generateKeyPair as _generateKeyPair,
+	inspectCertificate as _inspectCertificate,
+	listForwards as _listForwards,
+	listTransfers as _listTransfers,
+	openSftp as _openSftp,
+	pauseTransfer as _pauseTransfer,
+	parseSshConfig as _parseSshConfig,
+	requestRemoteForward as _requestRemoteForward,
 	resize as _resize,
+	resolveSshConfigHost as _resolveSshConfigHost,
+	resumeTransfer as _resumeTransfer,
+	respondToAgentSign as _respondToAgentSign,
+	respondToAuthPrompt as _respondToAuthPrompt,
 	respondToHostKey as _respondToHostKey,
 	scroll as _scroll,
 	selectionClear as _selectionClear,
 	selectionStart as _selectionStart,
 	runCommand as _runCommand,
+	runCommandInShellContext as _runCommandInShellContext,
 	selectionText as _selectionText,
 	selectionUpdate as _selectionUpdate,
 	sendData as _sendData,
 	setEventListener as _setEventListener,
+	setKnownHosts as _setKnownHosts,
+	sftpLstat as _sftpLstat,
+	sftpMkdir as _sftpMkdir,
+	sftpRead as _sftpRead,
+	sftpReadDir as _sftpReadDir,
+	sftpRealpath as _sftpRealpath,
+	sftpRemove as _sftpRemove,
+	sftpRename as _sftpRename,
+	sftpStat as _sftpStat,
+	sftpSymlink as _sftpSymlink,
+	sftpWrite as _sftpWrite,
+	shellEnd as _shellEnd,
+	startDownload as _startDownload,
+	startDynamicForward as _startDynamicForward,
+	startExec as _startExec,
+	startScpDownload as _startScpDownload,
+	startScpUpload as _startScpUpload,
+	startLocalForward as _startLocalForward,
+	startMoshShell as _startMoshShell,
 	startShell as _startShell,
+	startUpload as _startUpload,
+	stopForward as _stopForward,
 	validatePrivateKey as _validatePrivateKey,
+	type AgentKeyInfo,
+	type AgentSignRequest,
+	type AlgorithmPreferences,
+	type AuthPrompt,
+	type AuthPromptRequest,
+	type CertificateInfo,
+	CertificateType,
 	type CommandResult,
+	type ConnectConfig,
+	type ConnectTimeouts,
 	type ConnectionDetails,
+	type ConnectionInfo,
+	ExecSignal,
 	type FresshEvent,
 	FresshEvent_Tags,
 	type FresshEventListener,
+	type ForwardInfo,
+	ForwardSpec,
+	HostKeyStatus,
+	IpPreference,
+	type KeepaliveConfig,
 	KeyType,
+	type NegotiatedAlgorithms,
+	ProxyKind,
+	type ReconnectConfig,
 	SelectionKind,
 	type ServerPublicKeyInfo,
 	Security,
+	type SftpEntry,
+	SftpFileType,
+	type SftpStat,
+	ShellCloseReason,
+	type ShellEnd,
 	type ShellOptions,
+	type SshConfigHop,
+	type SshConfigHost,
 	SshConnectionProgressEvent,
+	SshConnectionProgressEvent_Tags,
 	SshError_Tags,
+	type StartedShell,
+	StreamKind,
 	TerminalType,
+	TransferDirection,
+	type TransferInfo,
+	TransferState,
+	type TransferVia,
+	TransferVia_Tags,
+	type UpstreamProxy,
 } from './generated/shim_uniffi';
 
 // Re-export the generated enums/factories (values) the app needs to construct
 // inputs and match events.
 export {
+	CertificateType,
+	ExecSignal,
+	ForwardSpec,
 	FresshEvent_Tags,
+	HostKeyStatus,
+	IpPreference,
 	KeyType,
+	ProxyKind,
 	Security,
 	SelectionKind,
+	SftpFileType,
+	ShellCloseReason,
 	SshConnectionProgressEvent,
+	SshConnectionProgressEvent_Tags,
 	SshError_Tags,
+	StreamKind,
 	TerminalType,
+	TransferDirection,
+	TransferState,
+	TransferVia_Tags,
 };
 // Records + the event union are plain object types — re-export as types.
 export type {
+	AgentKeyInfo,
+	AgentSignRequest,
+	AlgorithmPreferences,
+	AuthPrompt,
+	AuthPromptRequest,
+	CertificateInfo,
 	CommandResult,
+	ConnectConfig,
+	ConnectTimeouts,
 	ConnectionDetails,
+	ConnectionInfo,
 	FresshEvent,
 	FresshEventListener,
+	ForwardInfo,
+	KeepaliveConfig,
+	NegotiatedAlgorithms,
+	ReconnectConfig,
 	ServerPublicKeyInfo,
+	SftpEntry,
+	SftpStat,
+	ShellEnd,
 	ShellOptions,
+	SshConfigHop,
+	SshConfigHost,
+	StartedShell,
+	TransferInfo,
+	TransferVia,
+	UpstreamProxy,
 };
 
 export type ConnectionId = string;
 export type ShellId = string;
+export type ForwardId = string;
+export type ExecId = string;
+export type SftpId = string;
+export type TransferId = string;
 
 // ─────────────────────────── native install ───────────────────────────
 
@@ -108,34 +226,134 @@ ensureNativeInstalled();
 
 // ─────────────────────────── control plane ───────────────────────────
 
-/** Connect + authenticate. Resolves to a `connectionId`. A `HostKeyPending`
- *  event fires mid-handshake — answer it with {@link respondToHostKey}. */
-export const connect = (details: ConnectionDetails): Promise<ConnectionId> =>
-	_connect(details);
+/** Connect + authenticate, tunnelled through `jumpHosts` in order (ProxyJump;
+ *  empty = direct). Resolves to a `connectionId`. A `HostKeyPending` event fires
+ *  mid-handshake for every hop — answer each with {@link respondToHostKey}.
+ *  Keyboard-interactive hops fire `AuthPromptPending` per round — answer with
+ *  {@link respondToAuthPrompt}. `config.keepalive` turns on the health monitor
+ *  (`ConnectionDegraded` / `ConnectionRecovered` / `ConnectionLost`). */
+export const connect = (
+	details: ConnectionDetails,
+	jumpHosts: ConnectionDetails[] = [],
+	config?: ConnectConfig,
+): Promise<ConnectionId> => _connect(details, jumpHosts, config);
 
 export const disconnect = (connectionId: ConnectionId): Promise<void> =>
 	_disconnect(connectionId);
 
+/** Details of an open connection, including the negotiated kex/cipher/MAC/
+ *  host-key/compression algorithms. `undefined` for an unknown id. */
+export const connectionInfo = (
+	connectionId: ConnectionId,
+): ConnectionInfo | undefined => _connectionInfo(connectionId);
+
 export const respondToHostKey = (
 	connectionId: ConnectionId,
 	accept: boolean,
 ): void => _respondToHostKey(connectionId, accept);
 
-/** Open a PTY + shell. Resolves to a `shellId` — render it with
- *  `<Terminal shellId={shellId} />`. */
+/** Install the app's `known_hosts` text (OpenSSH syntax, incl. hashed names
+ *  and `@revoked`). `@cert-authority` lines are skipped and not counted: host
+ *  certificates aren't supported, so they can't vouch for a server.
+ *  Trusted keys then connect without a `HostKeyPending`; revoked ones are
+ *  refused. Pending events carry a `status` of `Unknown` or `Changed`.
+ *  Returns the entries understood. */
+export const setKnownHosts = (text: string): number => _setKnownHosts(text);
+
+/** Answer a keyboard-interactive round: one answer per prompt, in order, or
+ *  `undefined` to cancel. */
+export const respondToAuthPrompt = (
+	connectionId: ConnectionId,
+	answers: string[] | undefined,
+): void => _respondToAuthPrompt(connectionId, answers);
+
+// `ShellOptions` with the natively defaulted fields left optional.
+type ShellOptionsInit = Omit<ShellOptions, 'reportEnv' | 'env' | 'command'> &
+	Partial<Pick<ShellOptions, 'reportEnv' | 'env' | 'command'>>;
+
+const withShellDefaults = (options: ShellOptionsInit): ShellOptions => ({
+	reportEnv: [],
+	env: new Map(),
+	command: undefined,
+	...options,
+});
+
+/** Open a PTY + shell. Resolves to `{ shellId, rejectedEnv }` — render it
+ *  with `<Terminal shellId={shellId} />`. `reportEnv` names variables the
+ *  shell integration reports back (e.g. `VIRTUAL_ENV`) for
+ *  {@link runCommandInShellContext}. `env` is set in the shell (`LANG`,
+ *  `LC_*`, …); `rejectedEnv` lists what the server's `AcceptEnv` refused,
+ *  which the shell integration then exports itself (without it, they stay
+ *  unset). With a `command` (`tmux attach`, `htop`) the PTY runs that instead
+ *  of a prompt, and `ShellClosed` carries its exit in `end`. */
 export const startShell = (
 	connectionId: ConnectionId,
-	options: ShellOptions,
-): Promise<ShellId> => _startShell(connectionId, options);
+	options: ShellOptionsInit,
+): Promise<StartedShell> =>
+	_startShell(connectionId, withShellDefaults(options));
+
+/** Start a mosh shell: the connection runs `mosh-server` (or `server`), then
+ *  the shell talks UDP to the same host and rides out network changes on its
+ *  own. Renders like any shell; only the size/scrollback `options` apply.
+ *  Needs a direct connection, not one through a jump host or proxy. */
+export const startMoshShell = (
+	connectionId: ConnectionId,
+	options: ShellOptionsInit,
+	server?: string,
+): Promise<ShellId> =>
+	_startMoshShell(connectionId, withShellDefaults(options), server);
 
 /** Run a one-off command on an existing connection without opening a PTY/shell.
  *  Resolves to `{ stdout, stderr, exitCode }`. Runs in the login/home dir — an
- *  `exec` channel does NOT inherit a live shell's cwd (use `cd … && …` if needed). */
+ *  `exec` channel does NOT inherit a live shell's cwd — see
+ *  {@link runCommandInShellContext}. */
 export const runCommand = (
 	connectionId: ConnectionId,
 	command: string,
 ): Promise<CommandResult> => _runCommand(connectionId, command);
 
+/** Like {@link runCommand}, but in the shell's latest working directory, with
+ *  the variables it reported (`reportEnv`) exported. Relies on shell
+ *  integration (or the user's own OSC 7); a shell that reported nothing runs
+ *  the command in the login dir. */
+export const runCommandInShellContext = (
+	shellId: ShellId,
+	command: string,
+): Promise<CommandResult> => _runCommandInShellContext(shellId, command);
+
+/** Start a command whose output streams back as `ExecOutput` events (coalesced,
+ *  a few per second at most) instead of being collected; it ends with one
+ *  `ExecExited`. Past `maxOutputBytes` of output or `timeoutMs`, the command is
+ *  sent TERM and closed. Same cwd caveat as {@link runCommand}. */
+export const startExec = (
+	connectionId: ConnectionId,
+	command: string,
+	options: { maxOutputBytes?: number; timeoutMs?: number } = {},
+): Promise<ExecId> =>
+	_startExec(connectionId, command, {
+		maxOutputBytes:
+			options.maxOutputBytes === undefined
+				? undefined
+				: BigInt(options.maxOutputBytes),
+		timeoutMs: options.timeoutMs,
+	});
+
+/** Write to a streaming command's stdin. */
+export const execWrite = (execId: ExecId, data: ArrayBuffer): Promise<void> =>
+	_execWrite(execId, data);
+
+/** Close a streaming command's stdin (end of input). */
+export const execEof = (execId: ExecId): Promise<void> => _execEof(execId);
+
+/** Send `Int`/`Term`/`Kill`. Servers may ignore signals; {@link execClose}
+ *  always ends the command's channel. */
+export const execSignal = (
+	execId: ExecId,
+	signal: ExecSignal,
+): Promise<void> => _execSignal(execId, signal);
+
+export const execClose = (execId: ExecId): Promise<void> => _execClose(execId);
+
 /** Send user input (stdin). Also reachable on the render plane (the view forwards
  *  key/IME input straight to native), so most apps won't call this directly. */
 export const sendData = (shellId: ShellId, data: ArrayBuffer): Promise<void> =>
@@ -150,6 +368,221 @@ export const resize = (
 export const closeShell = (shellId: ShellId): Promise<void> =>
 	_closeShell(shellId);
 
+/** How a closed shell ended — `reason`, exit code or signal — as its
+ *  `ShellClosed` event said, for a "session ended" banner. Kept for the most
+ *  recent few dozen closed shells; `undefined` otherwise. */
+export const shellEnd = (shellId: ShellId): ShellEnd | undefined =>
+	_shellEnd(shellId);
+
+// ─────────────────────────── port forwarding ───────────────────────────
+// Forwards live in the native registry like shells: the bytes stay native, JS
+// gets `Forward*` lifecycle events and pulls counters via `listForwards`.
+
+/** Local forward (`-L`): listen on `bindHost:bindPort` on the device, forward
+ *  each socket to `remoteHost:remotePort` via the server. Port 0 = pick one. */
+export const startLocalForward = (
+	connectionId: ConnectionId,
+	bindHost: string,
+	bindPort: number,
+	remoteHost: string,
+	remotePort: number,
+): Promise<ForwardInfo> =>
+	_startLocalForward(connectionId, bindHost, bindPort, remoteHost, remotePort);
+
+/** Remote forward (`-R`): the server listens on `bindHost:bindPort`, each
+ *  connection is forwarded to `localHost:localPort` from the device. */
+export const requestRemoteForward = (
+	connectionId: ConnectionId,
+	bindHost: string,
+	bindPort: number,
+	localHost: string,
+	localPort: number,
+): Promise<ForwardInfo> =>
+	_requestRemoteForward(connectionId, bindHost, bindPort, localHost, localPort);
+
+/** Dynamic forward (`-D`): a SOCKS5/SOCKS4a proxy on `bindHost:bindPort` whose
+ *  connections go out via the server. */
+export const startDynamicForward = (
+	connectionId: ConnectionId,
+	bindHost: string,
+	bindPort: number,
+): Promise<ForwardInfo> => _startDynamicForward(connectionId, bindHost, bindPort);
+
+/** The forwards open on a connection, with live traffic counters. */
+export const listForwards = (connectionId: ConnectionId): ForwardInfo[] =>
+	_listForwards(connectionId);
+
+/** Stop a forward (also automatic on `disconnect`). Cancels `-R` server-side. */
+export const stopForward = (forwardId: ForwardId): Promise<void> =>
+	_stopForward(forwardId);
+
+// ─────────────────────────── sftp ───────────────────────────
+// An SFTP session is a registry entry like a forward: open it once per file
+// browser, address it by `sftpId`, and it ends with `closeSftp` or its
+// connection (`SftpClosed`). Paths are the server's (POSIX).
+
+/** Start an SFTP session. Throws `SshError.Sftp` if the server has no `sftp`
+ *  subsystem. */
+export const openSftp = (connectionId: ConnectionId): Promise<SftpId> =>
+	_openSftp(connectionId);
+
+export const closeSftp = (sftpId: SftpId): Promise<void> => _closeSftp(sftpId);
+
+/** The absolute form of `path`; `'.'` is the login directory. */
+export const sftpRealpath = (sftpId: SftpId, path: string): Promise<string> =>
+	_sftpRealpath(sftpId, path);
+
+/** A directory's entries (no `.`/`..`), in server order. */
+export const sftpReadDir = (
+	sftpId: SftpId,
+	path: string,
+): Promise<SftpEntry[]> => _sftpReadDir(sftpId, path);
+
+/** Attributes of `path`, following symlinks. */
+export const sftpStat = (sftpId: SftpId, path: string): Promise<SftpStat> =>
+	_sftpStat(sftpId, path);
+
+/** Attributes of `path` itself, even if it is a symlink. */
+export const sftpLstat = (sftpId: SftpId, path: string): Promise<SftpStat> =>
+	_sftpLstat(sftpId, path);
+
+export const sftpMkdir = (sftpId: SftpId, path: string): Promise<void> =>
+	_sftpMkdir(sftpId, path);
+
+export const sftpRename = (
+	sftpId: SftpId,
+	from: string,
+	to: string,
+): Promise<void> => _sftpRename(sftpId, from, to);
+
+/** Remove a file, symlink or empty directory. */
+export const sftpRemove = (sftpId: SftpId, path: string): Promise<void> =>
+	_sftpRemove(sftpId, path);
+
+/** Create a symlink at `linkPath` pointing to `target`. */
+export const sftpSymlink = (
+	sftpId: SftpId,
+	target: string,
+	linkPath: string,
+): Promise<void> => _sftpSymlink(sftpId, target, linkPath);
+
+/** Read up to `length` bytes of `path` from `offset` (shorter only at EOF).
+ *  Meant for chunks the app shows or edits — not for bulk transfers. */
+export const sftpRead = (
+	sftpId: SftpId,
+	path: string,
+	offset: number,
+	length: number,
+): Promise<ArrayBuffer> =>
+	_sftpRead(sftpId, path, BigInt(offset), length);
+
+/** Write `data` into `path` at `offset`, creating it if needed. `truncate`
+ *  empties the file first (pass it with the first chunk of a replacement). */
+export const sftpWrite = (
+	sftpId: SftpId,
+	path: string,
+	offset: number,
+	data: ArrayBuffer,
+	truncate = false,
+): Promise<void> => _sftpWrite(sftpId, path, BigInt(offset), data, truncate);
+
+// ─────────────────────────── file transfers ───────────────────────────
+// Bulk copies between a device file and the server, run natively — the bytes
+// never cross into JS. Up to three run at once; the rest queue. Watch
+// `TransferProgress` (throttled) and `TransferFinished` / `TransferFailed`.
+
+// Native file APIs want a plain path; Expo hands out `file://` URIs.
+const toPath = (uri: string): string =>
+	uri.startsWith('file://') ? decodeURI(uri.slice('file://'.length)) : uri;
+
+/** Download `remotePath` to the device file `localPath` (replaced). */
+export const startDownload = (
+	sftpId: SftpId,
+	remotePath: string,
+	localPath: string,
+): TransferId => _startDownload(sftpId, remotePath, toPath(localPath));
+
+/** Upload the device file `localPath` to `remotePath` (replaced). */
+export const startUpload = (
+	sftpId: SftpId,
+	localPath: string,
+	remotePath: string,
+): TransferId => _startUpload(sftpId, toPath(localPath), remotePath);
+
+/** `scp -r` (directories) and `-p` (modes and times). */
+export type ScpOptions = { recursive?: boolean; preserve?: boolean };
+
+/** Download `remotePath` over `scp`, for hosts without SFTP. An existing
+ *  directory at `localPath` receives it under its remote name. Resuming an
+ *  SCP transfer starts it over. */
+export const startScpDownload = (
+	connectionId: ConnectionId,
+	remotePath: string,
+	localPath: string,
+	options: ScpOptions = {},
+): TransferId =>
+	_startScpDownload(connectionId, remotePath, toPath(localPath), {
+		recursive: options.recursive ?? false,
+		preserve: options.preserve ?? false,
+	});
+
+/** Upload the device file (or with `recursive`, directory) `localPath` to
+ *  `remotePath` over `scp`. */
+export const startScpUpload = (
+	connectionId: ConnectionId,
+	localPath: string,
+	remotePath: string,
+	options: ScpOptions = {},
+): TransferId =>
+	_startScpUpload(connectionId, toPath(localPath), remotePath, {
+		recursive: options.recursive ?? false,
+		preserve: options.preserve ?? false,
+	});
+
+/** Transfers queued, running or paused, with exact byte counts. */
+export const listTransfers = (): TransferInfo[] => _listTransfers();
+
+export const pauseTransfer = (transferId: TransferId): void =>
+	_pauseTransfer(transferId);
+
+/** Continue a paused transfer from what already reached the destination. */
+export const resumeTransfer = (transferId: TransferId): void =>
+	_resumeTransfer(transferId);
+
+/** Stop and forget a transfer. No event follows; a partial file stays. */
+export const cancelTransfer = (transferId: TransferId): void =>
+	_cancelTransfer(transferId);
+
+// ─────────────────────────── ssh agent ───────────────────────────
+// One in-process agent for the whole app. Connections opened with
+// `config.forwardAgent` serve its keys to the target host (never to jump hosts).
+
+/** Hold a private key in the agent. With `confirm`, each signature first fires
+ *  `AgentSignPending` — answer with {@link respondToAgentSign}. Re-adding the
+ *  same key replaces it. */
+export const agentAddKey = (
+	pem: string,
+	options: { passphrase?: string; comment?: string; confirm?: boolean } = {},
+): AgentKeyInfo =>
+	_agentAddKey(
+		pem,
+		options.passphrase,
+		options.comment,
+		options.confirm ?? false,
+	);
+
+/** Drop a key by its SHA256 fingerprint. `false` if it wasn't held. */
+export const agentRemoveKey = (fingerprint: string): boolean =>
+	_agentRemoveKey(fingerprint);
+
+export const agentRemoveAll = (): void => _agentRemoveAll();
+
+export const agentListKeys = (): AgentKeyInfo[] => _agentListKeys();
+
+/** Allow or refuse one parked agent signature (`AgentSignPending.requestId`). */
+export const respondToAgentSign = (requestId: string, allow: boolean): void =>
+	_respondToAgentSign(requestId, allow);
+
 // ─────────────────────────── preview (non-SSH `Term`) ─────────────────────────
 // A `Term` driven by a canned byte snippet instead of an SSH channel — the
 // foundation for the Terminal-settings live preview (and, later, an on-device
@@ -168,11 +601,36 @@ export const createPreviewTerm = (
 export const closePreviewTerm = (previewId: ShellId): Promise<void> =>
 	_closePreview(previewId);
 
-export const generateKeyPair = (keyType: KeyType): string =>
-	_generateKeyPair(keyType);
+/** Generate an OpenSSH private key, encrypted under `passphrase` if given. */
+export const generateKeyPair = (keyType: KeyType, passphrase?: string): string =>
+	_generateKeyPair(keyType, passphrase);
+
+/** Describe an OpenSSH certificate (`*-cert.pub`): principals, validity window
+ *  (unix seconds), CA fingerprint — e.g. to warn before it expires. */
+export const inspectCertificate = (certificate: string): CertificateInfo =>
+	_inspectCertificate(certificate);
+
+/** Import a desktop `ssh_config`: every concrete `Host` alias, resolved
+ *  (HostName, Port, User, IdentityFile paths, ProxyJump chain, ServerAlive*).
+ *  `includes` maps paths (relative = under `~/.ssh/`) to files `Include` may
+ *  pull in. Throws `SshError.Config` on malformed input. */
+export const parseSshConfig = (
+	config: string,
+	includes: Map<string, string> = new Map(),
+): SshConfigHost[] => _parseSshConfig(config, includes);
+
+/** Resolve one alias (or bare hostname) against an `ssh_config`, to connect by
+ *  alias: `host.details` (plus credentials) through `host.jumpHosts`. */
+export const resolveSshConfigHost = (
+	config: string,
+	alias: string,
+	includes: Map<string, string> = new Map(),
+): SshConfigHost => _resolveSshConfigHost(config, includes, alias);
 
-export const validatePrivateKey = (pem: string): string =>
-	_validatePrivateKey(pem);
+/** Canonicalize a private key. An encrypted key needs its `passphrase` (else
+ *  throws `PassphraseRequired` / `WrongPassphrase`) and stays encrypted. */
+export const validatePrivateKey = (pem: string, passphrase?: string): string =>
+	_validatePrivateKey(pem, passphrase);
 
 // ─────────────────────── touch interaction (scroll + selection) ──────────────
 // Touch gestures live in JS (cross-platform), but the terminal logic lives in